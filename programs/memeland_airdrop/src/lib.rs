@@ -1,14 +1,65 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::ed25519_program;
 use anchor_lang::solana_program::keccak;
-use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
+use anchor_lang::solana_program::sysvar::instructions::{
+    self as instructions_sysvar, load_current_index_checked, load_instruction_at_checked,
+};
+use anchor_lang::system_program;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer, TransferChecked};
 
 declare_id!("CoRoXM3uPR9Mm9ES8nggW2KGnfJdGBJHh49uq7As8gaq");
 
 // ── Constants ──────────────────────────────────────────────────────────────────
 
 pub const TOTAL_DAYS: u64 = 20;
+/// Capacity of the per-day arrays in PoolState; `resize_pool_state` can grow a program up to this
+pub const MAX_PROGRAM_DAYS: u64 = 32;
 pub const CLAIM_WINDOW_DAYS: u64 = 40;
+/// Latest `initialize_pool` accepts a start_time, relative to now (180 days)
+pub const MAX_START_DELAY_SECONDS: i64 = 180 * SECONDS_PER_DAY as i64;
+/// Delay between `initiate_termination` and `execute_drain` (48 hours)
+pub const TIMELOCK_SECONDS: i64 = 172_800;
+/// Upper bound for `extend_claim_window` (keeps deadline arithmetic far from overflow)
+pub const MAX_CLAIM_WINDOW_DAYS: u64 = 365;
+/// Longest merkle proof accepted: a tree of up to 2^24 leaves (a claim transaction cannot
+/// carry many more proof nodes anyway)
+pub const MAX_PROOF_DEPTH: usize = 24;
+/// Most claims `claim_airdrop_batch` processes per call (compute and transaction size)
+pub const MAX_BATCH_CLAIMS: usize = 4;
+/// Most days `calculate_rewards_range` returns per call (keeps return data under 1 KiB)
+pub const REWARDS_PAGE_SIZE: u64 = 16;
 pub const SECONDS_PER_DAY: u64 = 86400;
+pub const SECONDS_PER_YEAR: u64 = 365 * SECONDS_PER_DAY;
+pub const BPS_DENOMINATOR: u64 = 10_000;
+/// Fixed-point scale for summing day shares, so fractions of a token unit carry across days
+pub const REWARD_SCALE: u128 = 1_000_000_000_000;
+
+/// Reward modes: each day's denominator is that day's snapshot, or the larger of it and the previous day's
+pub const REWARD_MODE_POINT_IN_TIME: u8 = 0;
+pub const REWARD_MODE_AVERAGED: u8 = 1;
+
+/// Merkle leaf schemes: keccak(user || amount), or keccak(prefix || index || user || amount)
+pub const MERKLE_SCHEME_LEGACY: u8 = 0;
+pub const MERKLE_SCHEME_INDEXED: u8 = 1;
+
+/// Shapes `initialize_pool` can check `daily_rewards` against: none, every day equal, or a
+/// constant step between days (days must ascend, so a decaying curve can never pass)
+pub const REWARD_CURVE_NONE: u8 = 0;
+pub const REWARD_CURVE_CONSTANT: u8 = 1;
+pub const REWARD_CURVE_LINEAR: u8 = 2;
+/// Allowed deviation from the curve, in bps of the mean daily reward
+pub const REWARD_CURVE_TOLERANCE_BPS: u16 = 10;
+/// Domain separator for indexed leaves (internal nodes hash 64 bytes with no prefix)
+pub const MERKLE_LEAF_PREFIX: &[u8] = &[0x00];
+
+/// Lockup tiers chosen at claim time; each weights the stake by a configured multiplier
+pub const LOCK_TIERS: usize = 4;
+/// Upper bound for a tier multiplier (5x)
+pub const MAX_LOCK_MULTIPLIER_BPS: u16 = 50_000;
+
+/// Current UserStake layout; `migrate_user_stake` upgrades accounts written by older layouts
+pub const USER_STAKE_VERSION: u8 = 3;
 
 /// Airdrop pool: 67_000_000 tokens × 10^9 (9 decimals)
 pub const AIRDROP_POOL: u64 = 67_000_000_000_000_000;
@@ -18,6 +69,10 @@ pub const STAKING_POOL: u64 = 133_000_000_000_000_000;
 
 pub const INIT_AUTHORITY: Pubkey = pubkey!("65mxnibS4DL2qqL24GpMJqtNxgEzWgnARTMvXv5SePUb");
 
+/// Metaplex Token Metadata program (owner of NFT metadata accounts checked by the NFT gate)
+pub const TOKEN_METADATA_PROGRAM_ID: Pubkey =
+    pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
 // ── Seeds ──────────────────────────────────────────────────────────────────────
 
 /// PDA seed constants for consistent usage across the program
@@ -26,6 +81,51 @@ pub mod seeds {
     pub const POOL_TOKEN: &[u8] = b"pool_token";
     pub const USER_STAKE: &[u8] = b"user_stake";
     pub const CLAIMED: &[u8] = b"claimed";
+    pub const BLACKLIST: &[u8] = b"blacklist";
+    pub const APPROVAL: &[u8] = b"approval";
+    pub const CRANK: &[u8] = b"crank";
+    pub const REWARD_POOL_TOKEN: &[u8] = b"reward_pool_token";
+    /// Metaplex metadata PDA prefix (derived under TOKEN_METADATA_PROGRAM_ID)
+    pub const METADATA: &[u8] = b"metadata";
+}
+
+/// Destructive actions gated by admin approvals (the action id is part of the approval PDA seeds)
+pub mod actions {
+    pub const RECOVER: u8 = 0;
+    pub const TERMINATE: u8 = 1;
+    pub const DRAIN: u8 = 2;
+}
+
+/// Pool phases reported by `get_phase`
+pub mod phases {
+    /// Before `start_time`
+    pub const NOT_STARTED: u8 = 0;
+    /// Program days: rewards accrue and snapshots are taken
+    pub const ACTIVE: u8 = 1;
+    /// After the program, until the claim window ends: claims and exits only
+    pub const EXIT_WINDOW: u8 = 2;
+    /// Claim window over: the admin can recover the surplus
+    pub const EXPIRED: u8 = 3;
+}
+
+/// Reason codes carried by `SnapshotSkipped`
+pub mod skip_reasons {
+    /// Every day up to today already has a snapshot
+    pub const ALREADY_RECORDED: u8 = 0;
+    /// The program is over and its last day is recorded
+    pub const PROGRAM_ENDED: u8 = 1;
+}
+
+/// Bits of the status byte returned by `get_flags`
+pub mod pool_flags {
+    /// Paused at the current time (a lapsed timed pause reads as unpaused)
+    pub const PAUSED: u8 = 1 << 0;
+    /// `initiate_termination` has run
+    pub const TERMINATED: u8 = 1 << 1;
+    /// The PoolState exists; always set, so a zero byte means the read itself failed
+    pub const INITIALIZED: u8 = 1 << 2;
+    /// `activate_pool` has run
+    pub const ACTIVE: u8 = 1 << 3;
 }
 
 // ── Program ────────────────────────────────────────────────────────────────────
@@ -40,12 +140,18 @@ pub mod memeland_airdrop {
         start_time: i64,
         merkle_root: [u8; 32],
         daily_rewards: [u64; 20],
+        config: PoolConfig,
     ) -> Result<()> {
         let clock = Clock::get()?;
         require!(
             start_time > clock.unix_timestamp,
             ErrorCode::StartTimeInPast
         );
+        require!(
+            start_time <= clock.unix_timestamp + MAX_START_DELAY_SECONDS,
+            ErrorCode::StartTimeTooFar
+        );
+        config.validate()?;
 
         let pool = &mut ctx.accounts.pool_state;
         pool.admin = ctx.accounts.admin.key();
@@ -59,6 +165,19 @@ pub mod memeland_airdrop {
         pool.paused = 0;
         pool.bump = ctx.bumps.pool_state;
         pool.pool_token_bump = ctx.bumps.pool_token_account;
+        // The pool token account signs for itself (pool_token_signer_seeds), so it must be
+        // its own authority and its stored bump must derive it
+        require_keys_eq!(
+            ctx.accounts.pool_token_account.owner,
+            pool.pool_token_account,
+            ErrorCode::InvalidPoolTokenAccount
+        );
+        verify_pool_bumps(pool, &pool.key(), ctx.program_id)?;
+        pool.config = config;
+        pool.claim_window_days = CLAIM_WINDOW_DAYS;
+        pool.program_days = TOTAL_DAYS as u8;
+        pool.staking_pool = STAKING_POOL;
+        pool.staking_budget_remaining = STAKING_POOL;
 
         // Validate that the supplied daily rewards sum to exactly STAKING_POOL
         // AND ensure ascending order
@@ -74,11 +193,14 @@ pub mod memeland_airdrop {
             pool.daily_rewards[d] = daily_rewards[d];
         }
         require!(sum == STAKING_POOL, ErrorCode::InvalidDailyRewards);
+        verify_reward_curve(&daily_rewards, pool.config.reward_curve)?;
 
         emit!(PoolInitialized {
             admin: pool.admin,
             token_mint: pool.token_mint,
             start_time: pool.start_time,
+            merkle_root: pool.merkle_root,
+            timestamp: clock.unix_timestamp,
         });
 
         msg!(
@@ -91,55 +213,130 @@ pub mod memeland_airdrop {
 
     /// Claim airdrop via merkle proof. Tokens are sent directly to user wallet.
     /// Creates a permanent ClaimMarker (prevents re-claims) and a UserStake for reward tracking (closed on unstake).
+    /// `index` is the allocation's position in the allowlist (ignored by the legacy leaf scheme).
     pub fn claim_airdrop(
         ctx: Context<ClaimAirdrop>,
         amount: u64,
         proof: Vec<[u8; 32]>,
+        index: u64,
+        lock_tier: u8,
     ) -> Result<()> {
         let pool_state_key = ctx.accounts.pool_state.key();
         let pool = &mut ctx.accounts.pool_state;
         let clock = Clock::get()?;
 
-        require!(pool.paused == 0, ErrorCode::PoolPaused);
-        require!(
-            clock.unix_timestamp > pool.start_time,
-            ErrorCode::PoolNotStartedYet
-        );
-
-        // Determine which day the user is claiming on
-        let current_day = get_current_day(pool.start_time, clock.unix_timestamp);
-
-        // Block claims after the claim window ends (day 40+)
-        require!(current_day < CLAIM_WINDOW_DAYS, ErrorCode::StakingPeriodEnded);
+        let current_day = validate_claim(
+            pool,
+            !ctx.accounts.blacklist_entry.data_is_empty(),
+            amount,
+            clock.unix_timestamp,
+        )?;
 
         // Verify merkle proof
-        let user_bytes = ctx.accounts.user.key().to_bytes();
-        let amount_bytes = amount.to_le_bytes();
-        let leaf = keccak::hashv(&[user_bytes.as_ref(), amount_bytes.as_ref()]);
+        let leaf = compute_leaf(
+            pool.config.merkle_scheme_version,
+            index,
+            &ctx.accounts.user.key(),
+            amount,
+        );
         require!(
-            verify_merkle_proof(&proof, &pool.merkle_root, &leaf.0),
+            verify_merkle_proof(&proof, &pool.merkle_root, &leaf)?,
             ErrorCode::InvalidMerkleProof
         );
+        verify_nft_gate(
+            &pool.config,
+            &ctx.accounts.user.key(),
+            &ctx.accounts.nft_token_account,
+            &ctx.accounts.nft_metadata,
+        )?;
 
         // Initialize claim marker (prevents re-claiming after unstake)
         let claim_marker = &mut ctx.accounts.claim_marker;
         claim_marker.bump = ctx.bumps.claim_marker;
 
-        // Initialize user stake
         let user_stake = &mut ctx.accounts.user_stake;
-        user_stake.owner = ctx.accounts.user.key();
-        user_stake.staked_amount = amount;
-        user_stake.bump = ctx.bumps.user_stake;
+        record_claim(
+            pool,
+            user_stake,
+            ctx.accounts.user.key(),
+            ctx.bumps.user_stake,
+            amount,
+            lock_tier,
+            clock.unix_timestamp,
+        )?;
+
+        // Send airdrop tokens to user via pool PDA signer
+        transfer_from_pool_pda(
+            &ctx.accounts.token_program,
+            &ctx.accounts.pool_token_account,
+            &ctx.accounts.user_token_account,
+            &pool_state_key,
+            pool.pool_token_bump,
+            amount,
+        )?;
+
+        emit!(AirdropClaimed {
+            user: user_stake.owner,
+            amount,
+            claim_day: current_day,
+            timestamp: clock.unix_timestamp,
+        });
+
+        msg!(
+            "Airdrop claimed and staked: {} tokens for {}, claim_day={}",
+            amount,
+            user_stake.owner,
+            current_day
+        );
+        Ok(())
+    }
+
+    /// Claim airdrop with a voucher signed by `config.claim_signer` instead of a merkle proof.
+    /// The transaction must carry an ed25519 program instruction, immediately before this one,
+    /// verifying the signer's signature over `voucher_message(pool_state, user, amount, expiry)`.
+    /// Shares the ClaimMarker with `claim_airdrop`, so each user claims once across both paths.
+    pub fn claim_airdrop_signed(
+        ctx: Context<ClaimAirdropSigned>,
+        amount: u64,
+        expiry: i64,
+        lock_tier: u8,
+    ) -> Result<()> {
+        let pool_state_key = ctx.accounts.pool_state.key();
+        let pool = &mut ctx.accounts.pool_state;
+        let clock = Clock::get()?;
 
-        pool.total_airdrop_claimed = pool.total_airdrop_claimed.checked_add(amount).unwrap();
-        pool.active_stakers = pool.active_stakers.checked_add(1).unwrap();
+        let current_day = validate_claim(
+            pool,
+            !ctx.accounts.blacklist_entry.data_is_empty(),
+            amount,
+            clock.unix_timestamp,
+        )?;
 
         require!(
-            pool.total_airdrop_claimed <= AIRDROP_POOL,
-            ErrorCode::AirdropPoolExhausted
+            pool.config.claim_signer != Pubkey::default(),
+            ErrorCode::InvalidVoucher
         );
+        require!(clock.unix_timestamp <= expiry, ErrorCode::VoucherExpired);
+        verify_ed25519_voucher(
+            &ctx.accounts.instructions_sysvar,
+            &pool.config.claim_signer,
+            &voucher_message(&pool_state_key, &ctx.accounts.user.key(), amount, expiry),
+        )?;
+
+        let claim_marker = &mut ctx.accounts.claim_marker;
+        claim_marker.bump = ctx.bumps.claim_marker;
+
+        let user_stake = &mut ctx.accounts.user_stake;
+        record_claim(
+            pool,
+            user_stake,
+            ctx.accounts.user.key(),
+            ctx.bumps.user_stake,
+            amount,
+            lock_tier,
+            clock.unix_timestamp,
+        )?;
 
-        // Send airdrop tokens to user via pool PDA signer
         transfer_from_pool_pda(
             &ctx.accounts.token_program,
             &ctx.accounts.pool_token_account,
@@ -153,10 +350,11 @@ pub mod memeland_airdrop {
             user: user_stake.owner,
             amount,
             claim_day: current_day,
+            timestamp: clock.unix_timestamp,
         });
 
         msg!(
-            "Airdrop claimed and staked: {} tokens for {}, claim_day={}",
+            "Airdrop claimed with voucher: {} tokens for {}, claim_day={}",
             amount,
             user_stake.owner,
             current_day
@@ -164,523 +362,4844 @@ pub mod memeland_airdrop {
         Ok(())
     }
 
-    /// Anyone can call snapshot once daily (any time during the day).
-    /// Records total_staked for the current day.
-    /// Claims/unstakes are blocked until the previous day's snapshot is taken.
-    pub fn snapshot(ctx: Context<Snapshot>) -> Result<()> {
+    /// Claim airdrop via merkle proof without staking: tokens go to the user's wallet and no
+    /// UserStake is created, so the claim earns no rewards and leaves total_staked untouched.
+    /// The ClaimMarker is shared with `claim_airdrop`, so the allocation is claimed only once.
+    pub fn claim_airdrop_liquid(
+        ctx: Context<ClaimAirdropLiquid>,
+        amount: u64,
+        proof: Vec<[u8; 32]>,
+        index: u64,
+    ) -> Result<()> {
+        let pool_state_key = ctx.accounts.pool_state.key();
         let pool = &mut ctx.accounts.pool_state;
         let clock = Clock::get()?;
 
-        require!(pool.paused == 0, ErrorCode::PoolPaused);
+        let current_day = validate_claim(
+            pool,
+            !ctx.accounts.blacklist_entry.data_is_empty(),
+            amount,
+            clock.unix_timestamp,
+        )?;
 
-        // Must be at least day 1 (snapshot records the previous day's state)
-        let raw_day = get_current_day(pool.start_time, clock.unix_timestamp);
-        require!(raw_day >= 1, ErrorCode::InvalidDay);
+        let leaf = compute_leaf(
+            pool.config.merkle_scheme_version,
+            index,
+            &ctx.accounts.user.key(),
+            amount,
+        );
+        require!(
+            verify_merkle_proof(&proof, &pool.merkle_root, &leaf)?,
+            ErrorCode::InvalidMerkleProof
+        );
+        verify_nft_gate(
+            &pool.config,
+            &ctx.accounts.user.key(),
+            &ctx.accounts.nft_token_account,
+            &ctx.accounts.nft_metadata,
+        )?;
 
-        // Cap to TOTAL_DAYS for array indexing (days 0..19)
-        let snapshot_day = raw_day.min(TOTAL_DAYS);
+        ctx.accounts.claim_marker.bump = ctx.bumps.claim_marker;
 
-        let last = pool.snapshot_count as usize;
+        pool.total_liquid_claimed = pool
+            .total_liquid_claimed
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        record_airdrop_claimed(pool, amount, current_day, clock.unix_timestamp)?;
 
-        let mut wrote = false;
+        transfer_from_pool_pda(
+            &ctx.accounts.token_program,
+            &ctx.accounts.pool_token_account,
+            &ctx.accounts.user_token_account,
+            &pool_state_key,
+            pool.pool_token_bump,
+            amount,
+        )?;
 
-        // fill ONLY missing days
-        for d in last..(snapshot_day as usize) {
-            pool.daily_snapshots[d] = pool.total_staked;
-            wrote = true;
-        }
+        emit!(LiquidAirdropClaimed {
+            user: ctx.accounts.user.key(),
+            amount,
+            timestamp: clock.unix_timestamp,
+        });
 
-        // snapshot_count tracks the highest day snapshotted (upper bound for reward loop)
-        pool.snapshot_count = snapshot_day as u8;
+        msg!(
+            "Airdrop claimed liquid: {} tokens for {}",
+            amount,
+            ctx.accounts.user.key()
+        );
+        Ok(())
+    }
 
-        if wrote {
-            emit!(SnapshotTaken {
-                day: snapshot_day,
-                total_staked: pool.total_staked,
-            });
-            msg!(
-                "Snapshot {} recorded: total_staked = {}",
-                snapshot_day,
-                pool.total_staked
-            );
-        } else {
-            msg!("No snapshots needed for today.");
-        }
+    /// Claim airdrop like `claim_airdrop`, recording `referrer` on the stake. On unstake the
+    /// referrer earns `config.referral_bps` of the claimer's rewards from `referral_budget`.
+    pub fn claim_airdrop_referred(
+        ctx: Context<ClaimAirdrop>,
+        amount: u64,
+        proof: Vec<[u8; 32]>,
+        index: u64,
+        lock_tier: u8,
+        referrer: Pubkey,
+    ) -> Result<()> {
+        require!(
+            referrer != Pubkey::default() && referrer != ctx.accounts.user.key(),
+            ErrorCode::InvalidReferrer
+        );
+        ctx.accounts.user_stake.referrer = referrer;
 
-        Ok(())
+        emit!(ReferralRecorded {
+            user: ctx.accounts.user.key(),
+            referrer,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        claim_airdrop(ctx, amount, proof, index, lock_tier)
     }
 
-    /// Unstake: permanent exit. Sends all accumulated rewards.
-    /// After claim window (day 40+), users can still unstake but receive 0 rewards.
-    /// Closes the UserStake account and returns rent to user.
-    pub fn unstake(ctx: Context<Unstake>) -> Result<()> {
+    /// Relay up to MAX_BATCH_CLAIMS merkle claims in one call. The relayer pays rent for each
+    /// ClaimMarker and UserStake; tokens go to the claimer's own token account, and stakes
+    /// use lock tier 0. Every claimer co-signs the transaction: a claim fixes the claimer's
+    /// lock tier and referrer for good, so a relayer holding public proofs must not be able
+    /// to make that choice for them. `remaining_accounts` holds, per claim in order: the
+    /// claimer (signer), the claimer's token account, ClaimMarker PDA, UserStake PDA and
+    /// Blacklist PDA. Any failing claim reverts the whole batch. Not available on NFT-gated
+    /// pools.
+    pub fn claim_airdrop_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClaimAirdropBatch<'info>>,
+        claims: Vec<BatchClaim>,
+    ) -> Result<()> {
         let pool_state_key = ctx.accounts.pool_state.key();
         let pool = &mut ctx.accounts.pool_state;
-        let user_stake = &ctx.accounts.user_stake;
         let clock = Clock::get()?;
 
-        require!(user_stake.staked_amount > 0, ErrorCode::NothingStaked);
+        require!(
+            !claims.is_empty() && claims.len() <= MAX_BATCH_CLAIMS,
+            ErrorCode::BatchTooLarge
+        );
+        require!(
+            ctx.remaining_accounts.len() == claims.len() * 5,
+            ErrorCode::InvalidBatchAccounts
+        );
+        require!(
+            pool.config.required_collection == Pubkey::default(),
+            ErrorCode::MissingRequiredNft
+        );
 
-        let expired = clock.unix_timestamp >= claim_window_end(pool.start_time);
+        for (claim, accounts) in claims.iter().zip(ctx.remaining_accounts.chunks(5)) {
+            let [claimer_info, token_info, marker_info, stake_info, blacklist_info] = accounts
+            else {
+                return err!(ErrorCode::InvalidBatchAccounts);
+            };
+            require!(claimer_info.is_signer, ErrorCode::BatchClaimerNotSigner);
+            let user = claimer_info.key();
+            let user_token_account = Account::<TokenAccount>::try_from(token_info)?;
+            require!(
+                user_token_account.mint == pool.token_mint && user_token_account.owner == user,
+                ErrorCode::InvalidBatchAccounts
+            );
 
-        let rewards = if expired {
-            // After claim window: user can still close their stake, but gets 0 rewards
-            0
-        } else {
-            // Cap to TOTAL_DAYS for snapshot comparison and reward calculation
-            let current_day = get_current_day(pool.start_time, clock.unix_timestamp)
-                .min(TOTAL_DAYS);
-            // Block unstaking if previous day's snapshot hasn't been taken yet
+            let (blacklist_key, _) = Pubkey::find_program_address(
+                &[seeds::BLACKLIST, pool_state_key.as_ref(), user.as_ref()],
+                ctx.program_id,
+            );
             require!(
-                pool.snapshot_count >= current_day as u8,
-                ErrorCode::SnapshotRequiredFirst
+                blacklist_info.key() == blacklist_key,
+                ErrorCode::InvalidBatchAccounts
             );
-            calculate_user_rewards(
-                user_stake.staked_amount,
-                current_day,
-                &pool.daily_rewards,
-                &pool.daily_snapshots,
-            )
-        };
+            let current_day = validate_claim(
+                pool,
+                !blacklist_info.data_is_empty(),
+                claim.amount,
+                clock.unix_timestamp,
+            )?;
+
+            let leaf = compute_leaf(
+                pool.config.merkle_scheme_version,
+                claim.index,
+                &user,
+                claim.amount,
+            );
+            require!(
+                verify_merkle_proof(&claim.proof, &pool.merkle_root, &leaf)?,
+                ErrorCode::InvalidMerkleProof
+            );
+
+            // ClaimMarker: always new, so a repeated claimer fails the whole batch
+            let (marker_key, marker_bump) = Pubkey::find_program_address(
+                &[seeds::CLAIMED, pool_state_key.as_ref(), user.as_ref()],
+                ctx.program_id,
+            );
+            require!(
+                marker_info.key() == marker_key && marker_info.data_is_empty(),
+                ErrorCode::InvalidBatchAccounts
+            );
+            init_pda_account(
+                &ctx.accounts.relayer,
+                marker_info,
+                &ctx.accounts.system_program,
+                8 + ClaimMarker::INIT_SPACE,
+                &[seeds::CLAIMED, pool_state_key.as_ref(), user.as_ref(), &[marker_bump]],
+            )?;
+            ClaimMarker { bump: marker_bump }
+                .try_serialize(&mut &mut marker_info.data.borrow_mut()[..])?;
+
+            // UserStake: created here unless pre-created empty by open_stake_account
+            let (stake_key, stake_bump) = Pubkey::find_program_address(
+                &[seeds::USER_STAKE, pool_state_key.as_ref(), user.as_ref()],
+                ctx.program_id,
+            );
+            require!(stake_info.key() == stake_key, ErrorCode::InvalidBatchAccounts);
+            if stake_info.data_is_empty() {
+                init_pda_account(
+                    &ctx.accounts.relayer,
+                    stake_info,
+                    &ctx.accounts.system_program,
+                    8 + UserStake::INIT_SPACE,
+                    &[seeds::USER_STAKE, pool_state_key.as_ref(), user.as_ref(), &[stake_bump]],
+                )?;
+                stake_info.data.borrow_mut()[..8].copy_from_slice(UserStake::DISCRIMINATOR);
+            }
+            let mut user_stake = Account::<UserStake>::try_from(stake_info)?;
+            record_claim(
+                pool,
+                &mut user_stake,
+                user,
+                stake_bump,
+                claim.amount,
+                0,
+                clock.unix_timestamp,
+            )?;
+            user_stake.exit(ctx.program_id)?;
 
-        // Transfer tokens via PDA signer (skip if 0 rewards)
-        if rewards > 0 {
             transfer_from_pool_pda(
                 &ctx.accounts.token_program,
                 &ctx.accounts.pool_token_account,
-                &ctx.accounts.user_token_account,
+                &user_token_account,
                 &pool_state_key,
                 pool.pool_token_bump,
-                rewards,
+                claim.amount,
             )?;
-        }
-
-        // Update pool state (UserStake account is closed by Anchor's close constraint)
-        pool.total_staked = pool
-            .total_staked
-            .checked_sub(user_stake.staked_amount)
-            .unwrap();
-        pool.active_stakers = pool.active_stakers.checked_sub(1).unwrap();
-        pool.total_unstaked = pool.total_unstaked.checked_add(1).unwrap();
 
-        emit!(Unstaked {
-            user: user_stake.owner,
-            rewards,
-        });
+            emit!(AirdropClaimed {
+                user,
+                amount: claim.amount,
+                claim_day: current_day,
+                timestamp: clock.unix_timestamp,
+            });
+        }
 
-        msg!(
-            "Unstaked: {} rewards sent to {}. UserStake account closed.",
-            rewards,
-            user_stake.owner
-        );
+        msg!("Batch of {} airdrop claims relayed", claims.len());
         Ok(())
     }
 
-    /// View function: calculate potential rewards for a user on a given day.
-    /// For past days with snapshots, uses actual values.
-    /// For future days, uses the last snapshot's total_staked.
-    /// Note: After unstake, UserStake is closed so this instruction will fail (account not found).
-    pub fn calculate_rewards(ctx: Context<CalculateRewards>, day: u64) -> Result<()> {
-        let pool = &ctx.accounts.pool_state;
-        let user_stake = &ctx.accounts.user_stake;
+    /// Pre-create an empty UserStake (staked_amount = 0) so the PDA is reserved before claiming.
+    /// A later `claim_airdrop` funds it in place; `close_empty_stake` reclaims the rent.
+    pub fn open_stake_account(ctx: Context<OpenStakeAccount>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool_state;
+        let clock = Clock::get()?;
 
-        require!(day < TOTAL_DAYS, ErrorCode::InvalidDay);
+        require!(pool.active == 1, ErrorCode::PoolNotActive);
+        require!(pool.terminated == 0, ErrorCode::PoolTerminated);
 
-        let day_idx = day as usize;
+        let current_day = pool.current_day(clock.unix_timestamp);
+        require!(current_day < pool.claim_window_days, ErrorCode::StakingPeriodEnded);
 
-        // Determine snapshot value to use
-        let snapshot_total = if (day as u8) < pool.snapshot_count {
-            // Actual snapshot exists
-            pool.daily_snapshots[day_idx]
-        } else if pool.snapshot_count > 0 {
-            // Future day: use last snapshot
-            pool.daily_snapshots[(pool.snapshot_count - 1) as usize]
-        } else {
-            // No snapshots yet: use current total_staked
-            pool.total_staked
-        };
+        let user_stake = &mut ctx.accounts.user_stake;
+        user_stake.owner = ctx.accounts.user.key();
+        user_stake.staked_amount = 0;
+        user_stake.bump = ctx.bumps.user_stake;
+        user_stake.stake_ts = clock.unix_timestamp;
+        user_stake.claim_day = current_day;
+        user_stake.version = USER_STAKE_VERSION;
 
-        let daily = pool.daily_rewards[day_idx] as u128;
-        let reward = (user_stake.staked_amount as u128)
-            .checked_mul(daily)
-            .unwrap()
-            .checked_div(snapshot_total as u128)
-            .unwrap_or(0) as u64;
+        pool.active_stakers = pool
+            .active_stakers
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
 
-        msg!("Day {} reward: {}", day, reward);
+        msg!(
+            "Stake account opened for {}, day={}",
+            user_stake.owner,
+            current_day
+        );
         Ok(())
     }
 
-    /// After claim window (day 40+), admin recovers all remaining tokens.
-    /// Since stakes are virtual (airdrop tokens were sent directly to users on claim),
-    /// total_staked represents no real token obligation — the entire balance can be drained.
-    /// Can be called again if tokens are sent to the pool after first recovery.
-    pub fn recover_expired_rewards(ctx: Context<RecoverExpiredRewards>) -> Result<()> {
-        let pool_state_key = ctx.accounts.pool_state.key();
+    /// Anyone can call snapshot once daily (any time during the day).
+    /// Records total_staked for the current day.
+    /// Claims/unstakes are blocked until the previous day's snapshot is taken.
+    /// Returns whether this call recorded anything (a later same-day crank returns wrote = false).
+    pub fn snapshot(ctx: Context<Snapshot>) -> Result<SnapshotResult> {
         let pool = &mut ctx.accounts.pool_state;
         let clock = Clock::get()?;
 
+        require!(!is_paused(pool, clock.unix_timestamp), ErrorCode::PoolPaused);
+
+        // Must be at least day 1 (snapshot records the previous day's state)
+        let raw_day = pool.current_day(clock.unix_timestamp);
+        require!(raw_day >= 1, ErrorCode::InvalidDay);
+
+        // One crank per caller per day, whether or not it writes anything
+        let crank_record = &mut ctx.accounts.crank_record;
         require!(
-            clock.unix_timestamp >= claim_window_end(pool.start_time),
-            ErrorCode::ClaimWindowStillOpen
+            crank_record.last_crank_ts == 0
+                || pool.current_day(crank_record.last_crank_ts) != raw_day,
+            ErrorCode::CrankTooSoon
         );
+        crank_record.last_crank_ts = clock.unix_timestamp;
+        crank_record.bump = ctx.bumps.crank_record;
 
-        // Drain entire balance — total_staked is virtual (no real tokens owed)
-        let pool_balance = ctx.accounts.pool_token_account.amount;
-        require!(pool_balance > 0, ErrorCode::NothingToRecover);
+        // Cap to the program length for array indexing
+        let snapshot_day = raw_day.min(pool.program_days());
+        // Guard the clamp: the fill loop indexes daily_snapshots and the count is stored as u8
+        require!(
+            snapshot_day <= pool.program_days() && snapshot_day <= MAX_PROGRAM_DAYS,
+            ErrorCode::InvalidDay
+        );
 
-        transfer_from_pool_pda(
-            &ctx.accounts.token_program,
-            &ctx.accounts.pool_token_account,
-            &ctx.accounts.admin_token_account,
-            &pool_state_key,
-            pool.pool_token_bump,
-            pool_balance,
-        )?;
+        let last = pool.snapshot_count as usize;
 
-        emit!(TokensRecovered { amount: pool_balance });
+        // A regressed validator clock must never roll snapshot_count back
+        require!(snapshot_day as usize >= last, ErrorCode::ClockRegression);
 
-        msg!("{} tokens recovered.", pool_balance);
-        Ok(())
-    }
+        // Writing during the program waits until the configured offset into the current day
+        let day_start = pool.accrual_start(clock.unix_timestamp)
+            + (raw_day * pool.config.seconds_per_day()) as i64;
+        require!(
+            snapshot_day as usize == last
+                || raw_day > pool.program_days()
+                || clock.unix_timestamp
+                    >= day_start + pool.config.snapshot_earliest_offset_seconds as i64,
+            ErrorCode::SnapshotTooEarly
+        );
 
-    /// Emergency pause - blocks claims and snapshots.
-    /// Users can still unstake to protect their funds.
-    pub fn pause_pool(ctx: Context<PausePool>) -> Result<()> {
-        let pool = &mut ctx.accounts.pool_state;
+        let mut wrote = false;
+        let previous = (last > 0 && last < snapshot_day as usize)
+            .then(|| pool.daily_snapshots[last - 1]);
+
+        // fill ONLY missing days; the bitmap (not a zero value) marks a day as recorded,
+        // so a day with a genuine zero total_staked is never rewritten
+        for d in last..(snapshot_day as usize) {
+            require!(
+                pool.snapshot_taken_bitmap & (1 << d) == 0,
+                ErrorCode::SnapshotAlreadyExists
+            );
+            pool.daily_snapshots[d] = pool.total_staked;
+            pool.snapshot_taken_bitmap |= 1 << d;
+            carry_forward_reward(pool, d)?;
+            wrote = true;
+        }
 
-        require!(pool.paused == 0, ErrorCode::AlreadyPaused);
+        // snapshot_count is the number of recorded days: 0..snapshot_count are snapshotted,
+        // so it is also the exclusive upper bound for the reward loop
+        pool.snapshot_count = pool
+            .snapshot_count
+            .max(snapshot_day as u8)
+            .min(pool.program_days() as u8);
 
-        pool.paused = 1;
+        if wrote {
+            emit!(SnapshotTaken {
+                day: snapshot_day,
+                total_staked: pool.total_staked,
+                timestamp: clock.unix_timestamp,
+            });
+            msg!(
+                "Snapshot {} recorded: total_staked = {}",
+                snapshot_day,
+                pool.total_staked
+            );
 
-        emit!(PoolPausedEvent {
-            admin: ctx.accounts.admin.key(),
-        });
+            // Circuit breaker: a large swing against the previous snapshot pauses the pool
+            let max_delta_bps = pool.config.max_daily_stake_delta_bps as u64;
+            let delta = previous.and_then(|prev| stake_delta_bps(prev, pool.total_staked));
+            if let Some(delta_bps) = delta.filter(|&d| max_delta_bps != 0 && d > max_delta_bps) {
+                pool.paused = 1;
+                pool.pause_reason = PauseReason::CircuitBreaker as u8;
+                pool.pause_expiry = 0;
+                emit!(CircuitBreakerTripped {
+                    day: snapshot_day,
+                    delta_bps,
+                    timestamp: clock.unix_timestamp,
+                });
+                msg!("Circuit breaker tripped: total_staked moved {} bps", delta_bps);
+            }
+        } else {
+            let reason_code = if raw_day > pool.program_days() {
+                skip_reasons::PROGRAM_ENDED
+            } else {
+                skip_reasons::ALREADY_RECORDED
+            };
+            emit!(SnapshotSkipped {
+                day: snapshot_day,
+                reason_code,
+                timestamp: clock.unix_timestamp,
+            });
+            msg!("No snapshots needed for today.");
+        }
 
-        msg!("Pool paused by admin: {}", ctx.accounts.admin.key());
-        Ok(())
+        Ok(SnapshotResult {
+            wrote,
+            day: snapshot_day,
+            total_staked: pool.total_staked,
+        })
     }
 
-    /// Unpause pool - resumes normal operations.
-    pub fn unpause_pool(ctx: Context<PausePool>) -> Result<()> {
+    /// Overwrite the snapshot recorded today (admin only). The crank on day D records day D - 1,
+    /// so only `day == current_day - 1 == snapshot_count - 1` qualifies, and only until day D
+    /// ends; earlier days are final. `value` must cover the open stake so a day's shares never
+    /// exceed its reward.
+    pub fn admin_set_snapshot(ctx: Context<AdminSetSnapshot>, day: u64, value: u64) -> Result<()> {
         let pool = &mut ctx.accounts.pool_state;
+        let clock = Clock::get()?;
 
-        require!(pool.paused == 1, ErrorCode::PoolNotPaused);
-
-        pool.paused = 0;
-
-        emit!(PoolUnpausedEvent {
-            admin: ctx.accounts.admin.key(),
+        let current_day = pool.current_day(clock.unix_timestamp);
+        require!(
+            day + 1 == current_day
+                && day + 1 == pool.snapshot_count as u64
+                && day < pool.program_days(),
+            ErrorCode::SnapshotNotCorrectable
+        );
+        require!(value as u128 >= open_staked(pool), ErrorCode::InvalidSnapshotValue);
+
+        let old = pool.daily_snapshots[day as usize];
+        undo_carry_forward(pool, day as usize)?;
+        pool.daily_snapshots[day as usize] = value;
+        carry_forward_reward(pool, day as usize)?;
+
+        emit!(SnapshotCorrected {
+            day,
+            old,
+            new: value,
+            timestamp: clock.unix_timestamp,
         });
 
-        msg!("Pool unpaused by admin: {}", ctx.accounts.admin.key());
+        msg!("Snapshot {} corrected: {} -> {}", day, old, value);
         Ok(())
     }
-}
+
+    /// Unstake: permanent exit. Sends all accumulated rewards.
+    /// After claim window (day 40+), users can still unstake and receive accrued rewards.
+    /// Closes the UserStake account and returns rent to user.
+    pub fn unstake(ctx: Context<Unstake>) -> Result<()> {
+        let pool_state_key = ctx.accounts.pool_state.key();
+        let recipient = reward_account(
+            &ctx.accounts.pool_state,
+            &ctx.accounts.user_token_account,
+            &ctx.accounts.user_reward_token_account,
+        )?;
+        let payout = UnstakePayout {
+            pool_token_account: &ctx.accounts.pool_token_account,
+            reward_pool_token_account: &ctx.accounts.reward_pool_token_account,
+            recipient,
+            treasury_token_account: &ctx.accounts.treasury_token_account,
+            referrer_token_account: &ctx.accounts.referrer_token_account,
+            token_program: &ctx.accounts.token_program,
+        };
+        settle_unstake(
+            &mut ctx.accounts.pool_state,
+            &pool_state_key,
+            ctx.program_id,
+            &mut ctx.accounts.user_stake,
+            payout,
+            Clock::get()?.unix_timestamp,
+        )
+    }
+
+    /// Unstake with the payout sent to `destination`, any token account of the reward mint
+    /// (e.g. an exchange deposit account). Still signed by the stake owner, who gets the
+    /// stake account's rent back.
+    pub fn unstake_to(ctx: Context<UnstakeTo>, destination: Pubkey) -> Result<()> {
+        let pool_state_key = ctx.accounts.pool_state.key();
+        let now = Clock::get()?.unix_timestamp;
+        let payout = UnstakePayout {
+            pool_token_account: &ctx.accounts.pool_token_account,
+            reward_pool_token_account: &ctx.accounts.reward_pool_token_account,
+            recipient: &ctx.accounts.destination_token_account,
+            treasury_token_account: &ctx.accounts.treasury_token_account,
+            referrer_token_account: &ctx.accounts.referrer_token_account,
+            token_program: &ctx.accounts.token_program,
+        };
+        settle_unstake(
+            &mut ctx.accounts.pool_state,
+            &pool_state_key,
+            ctx.program_id,
+            &mut ctx.accounts.user_stake,
+            payout,
+            now,
+        )?;
+
+        emit!(UnstakedTo {
+            user: ctx.accounts.user.key(),
+            destination,
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// Withdraw part of the accrued rewards while keeping the stake open and earning.
+    /// During the staking period the early-exit penalty stays reserved so it can still
+    /// be collected on unstake. May be signed by the owner or its harvest delegate;
+    /// rewards always go to the owner's token account.
+    pub fn withdraw_rewards(ctx: Context<WithdrawRewards>, amount: u64) -> Result<()> {
+        let pool_state_key = ctx.accounts.pool_state.key();
+        let pool = &ctx.accounts.pool_state;
+        let user_stake = &ctx.accounts.user_stake;
+        let clock = Clock::get()?;
+
+        require!(!is_paused(pool, clock.unix_timestamp), ErrorCode::PoolPaused);
+        require!(user_stake.staked_amount > 0, ErrorCode::NothingStaked);
+
+        let current_day =
+            pool.current_day(clock.unix_timestamp).min(pool.program_days());
+        require!(
+            pool.snapshot_count >= current_day as u8,
+            ErrorCode::SnapshotRequiredFirst
+        );
+
+        let payout = pay_accrued_rewards(
+            ctx.accounts,
+            &pool_state_key,
+            current_day,
+            amount,
+            clock.unix_timestamp,
+        )?;
+
+        msg!(
+            "Withdrew {} rewards for {} ({} withdrawn in total)",
+            payout,
+            ctx.accounts.user_stake.owner,
+            ctx.accounts.user_stake.rewards_withdrawn
+        );
+        Ok(())
+    }
+
+    /// Withdraw a single finalized day's reward share (`day < snapshot_count`), marking it in
+    /// the stake's `claimed_days` bitmap. Like `withdraw_rewards` it needs the days before the
+    /// current one snapshotted, and the payout advances `rewards_withdrawn`, so `unstake` only
+    /// pays the days not claimed here; the early-exit penalty reserve applies the same way.
+    pub fn claim_day_reward(ctx: Context<WithdrawRewards>, day: u64) -> Result<()> {
+        let pool_state_key = ctx.accounts.pool_state.key();
+        let pool = &ctx.accounts.pool_state;
+        let user_stake = &mut ctx.accounts.user_stake;
+        let clock = Clock::get()?;
+
+        require!(!is_paused(pool, clock.unix_timestamp), ErrorCode::PoolPaused);
+        require!(user_stake.staked_amount > 0, ErrorCode::NothingStaked);
+        require!(day < pool.program_days(), ErrorCode::InvalidDay);
+        // Accrual runs through the current day, so every earlier day must be recorded, as
+        // in withdraw_rewards (averaged mode would otherwise price unrecorded days)
+        let current_day =
+            pool.current_day(clock.unix_timestamp).min(pool.program_days());
+        require!(
+            day < pool.snapshot_count as u64 && pool.snapshot_count >= current_day as u8,
+            ErrorCode::SnapshotRequiredFirst
+        );
+        let day_bit = 1u32 << day;
+        require!(
+            user_stake.claimed_days & day_bit == 0,
+            ErrorCode::DayAlreadyClaimed
+        );
+        user_stake.claimed_days |= day_bit;
+
+        let amount = if day >= reward_start_day(&pool.config, user_stake.claim_day) {
+            let d = day as usize;
+            let scaled = reward_for_day(
+                &pool.config,
+                effective_stake(&pool.config, user_stake)?,
+                pool.day_reward(d),
+                reward_denominator(pool, d),
+            );
+            reward_to_u64(scaled / REWARD_SCALE)?
+        } else {
+            0
+        };
+        let payout = pay_accrued_rewards(
+            ctx.accounts,
+            &pool_state_key,
+            current_day,
+            amount,
+            clock.unix_timestamp,
+        )?;
+
+        msg!(
+            "Claimed day {} reward of {} for {}",
+            day,
+            payout,
+            ctx.accounts.user_stake.owner
+        );
+        Ok(())
+    }
+
+    /// Let `delegate` sign `withdraw_rewards` for this stake (owner only). The delegate cannot
+    /// unstake, and rewards still go to the owner. Pass the default pubkey to revoke.
+    pub fn set_harvest_delegate(ctx: Context<SetHarvestDelegate>, delegate: Pubkey) -> Result<()> {
+        let user_stake = &mut ctx.accounts.user_stake;
+        user_stake.harvest_delegate = delegate;
+
+        emit!(HarvestDelegateSet {
+            user: user_stake.owner,
+            delegate,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Harvest delegate for {} set to {}", user_stake.owner, delegate);
+        Ok(())
+    }
+
+    /// Close a UserStake that holds nothing (staked_amount == 0), returning rent to the owner.
+    /// Not gated by snapshots since an empty stake has no rewards to settle.
+    pub fn close_empty_stake(ctx: Context<CloseEmptyStake>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool_state;
+        let user_stake = &ctx.accounts.user_stake;
+
+        require!(user_stake.staked_amount == 0, ErrorCode::StakeNotEmpty);
+
+        pool.active_stakers = pool
+            .active_stakers
+            .checked_sub(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        msg!("Empty stake closed for {}", user_stake.owner);
+        Ok(())
+    }
+
+    /// Close the caller's ClaimMarker once the pool is terminated, returning its rent.
+    /// Termination is permanent and blocks every claim, so the marker no longer guards anything.
+    pub fn close_claim_marker_terminated(ctx: Context<CloseClaimMarkerTerminated>) -> Result<()> {
+        require!(
+            ctx.accounts.pool_state.terminated == 1,
+            ErrorCode::PoolNotTerminated
+        );
+
+        msg!("Claim marker closed for {}", ctx.accounts.user.key());
+        Ok(())
+    }
+
+    /// Close the caller's CrankRecord, returning its rent, once every program day is
+    /// snapshotted or the pool is terminated: later cranks record nothing, so the rate limit
+    /// no longer guards anything.
+    pub fn close_crank_record(ctx: Context<CloseCrankRecord>) -> Result<()> {
+        let pool = &ctx.accounts.pool_state;
+        require!(
+            pool.snapshot_count as u64 >= pool.program_days() || pool.terminated == 1,
+            ErrorCode::CrankRecordInUse
+        );
+
+        msg!("Crank record closed for {}", ctx.accounts.signer.key());
+        Ok(())
+    }
+
+    /// Upgrade a UserStake written under an older, shorter layout to the current one in place
+    /// (anyone can pay the added rent). Fields the old layout lacked start zeroed, i.e. no
+    /// referrer, no harvest delegate and no claimed days, and `version` becomes USER_STAKE_VERSION.
+    pub fn migrate_user_stake(ctx: Context<MigrateUserStake>) -> Result<()> {
+        let stake_info = ctx.accounts.user_stake.to_account_info();
+        let old_len = stake_info.data_len();
+        let new_len = 8 + UserStake::INIT_SPACE;
+        require!(old_len <= new_len, ErrorCode::UserStakeAlreadyMigrated);
+
+        let rent_due = Rent::get()?
+            .minimum_balance(new_len)
+            .saturating_sub(stake_info.lamports());
+        if rent_due > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.payer.to_account_info(),
+                        to: stake_info.clone(),
+                    },
+                ),
+                rent_due,
+            )?;
+        }
+        stake_info.resize(new_len)?;
+
+        let mut user_stake = UserStake::try_deserialize(&mut &stake_info.data.borrow()[..])?;
+        require!(
+            user_stake.version < USER_STAKE_VERSION,
+            ErrorCode::UserStakeAlreadyMigrated
+        );
+        user_stake.version = USER_STAKE_VERSION;
+        user_stake.try_serialize(&mut &mut stake_info.data.borrow_mut()[..])?;
+
+        emit!(UserStakeMigrated {
+            user: user_stake.owner,
+            previous_len: old_len as u64,
+            version: USER_STAKE_VERSION,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("UserStake for {} migrated to version {}", user_stake.owner, USER_STAKE_VERSION);
+        Ok(())
+    }
+
+    /// View function: calculate potential rewards for a user on a given day.
+    /// For past days with snapshots, uses actual values.
+    /// For future days, uses the last snapshot's total_staked.
+    /// Note: After unstake, UserStake is closed so this instruction will fail (account not found).
+    /// Emits RewardsCalculated alongside the log; no state is modified.
+    pub fn calculate_rewards(ctx: Context<CalculateRewards>, day: u64) -> Result<()> {
+        let pool = &ctx.accounts.pool_state;
+        let user_stake = &ctx.accounts.user_stake;
+
+        require!(day < pool.program_days(), ErrorCode::InvalidDay);
+
+        let reward = preview_day_reward(pool, user_stake, day)?;
+
+        emit!(RewardsCalculated {
+            user: user_stake.owner,
+            day,
+            reward,
+        });
+
+        msg!("Day {} reward: {}", day, reward);
+        Ok(())
+    }
+
+    /// View function: `calculate_rewards` for each day in `from_day..to_day`, at most
+    /// REWARDS_PAGE_SIZE days per call. Returned via return data.
+    pub fn calculate_rewards_range(
+        ctx: Context<CalculateRewards>,
+        from_day: u64,
+        to_day: u64,
+    ) -> Result<Vec<u64>> {
+        let pool = &ctx.accounts.pool_state;
+        let user_stake = &ctx.accounts.user_stake;
+
+        require!(
+            from_day <= to_day && to_day <= pool.program_days(),
+            ErrorCode::InvalidDay
+        );
+        require!(to_day - from_day <= REWARDS_PAGE_SIZE, ErrorCode::RangeTooLarge);
+
+        (from_day..to_day)
+            .map(|day| preview_day_reward(pool, user_stake, day))
+            .collect()
+    }
+
+    /// View function: pool-wide totals and the airdrop still available before the cap is hit.
+    /// Returned via return data.
+    pub fn get_pool_stats(ctx: Context<GetPoolStats>) -> Result<PoolStats> {
+        let pool = &ctx.accounts.pool_state;
+
+        Ok(PoolStats {
+            total_staked: pool.total_staked,
+            total_airdrop_claimed: pool.total_airdrop_claimed,
+            airdrop_remaining: pool.airdrop_cap().saturating_sub(pool.total_airdrop_claimed),
+            pool_balance: ctx.accounts.pool_token_account.amount,
+        })
+    }
+
+    /// View function: airdrop claimed per program day, from day 0 through the current day
+    /// (at most 32 entries; the last one also holds claims from later days).
+    /// Returned via return data.
+    pub fn get_daily_claimed(ctx: Context<GetDailyClaimed>) -> Result<Vec<u64>> {
+        let pool = &ctx.accounts.pool_state;
+        let clock = Clock::get()?;
+
+        let current_day = pool.current_day(clock.unix_timestamp) as usize;
+        let days = (current_day + 1).min(pool.daily_claimed.len());
+        Ok(pool.daily_claimed[..days].to_vec())
+    }
+
+    /// View function: a stake's owner, amount, claim day and rewards accrued over snapshotted
+    /// days that have not been withdrawn yet. Returned via return data.
+    pub fn get_user_stake(ctx: Context<GetUserStake>) -> Result<UserStakeInfo> {
+        let pool = &ctx.accounts.pool_state;
+        let user_stake = &ctx.accounts.user_stake;
+        let clock = Clock::get()?;
+
+        let current_day = pool.current_day(clock.unix_timestamp)
+            .min(pool.program_days())
+            .min(pool.snapshot_count as u64);
+        let pending_rewards = calculate_user_rewards(pool, user_stake, current_day)?
+            .saturating_sub(user_stake.rewards_withdrawn);
+
+        Ok(UserStakeInfo {
+            owner: user_stake.owner,
+            staked_amount: user_stake.staked_amount,
+            claim_day: user_stake.claim_day,
+            pending_rewards,
+        })
+    }
+
+    /// View function: instantaneous APR estimate in bps for `day`, annualizing that day's
+    /// reward over the current `total_staked`. It moves as stakes leave and the curve rises,
+    /// so it is not a promised rate. Returns 0 when nothing is staked or on a cliff day.
+    /// Returned via return data.
+    pub fn estimate_apr(ctx: Context<EstimateApr>, day: u64) -> Result<u64> {
+        let pool = &ctx.accounts.pool_state;
+
+        require!(day < pool.program_days(), ErrorCode::InvalidDay);
+        if pool.total_staked == 0 || day < pool.config.reward_cliff_days {
+            return Ok(0);
+        }
+
+        let apr_bps = (pool.daily_rewards[day as usize] as u128)
+            .checked_mul((SECONDS_PER_YEAR / pool.config.seconds_per_day()) as u128)
+            .and_then(|r| r.checked_mul(BPS_DENOMINATOR as u128))
+            .ok_or(ErrorCode::MathOverflow)?
+            / pool.total_staked as u128;
+
+        Ok(apr_bps.min(u64::MAX as u128) as u64)
+    }
+
+    /// View function: estimated rewards a stake receives by unstaking at program end, after
+    /// the reward fee. Recorded days count as accrued (less any already withdrawn); each
+    /// remaining day is projected with the latest snapshot as its denominator. It is only an
+    /// estimate and moves as participation changes. The airdrop itself was sent at claim and
+    /// is not included. Returned via return data.
+    pub fn estimate_final_payout(ctx: Context<EstimateFinalPayout>) -> Result<u64> {
+        let pool = &ctx.accounts.pool_state;
+        let user_stake = &ctx.accounts.user_stake;
+
+        let recorded = (pool.snapshot_count as u64).min(pool.program_days());
+        let accrued = calculate_user_rewards(pool, user_stake, recorded)?
+            .saturating_sub(user_stake.rewards_withdrawn);
+
+        let first_projected = recorded.max(reward_start_day(&pool.config, user_stake.claim_day));
+        let mut projected: u64 = 0;
+        for day in first_projected..pool.program_days() {
+            let reward = preview_day_reward(pool, user_stake, day)?;
+            projected = projected.checked_add(reward).ok_or(ErrorCode::MathOverflow)?;
+        }
+
+        let total = accrued.checked_add(projected).ok_or(ErrorCode::MathOverflow)?;
+        Ok(total - bps_of(total, pool.config.reward_fee_bps))
+    }
+
+    /// View function: the pool's current phase (see `phases`) and whole days, rounded up,
+    /// until the next phase begins (0 once expired). Returned via return data.
+    pub fn get_phase(ctx: Context<GetPhase>) -> Result<PoolPhase> {
+        let pool = &ctx.accounts.pool_state;
+        let now = Clock::get()?.unix_timestamp;
+
+        let program_end =
+            pool.accrual_start(now) + (pool.program_days() * pool.config.seconds_per_day()) as i64;
+        let window_end = pool.claim_window_end(now);
+        let (phase, phase_end) = if now < pool.start_time {
+            (phases::NOT_STARTED, pool.start_time)
+        } else if now < program_end {
+            (phases::ACTIVE, program_end)
+        } else if now < window_end {
+            (phases::EXIT_WINDOW, window_end)
+        } else {
+            (phases::EXPIRED, now)
+        };
+
+        Ok(PoolPhase {
+            phase,
+            days_remaining_in_phase: ((phase_end - now) as u64)
+                .div_ceil(pool.config.seconds_per_day()),
+        })
+    }
+
+    /// Emit a StateCheckpoint of the pool's running totals (anyone) so indexers that missed
+    /// logs can resync without scanning accounts. No state is modified.
+    pub fn emit_state_checkpoint(ctx: Context<EmitStateCheckpoint>) -> Result<()> {
+        let pool = &ctx.accounts.pool_state;
+        let now = Clock::get()?.unix_timestamp;
+
+        emit!(StateCheckpoint {
+            total_staked: pool.total_staked,
+            total_airdrop_claimed: pool.total_airdrop_claimed,
+            snapshot_count: pool.snapshot_count,
+            total_rewards_paid: total_rewards_paid(pool),
+            paused: is_paused(pool, now),
+            terminated: pool.terminated == 1,
+            timestamp: now,
+        });
+        Ok(())
+    }
+
+    /// View function: dry-run `claim_airdrop` for `user` with the same checks (pool state,
+    /// blacklist, claim window, proof, lock tier, airdrop and stake caps, prior claim) and
+    /// report the first failing one as its error code (0 = the claim would pass). Nothing is
+    /// created or transferred. The NFT gate is not checked. Returned via return data.
+    pub fn debug_claim(
+        ctx: Context<DebugClaim>,
+        amount: u64,
+        proof: Vec<[u8; 32]>,
+        index: u64,
+        lock_tier: u8,
+    ) -> Result<ClaimDiagnosis> {
+        let now = Clock::get()?.unix_timestamp;
+        let error_code = match diagnose_claim(ctx.accounts, amount, &proof, index, lock_tier, now) {
+            Ok(()) => 0,
+            Err(Error::AnchorError(error)) => error.error_code_number,
+            Err(error) => return Err(error),
+        };
+
+        Ok(ClaimDiagnosis { error_code })
+    }
+
+    /// View function: the exit deadline (end of the claim window, moved by extensions and
+    /// accrual freezes) and whether it has passed, i.e. whether `recover_expired_rewards` is
+    /// open. Returned via return data.
+    pub fn is_expired(ctx: Context<IsExpired>) -> Result<ExpiryStatus> {
+        let pool = &ctx.accounts.pool_state;
+        let now = Clock::get()?.unix_timestamp;
+
+        let exit_deadline = pool.claim_window_end(now);
+        Ok(ExpiryStatus {
+            expired: now >= exit_deadline,
+            exit_deadline,
+        })
+    }
+
+    /// View function: the merkle root claims are verified against. Returned via return data.
+    pub fn get_merkle_root(ctx: Context<GetMerkleRoot>) -> Result<[u8; 32]> {
+        Ok(ctx.accounts.pool_state.merkle_root)
+    }
+
+    /// View function: the pool's status flags packed into one byte (see `pool_flags`) for
+    /// cheap health polling. Returned via return data.
+    pub fn get_flags(ctx: Context<GetFlags>) -> Result<u8> {
+        let pool = &ctx.accounts.pool_state;
+        let now = Clock::get()?.unix_timestamp;
+
+        let mut flags = pool_flags::INITIALIZED;
+        if is_paused(pool, now) {
+            flags |= pool_flags::PAUSED;
+        }
+        if pool.terminated == 1 {
+            flags |= pool_flags::TERMINATED;
+        }
+        if pool.active == 1 {
+            flags |= pool_flags::ACTIVE;
+        }
+        Ok(flags)
+    }
+
+    /// View function: the recorded `daily_snapshots[0..snapshot_count]` and the matching
+    /// `daily_rewards` entries. Returned via return data.
+    pub fn get_snapshots(ctx: Context<GetSnapshots>) -> Result<SnapshotHistory> {
+        let pool = &ctx.accounts.pool_state;
+        let count = pool.snapshot_count as usize;
+
+        Ok(SnapshotHistory {
+            daily_snapshots: pool.daily_snapshots[..count].to_vec(),
+            daily_rewards: pool.daily_rewards[..count].to_vec(),
+        })
+    }
+
+    /// View function: whether `user` has claimed (its ClaimMarker exists).
+    /// Returned via return data; a marker that was never created reads as false.
+    pub fn is_claimed(ctx: Context<IsClaimed>) -> Result<bool> {
+        Ok(!ctx.accounts.claim_marker.data_is_empty())
+    }
+
+    /// View function: `user`'s claim and stake state in one call. A ClaimMarker or UserStake
+    /// that does not exist reads as false/zero. Returned via return data.
+    pub fn get_claim_status(ctx: Context<GetClaimStatus>) -> Result<ClaimStatus> {
+        let stake_info = &ctx.accounts.user_stake;
+        let stake = if stake_info.data_is_empty() {
+            None
+        } else {
+            Some(UserStake::try_deserialize(&mut &stake_info.data.borrow()[..])?)
+        };
+
+        Ok(ClaimStatus {
+            claimed: !ctx.accounts.claim_marker.data_is_empty(),
+            staking: stake.is_some(),
+            staked_amount: stake.as_ref().map_or(0, |s| s.staked_amount),
+            claim_day: stake.as_ref().map_or(0, |s| s.claim_day),
+        })
+    }
+
+    /// View function: dry-run a merkle proof for `user`/`amount`/`index` against the pool's root.
+    /// Creates no accounts and moves no tokens. Returned via return data.
+    pub fn verify_proof(
+        ctx: Context<VerifyProof>,
+        amount: u64,
+        proof: Vec<[u8; 32]>,
+        user: Pubkey,
+        index: u64,
+    ) -> Result<bool> {
+        let pool = &ctx.accounts.pool_state;
+        let leaf = compute_leaf(pool.config.merkle_scheme_version, index, &user, amount);
+        verify_merkle_proof(&proof, &pool.merkle_root, &leaf)
+    }
+
+    /// After claim window (day 40+), admin recovers the remaining tokens.
+    /// Stakes are virtual (airdrop tokens were sent directly to users on claim), so
+    /// total_staked is no token obligation, but rewards accrued by stakes that are still
+    /// open remain payable on unstake and are held back (see outstanding_rewards_bound).
+    /// Can be called again as stakes close or if tokens are sent to the pool.
+    pub fn recover_expired_rewards(ctx: Context<RecoverExpiredRewards>) -> Result<()> {
+        let pool_state_key = ctx.accounts.pool_state.key();
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(
+            now >= ctx.accounts.pool_state.claim_window_end(now),
+            ErrorCode::ClaimWindowStillOpen
+        );
+
+        let accounts = &mut *ctx.accounts;
+        let drained = drain_surplus(
+            &mut accounts.pool_state,
+            &pool_state_key,
+            ctx.program_id,
+            &accounts.approval,
+            SurplusDrain {
+                pool_token_account: &accounts.pool_token_account,
+                token_mint: &accounts.token_mint,
+                reward_pool_token_account: &accounts.reward_pool_token_account,
+                destination_token_account: &accounts.destination_token_account,
+                destination_reward_token_account: &accounts.destination_reward_token_account,
+                treasury: None,
+                token_program: &accounts.token_program,
+            },
+            now,
+        )?;
+
+        msg!(
+            "{} tokens and {} reward tokens recovered, {} reserved for open stakes.",
+            drained.recoverable,
+            drained.reward_recoverable,
+            drained.reserved
+        );
+        Ok(())
+    }
+
+    /// Start terminating the pool (admin only): new claims are blocked immediately and the
+    /// surplus can be drained with `execute_drain` once TIMELOCK_SECONDS have passed.
+    /// Users can keep unstaking throughout.
+    pub fn initiate_termination(ctx: Context<InitiateTermination>) -> Result<()> {
+        let pool_state_key = ctx.accounts.pool_state.key();
+        let pool = &mut ctx.accounts.pool_state;
+        let clock = Clock::get()?;
+
+        verify_pool_bumps(pool, &pool_state_key, ctx.program_id)?;
+
+        require!(pool.terminated == 0, ErrorCode::AlreadyTerminated);
+        verify_approvals(pool, &ctx.accounts.approval)?;
+
+        pool.terminated = 1;
+        pool.termination_ts = clock.unix_timestamp;
+
+        emit!(TerminationInitiated {
+            admin: ctx.accounts.admin.key(),
+            drain_after: clock.unix_timestamp + TIMELOCK_SECONDS,
+            timestamp: clock.unix_timestamp,
+        });
+
+        msg!(
+            "Termination initiated, drain possible after {}",
+            clock.unix_timestamp + TIMELOCK_SECONDS
+        );
+        Ok(())
+    }
+
+    /// Drain the surplus of a terminated pool after the timelock (admin only).
+    /// Rewards still owed to open stakes are held back, as in recover_expired_rewards.
+    pub fn execute_drain(ctx: Context<ExecuteDrain>) -> Result<()> {
+        let pool_state_key = ctx.accounts.pool_state.key();
+        let now = Clock::get()?.unix_timestamp;
+
+        let pool = &ctx.accounts.pool_state;
+        require!(pool.terminated == 1, ErrorCode::PoolNotTerminated);
+        require!(
+            now >= pool.termination_ts + TIMELOCK_SECONDS,
+            ErrorCode::TimelockNotElapsed
+        );
+
+        let accounts = &mut *ctx.accounts;
+        let drained = drain_surplus(
+            &mut accounts.pool_state,
+            &pool_state_key,
+            ctx.program_id,
+            &accounts.approval,
+            SurplusDrain {
+                pool_token_account: &accounts.pool_token_account,
+                token_mint: &accounts.token_mint,
+                reward_pool_token_account: &accounts.reward_pool_token_account,
+                destination_token_account: &accounts.destination_token_account,
+                destination_reward_token_account: &accounts.destination_reward_token_account,
+                treasury: None,
+                token_program: &accounts.token_program,
+            },
+            now,
+        )?;
+
+        msg!(
+            "{} tokens and {} reward tokens drained, {} reserved for open stakes.",
+            drained.recoverable,
+            drained.reward_recoverable,
+            drained.reserved
+        );
+        Ok(())
+    }
+
+    /// Like execute_drain, but splits the surplus: `treasury_bps / 10000` goes to the
+    /// `config.treasury` token account and the rest (including rounding dust) to the admin.
+    /// Consumes the same DRAIN approval. Not available when rewards use a separate reward_mint.
+    pub fn terminate_pool_split(ctx: Context<TerminatePoolSplit>, treasury_bps: u16) -> Result<()> {
+        let pool_state_key = ctx.accounts.pool_state.key();
+        let now = Clock::get()?.unix_timestamp;
+
+        let pool = &ctx.accounts.pool_state;
+        require!(pool.terminated == 1, ErrorCode::PoolNotTerminated);
+        require!(
+            now >= pool.termination_ts + TIMELOCK_SECONDS,
+            ErrorCode::TimelockNotElapsed
+        );
+        require!(
+            treasury_bps as u64 <= BPS_DENOMINATOR,
+            ErrorCode::InvalidTreasuryBps
+        );
+        require!(!pool.has_reward_mint(), ErrorCode::SplitUnsupportedWithRewardMint);
+
+        let accounts = &mut *ctx.accounts;
+        let drained = drain_surplus(
+            &mut accounts.pool_state,
+            &pool_state_key,
+            ctx.program_id,
+            &accounts.approval,
+            SurplusDrain {
+                pool_token_account: &accounts.pool_token_account,
+                token_mint: &accounts.token_mint,
+                reward_pool_token_account: &None,
+                destination_token_account: &accounts.destination_token_account,
+                destination_reward_token_account: &None,
+                treasury: Some((&accounts.treasury_token_account, treasury_bps)),
+                token_program: &accounts.token_program,
+            },
+            now,
+        )?;
+        let admin_amount = drained.recoverable - drained.treasury_amount;
+
+        emit!(PoolTerminated {
+            admin: accounts.admin.key(),
+            admin_amount,
+            treasury_amount: drained.treasury_amount,
+            treasury_bps,
+            timestamp: now,
+        });
+
+        msg!(
+            "{} tokens drained: {} to treasury, {} to admin, {} reserved for open stakes.",
+            drained.recoverable,
+            drained.treasury_amount,
+            admin_amount,
+            drained.reserved
+        );
+        Ok(())
+    }
+
+    /// Enable multi-admin approval for destructive actions (admin only, once).
+    /// `required_approvals` distinct approvers from `admins` must call `approve_action`
+    /// before a gated instruction runs; the approval account is consumed on execution.
+    pub fn configure_approvals(
+        ctx: Context<ConfigureApprovals>,
+        admins: [Pubkey; 3],
+        required_approvals: u8,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool_state;
+        let clock = Clock::get()?;
+
+        require!(pool.required_approvals == 0, ErrorCode::InvalidApprovalConfig);
+
+        let mut distinct = 0u8;
+        for (i, admin) in admins.iter().enumerate() {
+            require!(
+                *admin == Pubkey::default() || !admins[..i].contains(admin),
+                ErrorCode::InvalidApprovalConfig
+            );
+            if *admin != Pubkey::default() {
+                distinct += 1;
+            }
+        }
+        require!(
+            required_approvals > 0 && required_approvals <= distinct,
+            ErrorCode::InvalidApprovalConfig
+        );
+
+        pool.admins = admins;
+        pool.required_approvals = required_approvals;
+
+        emit!(ApprovalsConfigured {
+            admin: ctx.accounts.admin.key(),
+            admins,
+            required_approvals,
+            timestamp: clock.unix_timestamp,
+        });
+
+        msg!(
+            "Approvals configured: {} of {} admins required",
+            required_approvals,
+            distinct
+        );
+        Ok(())
+    }
+
+    /// Record one admin's approval for a destructive action (see `actions`).
+    pub fn approve_action(ctx: Context<ApproveAction>, action: u8) -> Result<()> {
+        let pool = &ctx.accounts.pool_state;
+        let approver = ctx.accounts.approver.key();
+        let clock = Clock::get()?;
+
+        let index = pool
+            .admins
+            .iter()
+            .position(|admin| *admin == approver && approver != Pubkey::default())
+            .ok_or(ErrorCode::NotAnApprover)?;
+
+        let approval = &mut ctx.accounts.approval;
+        approval.action = action;
+        approval.bump = ctx.bumps.approval;
+
+        let bit = 1u8 << index;
+        require!(approval.approved & bit == 0, ErrorCode::AlreadyApproved);
+        approval.approved |= bit;
+
+        let approvals = approval.approved.count_ones() as u8;
+
+        emit!(ActionApproved {
+            approver,
+            action,
+            approvals,
+            timestamp: clock.unix_timestamp,
+        });
+
+        msg!(
+            "Action {} approved by {} ({}/{})",
+            action,
+            approver,
+            approvals,
+            pool.required_approvals
+        );
+        Ok(())
+    }
+
+    /// Admin blocks an address from claiming by creating its Blacklist PDA.
+    pub fn blacklist_address(ctx: Context<BlacklistAddress>, address: Pubkey) -> Result<()> {
+        let clock = Clock::get()?;
+        ctx.accounts.blacklist_entry.bump = ctx.bumps.blacklist_entry;
+
+        emit!(AddressBlacklistedEvent {
+            admin: ctx.accounts.admin.key(),
+            address,
+            timestamp: clock.unix_timestamp,
+        });
+
+        msg!("Address blacklisted: {}", address);
+        Ok(())
+    }
+
+    /// Admin lifts a blacklist entry. The Blacklist PDA is closed and rent returned to admin.
+    pub fn unblacklist_address(ctx: Context<UnblacklistAddress>, address: Pubkey) -> Result<()> {
+        let clock = Clock::get()?;
+
+        emit!(AddressUnblacklistedEvent {
+            admin: ctx.accounts.admin.key(),
+            address,
+            timestamp: clock.unix_timestamp,
+        });
+
+        msg!("Address removed from blacklist: {}", address);
+        Ok(())
+    }
+
+    /// Emergency pause - blocks claims and snapshots.
+    /// Users can still unstake to protect their funds.
+    /// With `duration_seconds > 0` the pause lapses on its own after that long, so a lost
+    /// admin key cannot freeze the pool; 0 pauses until `unpause_pool`.
+    /// `reason_code` is a `PauseReason` recorded in state and in the event.
+    pub fn pause_pool(
+        ctx: Context<PausePool>,
+        duration_seconds: u64,
+        reason_code: u8,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool_state;
+        let clock = Clock::get()?;
+
+        require!(!is_paused(pool, clock.unix_timestamp), ErrorCode::AlreadyPaused);
+        require!(
+            PauseReason::from_code(reason_code).is_some(),
+            ErrorCode::InvalidPauseReason
+        );
+
+        pool.paused = 1;
+        pool.pause_reason = reason_code;
+        pool.pause_expiry = if duration_seconds == 0 {
+            0
+        } else {
+            let duration = i64::try_from(duration_seconds)
+                .map_err(|_| ErrorCode::InvalidPauseDuration)?;
+            clock
+                .unix_timestamp
+                .checked_add(duration)
+                .ok_or(ErrorCode::InvalidPauseDuration)?
+        };
+
+        emit!(PoolPausedEvent {
+            admin: ctx.accounts.admin.key(),
+            reason: reason_code,
+            timestamp: clock.unix_timestamp,
+        });
+
+        msg!("Pool paused by admin: {}", ctx.accounts.admin.key());
+        Ok(())
+    }
+
+    /// Extend the claim window by `additional_days` (admin only). The window can only grow,
+    /// and only while it is still open.
+    pub fn extend_claim_window(
+        ctx: Context<ExtendClaimWindow>,
+        additional_days: u64,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool_state;
+        let clock = Clock::get()?;
+
+        require!(additional_days > 0, ErrorCode::InvalidClaimWindowExtension);
+        require!(
+            clock.unix_timestamp < pool.claim_window_end(clock.unix_timestamp),
+            ErrorCode::InvalidClaimWindowExtension
+        );
+
+        let claim_window_days = pool.claim_window_days.checked_add(additional_days).unwrap();
+        require!(
+            claim_window_days <= MAX_CLAIM_WINDOW_DAYS,
+            ErrorCode::InvalidClaimWindowExtension
+        );
+        pool.claim_window_days = claim_window_days;
+
+        emit!(ClaimWindowExtended {
+            admin: ctx.accounts.admin.key(),
+            claim_window_days,
+            timestamp: clock.unix_timestamp,
+        });
+
+        msg!("Claim window extended to {} days", claim_window_days);
+        Ok(())
+    }
+
+    /// Set the claim deadline (admin only). Must be in the future and within the claim
+    /// window, and can only be changed while claims are still open.
+    pub fn set_claim_deadline(ctx: Context<SetClaimDeadline>, claim_deadline: i64) -> Result<()> {
+        let pool = &mut ctx.accounts.pool_state;
+        let clock = Clock::get()?;
+
+        require!(
+            clock.unix_timestamp < effective_claim_deadline(pool, clock.unix_timestamp),
+            ErrorCode::ClaimWindowClosed
+        );
+        require!(
+            claim_deadline > clock.unix_timestamp
+                && claim_deadline <= pool.claim_window_end(clock.unix_timestamp),
+            ErrorCode::InvalidClaimDeadline
+        );
+
+        pool.claim_deadline = claim_deadline;
+
+        emit!(ClaimDeadlineSet {
+            admin: ctx.accounts.admin.key(),
+            claim_deadline,
+            timestamp: clock.unix_timestamp,
+        });
+
+        msg!("Claim deadline set to {}", claim_deadline);
+        Ok(())
+    }
+
+    /// Grow the program to `new_days` days (admin only, before start and activation), up to
+    /// MAX_PROGRAM_DAYS. `extra_daily_rewards` holds one reward per added day and must continue
+    /// the ascending curve; `activate_pool` then requires them funded on top of STAKING_POOL.
+    pub fn resize_pool_state(
+        ctx: Context<ResizePoolState>,
+        new_days: u8,
+        extra_daily_rewards: Vec<u64>,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool_state;
+        let clock = Clock::get()?;
+
+        require!(
+            clock.unix_timestamp < pool.start_time,
+            ErrorCode::PoolAlreadyStarted
+        );
+        // Activation is the only funding check; extend_program funds days added after it
+        require!(pool.active == 0, ErrorCode::PoolAlreadyActive);
+
+        let old_days = pool.program_days();
+        require!(
+            (new_days as u64) > old_days && (new_days as u64) <= MAX_PROGRAM_DAYS,
+            ErrorCode::InvalidProgramDays
+        );
+        require!(
+            extra_daily_rewards.len() as u64 == new_days as u64 - old_days,
+            ErrorCode::InvalidProgramDays
+        );
+
+        let mut prev = pool.daily_rewards[old_days as usize - 1];
+        let mut added: u64 = 0;
+        for (i, reward) in extra_daily_rewards.iter().enumerate() {
+            require!(*reward >= prev, ErrorCode::InvalidDailyRewardsOrder);
+            pool.daily_rewards[old_days as usize + i] = *reward;
+            added = added.checked_add(*reward).ok_or(ErrorCode::MathOverflow)?;
+            prev = *reward;
+        }
+        pool.program_days = new_days;
+        pool.staking_pool = pool.staking_pool().checked_add(added).ok_or(ErrorCode::MathOverflow)?;
+        // Keep donations already credited to the budget
+        pool.staking_budget_remaining = pool
+            .staking_budget_remaining
+            .checked_add(added)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(ProgramDaysExtended {
+            admin: ctx.accounts.admin.key(),
+            program_days: new_days,
+            timestamp: clock.unix_timestamp,
+        });
+
+        msg!("Program extended from {} to {} days", old_days, new_days);
+        Ok(())
+    }
+
+    /// Extend a running program by `additional_days` (admin only, before its final day), up to
+    /// MAX_PROGRAM_DAYS. `additional_rewards` holds one ascending reward per added day; their sum
+    /// is transferred from the admin into the reward source and added to the staking budget.
+    pub fn extend_program(
+        ctx: Context<ExtendProgram>,
+        additional_days: u8,
+        additional_rewards: Vec<u64>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let pool = &ctx.accounts.pool_state;
+
+        let old_days = pool.program_days();
+        let new_days = old_days + additional_days as u64;
+        require!(
+            pool.current_day(clock.unix_timestamp) + 1 < old_days,
+            ErrorCode::ProgramExtensionTooLate
+        );
+        require!(
+            additional_days > 0 && new_days <= MAX_PROGRAM_DAYS,
+            ErrorCode::InvalidProgramDays
+        );
+        require!(
+            additional_rewards.len() == additional_days as usize,
+            ErrorCode::InvalidProgramDays
+        );
+
+        let mut prev = pool.daily_rewards[old_days as usize - 1];
+        let mut added: u64 = 0;
+        for reward in additional_rewards.iter() {
+            require!(*reward >= prev, ErrorCode::InvalidDailyRewardsOrder);
+            added = added.checked_add(*reward).ok_or(ErrorCode::MathOverflow)?;
+            prev = *reward;
+        }
+
+        // Top up the reward source with the added rewards
+        let destination = reward_account(
+            pool,
+            &ctx.accounts.pool_token_account,
+            &ctx.accounts.reward_pool_token_account,
+        )?;
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.admin_token_account.to_account_info(),
+                    to: destination.to_account_info(),
+                    authority: ctx.accounts.admin.to_account_info(),
+                },
+            ),
+            added,
+        )?;
+
+        let pool = &mut ctx.accounts.pool_state;
+        pool.daily_rewards[old_days as usize..new_days as usize]
+            .copy_from_slice(&additional_rewards);
+        pool.program_days = new_days as u8;
+        pool.staking_pool = pool.staking_pool().checked_add(added).ok_or(ErrorCode::MathOverflow)?;
+        pool.staking_budget_remaining = pool
+            .staking_budget_remaining
+            .checked_add(added)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(ProgramDaysExtended {
+            admin: ctx.accounts.admin.key(),
+            program_days: new_days as u8,
+            timestamp: clock.unix_timestamp,
+        });
+
+        msg!(
+            "Program extended mid-flight from {} to {} days (+{} rewards)",
+            old_days,
+            new_days,
+            added
+        );
+        Ok(())
+    }
+
+    /// Replace the daily reward schedule with a smaller one (admin only, before start).
+    /// `new_rewards` covers every program day, stays ascending, fits the pool's reward curve and
+    /// sums to at most the current staking pool; the freed difference is refunded to the admin.
+    pub fn rebalance_daily_rewards(
+        ctx: Context<RebalanceDailyRewards>,
+        new_rewards: Vec<u64>,
+    ) -> Result<()> {
+        let pool_state_key = ctx.accounts.pool_state.key();
+        let pool = &mut ctx.accounts.pool_state;
+        let clock = Clock::get()?;
+
+        require!(
+            clock.unix_timestamp < pool.start_time,
+            ErrorCode::PoolAlreadyStarted
+        );
+        require!(
+            new_rewards.len() as u64 == pool.program_days(),
+            ErrorCode::InvalidRebalance
+        );
+
+        let mut sum: u64 = 0;
+        for (d, reward) in new_rewards.iter().enumerate() {
+            require!(
+                d == 0 || *reward >= new_rewards[d - 1],
+                ErrorCode::InvalidDailyRewardsOrder
+            );
+            sum = sum.checked_add(*reward).ok_or(ErrorCode::MathOverflow)?;
+        }
+        let old_sum = pool.staking_pool();
+        require!(sum > 0 && sum <= old_sum, ErrorCode::InvalidRebalance);
+        verify_reward_curve(&new_rewards, pool.config.reward_curve)?;
+
+        let refunded = old_sum - sum;
+        pool.daily_rewards[..new_rewards.len()].copy_from_slice(&new_rewards);
+        pool.staking_pool = sum;
+        // Only the refund leaves the budget; donations already credited stay
+        pool.staking_budget_remaining = pool
+            .staking_budget_remaining
+            .checked_sub(refunded)
+            .ok_or(ErrorCode::MathOverflow)?;
+        if refunded > 0 {
+            transfer_rewards(
+                &ctx.accounts.token_program,
+                pool,
+                &pool_state_key,
+                reward_account(
+                    pool,
+                    &ctx.accounts.pool_token_account,
+                    &ctx.accounts.reward_pool_token_account,
+                )?,
+                reward_account(
+                    pool,
+                    &ctx.accounts.admin_token_account,
+                    &ctx.accounts.admin_reward_token_account,
+                )?,
+                refunded,
+            )?;
+        }
+
+        emit!(DailyRewardsRebalanced {
+            admin: ctx.accounts.admin.key(),
+            staking_pool: sum,
+            refunded,
+            timestamp: clock.unix_timestamp,
+        });
+
+        msg!("Daily rewards rebalanced: {} total, {} refunded", sum, refunded);
+        Ok(())
+    }
+
+    /// Fund the referral bonus budget (admin only). Tokens move from the admin into the pool
+    /// and are tracked separately, so referral bonuses never draw on the staking rewards.
+    pub fn fund_referral_budget(ctx: Context<FundReferralBudget>, amount: u64) -> Result<()> {
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.admin_token_account.to_account_info(),
+                    to: ctx.accounts.pool_token_account.to_account_info(),
+                    authority: ctx.accounts.admin.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let pool = &mut ctx.accounts.pool_state;
+        pool.referral_budget = pool
+            .referral_budget
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(ReferralBudgetFunded {
+            admin: ctx.accounts.admin.key(),
+            amount,
+            referral_budget: pool.referral_budget,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Referral budget funded: +{} (now {})", amount, pool.referral_budget);
+        Ok(())
+    }
+
+    /// Donate `amount` of the reward token to the pool (anyone). Donations land where rewards
+    /// are paid from and raise `staking_budget_remaining`; the reward schedule is unchanged,
+    /// so whatever is not paid out is recoverable surplus.
+    pub fn donate(ctx: Context<Donate>, amount: u64) -> Result<()> {
+        let pool = &ctx.accounts.pool_state;
+        let destination = reward_account(
+            pool,
+            &ctx.accounts.pool_token_account,
+            &ctx.accounts.reward_pool_token_account,
+        )?;
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.donor_token_account.to_account_info(),
+                    to: destination.to_account_info(),
+                    authority: ctx.accounts.donor.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let pool = &mut ctx.accounts.pool_state;
+        pool.staking_budget_remaining = pool
+            .staking_budget_remaining
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        pool.total_donated = pool.total_donated.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(Donated {
+            donor: ctx.accounts.donor.key(),
+            amount,
+            total_donated: pool.total_donated,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Donation of {} received (now {})", amount, pool.total_donated);
+        Ok(())
+    }
+
+    /// Raise the airdrop cap by `additional` (admin only). The extra tokens must already be in
+    /// the pool token account, e.g. via a transfer earlier in the same transaction: it has to
+    /// cover the unclaimed airdrop under the new cap, the referral budget and, without a
+    /// separate reward mint, the remaining staking budget.
+    pub fn increase_airdrop_cap(ctx: Context<IncreaseAirdropCap>, additional: u64) -> Result<()> {
+        let pool = &mut ctx.accounts.pool_state;
+        let clock = Clock::get()?;
+
+        require!(pool.terminated == 0, ErrorCode::PoolTerminated);
+
+        let new_cap = pool
+            .airdrop_cap()
+            .checked_add(additional)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let rewards_held = if pool.has_reward_mint() {
+            0
+        } else {
+            pool.staking_budget_remaining
+        };
+        let required = (new_cap - pool.total_airdrop_claimed)
+            .checked_add(rewards_held)
+            .and_then(|r| r.checked_add(pool.referral_budget))
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(
+            ctx.accounts.pool_token_account.amount >= required,
+            ErrorCode::PoolUnderfunded
+        );
+
+        pool.airdrop_cap = new_cap;
+        // Unclaimed airdrop counts as virtual stake, so the extra allocation joins total_staked
+        pool.total_staked = pool
+            .total_staked
+            .checked_add(additional)
+            .ok_or(ErrorCode::MathOverflow)?;
+        pool.airdrop_exhausted = 0;
+
+        emit!(AirdropCapIncreased {
+            admin: ctx.accounts.admin.key(),
+            additional,
+            new_cap,
+            timestamp: clock.unix_timestamp,
+        });
+
+        msg!("Airdrop cap raised by {} to {}", additional, new_cap);
+        Ok(())
+    }
+
+    /// Pay staking rewards in `reward_mint` instead of the airdrop token (admin only, before
+    /// start). Creates the reward pool token account, which the admin funds with the staking
+    /// pool; the pool token account then only needs the airdrop.
+    pub fn set_reward_mint(ctx: Context<SetRewardMint>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool_state;
+        let clock = Clock::get()?;
+
+        require!(
+            clock.unix_timestamp < pool.start_time,
+            ErrorCode::PoolAlreadyStarted
+        );
+        // Activation checked funding against the old layout
+        require!(pool.active == 0, ErrorCode::PoolAlreadyActive);
+
+        pool.reward_mint = ctx.accounts.reward_mint.key();
+        pool.reward_pool_token_account = ctx.accounts.reward_pool_token_account.key();
+        pool.reward_pool_token_bump = ctx.bumps.reward_pool_token_account;
+
+        emit!(RewardMintSet {
+            admin: ctx.accounts.admin.key(),
+            reward_mint: pool.reward_mint,
+            reward_pool_token_account: pool.reward_pool_token_account,
+            timestamp: clock.unix_timestamp,
+        });
+
+        msg!("Rewards will be paid in {}", pool.reward_mint);
+        Ok(())
+    }
+
+    /// Open the pool for claims once it holds the full airdrop and staking pool (admin only).
+    /// With a separate reward mint the staking pool must sit in the reward pool token account.
+    pub fn activate_pool(ctx: Context<ActivatePool>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool_state;
+        let clock = Clock::get()?;
+
+        require!(pool.active == 0, ErrorCode::PoolAlreadyActive);
+
+        let airdrop_held = ctx.accounts.pool_token_account.amount;
+        let (airdrop_needed, rewards_held) = if pool.has_reward_mint() {
+            let reward_pool = reward_account(
+                pool,
+                &ctx.accounts.pool_token_account,
+                &ctx.accounts.reward_pool_token_account,
+            )?;
+            (pool.airdrop_cap(), reward_pool.amount)
+        } else {
+            (
+                pool.airdrop_cap()
+                    .checked_add(pool.staking_pool())
+                    .ok_or(ErrorCode::MathOverflow)?,
+                airdrop_held.saturating_sub(pool.airdrop_cap()),
+            )
+        };
+        require!(
+            airdrop_held >= airdrop_needed && rewards_held >= pool.staking_pool(),
+            ErrorCode::PoolUnderfunded
+        );
+
+        pool.active = 1;
+
+        emit!(PoolActivated {
+            admin: ctx.accounts.admin.key(),
+            pool_balance: airdrop_held,
+            reward_balance: rewards_held,
+            timestamp: clock.unix_timestamp,
+        });
+
+        msg!("Pool activated with {} tokens", airdrop_held);
+        Ok(())
+    }
+
+    /// Freeze reward accrual (admin only, during the program days): the program day stops
+    /// advancing and the program end and claim window end move out with the freeze.
+    pub fn freeze_accrual(ctx: Context<FreezeAccrual>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool_state;
+        let clock = Clock::get()?;
+
+        require!(pool.frozen_since == 0, ErrorCode::AccrualAlreadyFrozen);
+        require!(
+            clock.unix_timestamp >= pool.start_time
+                && pool.current_day(clock.unix_timestamp) < pool.program_days(),
+            ErrorCode::AccrualFreezeOutsideProgram
+        );
+
+        pool.frozen_since = clock.unix_timestamp;
+
+        emit!(AccrualFrozen {
+            admin: ctx.accounts.admin.key(),
+            day: pool.current_day(clock.unix_timestamp),
+            timestamp: clock.unix_timestamp,
+        });
+
+        msg!("Accrual frozen by admin: {}", ctx.accounts.admin.key());
+        Ok(())
+    }
+
+    /// Resume reward accrual (admin only); the frozen time is added to `frozen_seconds`.
+    pub fn unfreeze_accrual(ctx: Context<FreezeAccrual>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool_state;
+        let clock = Clock::get()?;
+
+        require!(pool.frozen_since != 0, ErrorCode::AccrualNotFrozen);
+
+        pool.frozen_seconds = pool.frozen_seconds_at(clock.unix_timestamp);
+        let frozen_for = (clock.unix_timestamp - pool.frozen_since).max(0) as u64;
+        pool.frozen_since = 0;
+
+        emit!(AccrualUnfrozen {
+            admin: ctx.accounts.admin.key(),
+            frozen_for,
+            frozen_seconds: pool.frozen_seconds,
+            timestamp: clock.unix_timestamp,
+        });
+
+        msg!("Accrual resumed after {}s frozen", frozen_for);
+        Ok(())
+    }
+
+    /// Compliance hold (admin only): the stake keeps accruing rewards, but `unstake`,
+    /// `unstake_to`, `withdraw_rewards` and `claim_day_reward` fail with StakeFrozen until
+    /// `unfreeze_stake`.
+    pub fn freeze_stake(ctx: Context<FreezeStake>, user: Pubkey) -> Result<()> {
+        let user_stake = &mut ctx.accounts.user_stake;
+        require!(user_stake.frozen == 0, ErrorCode::StakeAlreadyFrozen);
+        user_stake.frozen = 1;
+
+        emit!(StakeFreezeSet {
+            admin: ctx.accounts.admin.key(),
+            user,
+            frozen: true,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Stake of {} frozen", user);
+        Ok(())
+    }
+
+    /// Lift a compliance hold (admin only).
+    pub fn unfreeze_stake(ctx: Context<FreezeStake>, user: Pubkey) -> Result<()> {
+        let user_stake = &mut ctx.accounts.user_stake;
+        require!(user_stake.frozen != 0, ErrorCode::StakeNotFrozen);
+        user_stake.frozen = 0;
+
+        emit!(StakeFreezeSet {
+            admin: ctx.accounts.admin.key(),
+            user,
+            frozen: false,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Stake of {} unfrozen", user);
+        Ok(())
+    }
+
+    /// Unpause pool - resumes normal operations. The admin or, as a break-glass path if the
+    /// admin key is lost, the config's `guardian`.
+    pub fn unpause_pool(ctx: Context<UnpausePool>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool_state;
+        let clock = Clock::get()?;
+
+        require!(pool.paused == 1, ErrorCode::PoolNotPaused);
+
+        pool.paused = 0;
+        pool.pause_expiry = 0;
+
+        emit!(PoolUnpausedEvent {
+            admin: ctx.accounts.admin.key(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        msg!("Pool unpaused by: {}", ctx.accounts.admin.key());
+        Ok(())
+    }
+}
 
 // ── Helpers ────────────────────────────────────────────────────────────────────
 
-/// Shared helper to transfer tokens from the pool's PDA-owned token account.
-fn transfer_from_pool_pda<'info>(
-    token_program: &Program<'info, Token>,
-    pool_token_account: &Account<'info, TokenAccount>,
-    destination_token_account: &Account<'info, TokenAccount>,
-    pool_state_key: &Pubkey,
-    pool_token_bump: u8,
-    amount: u64,
-) -> Result<()> {
-    let seeds = &[
-        seeds::POOL_TOKEN,
-        pool_state_key.as_ref(),
-        &[pool_token_bump],
-    ];
-    let signer_seeds = &[&seeds[..]];
+/// Seeds of the pool token account PDA. The account is its own token authority
+/// (`token::authority = pool_token_account`), so every transfer out of it signs with these.
+fn pool_token_signer_seeds<'a>(pool_state_key: &'a Pubkey, bump: &'a [u8; 1]) -> [&'a [u8]; 3] {
+    [seeds::POOL_TOKEN, pool_state_key.as_ref(), bump]
+}
+
+/// Shared helper to transfer tokens from the pool's PDA-owned token account.
+fn transfer_from_pool_pda<'info>(
+    token_program: &Program<'info, Token>,
+    pool_token_account: &Account<'info, TokenAccount>,
+    destination_token_account: &Account<'info, TokenAccount>,
+    pool_state_key: &Pubkey,
+    pool_token_bump: u8,
+    amount: u64,
+) -> Result<()> {
+    let bump = [pool_token_bump];
+    let seeds = pool_token_signer_seeds(pool_state_key, &bump);
+    let signer_seeds = &[&seeds[..]];
+
+    let transfer_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        Transfer {
+            from: pool_token_account.to_account_info(),
+            to: destination_token_account.to_account_info(),
+            authority: pool_token_account.to_account_info(),
+        },
+        signer_seeds,
+    );
+    token::transfer(transfer_ctx, amount)
+}
+
+/// Create a program-owned PDA with `space` bytes, rent paid by `payer`. Like Anchor's `init`,
+/// an address already holding lamports is topped up, allocated and assigned instead.
+fn init_pda_account<'info>(
+    payer: &Signer<'info>,
+    account: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    space: usize,
+    signer_seeds: &[&[u8]],
+) -> Result<()> {
+    let rent = Rent::get()?.minimum_balance(space);
+    let signer = &[signer_seeds];
+    if account.lamports() == 0 {
+        return system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                system_program::CreateAccount {
+                    from: payer.to_account_info(),
+                    to: account.clone(),
+                },
+                signer,
+            ),
+            rent,
+            space as u64,
+            &crate::ID,
+        );
+    }
+
+    let top_up = rent.saturating_sub(account.lamports());
+    if top_up > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                system_program::Transfer {
+                    from: payer.to_account_info(),
+                    to: account.clone(),
+                },
+            ),
+            top_up,
+        )?;
+    }
+    system_program::allocate(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            system_program::Allocate {
+                account_to_allocate: account.clone(),
+            },
+            signer,
+        ),
+        space as u64,
+    )?;
+    system_program::assign(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            system_program::Assign {
+                account_to_assign: account.clone(),
+            },
+            signer,
+        ),
+        &crate::ID,
+    )
+}
+
+/// Pay `amount` of a stake's accrued, not yet withdrawn rewards (after the fee) to its owner,
+/// advancing the `rewards_withdrawn` watermark. During the staking period the early-exit
+/// penalty stays reserved. Returns the payout after the fee.
+fn pay_accrued_rewards(
+    accounts: &mut WithdrawRewards,
+    pool_state_key: &Pubkey,
+    current_day: u64,
+    amount: u64,
+    now: i64,
+) -> Result<u64> {
+    let pool = &mut accounts.pool_state;
+    let user_stake = &mut accounts.user_stake;
+    require!(user_stake.frozen == 0, ErrorCode::StakeFrozen);
+
+    let accrued = calculate_user_rewards(pool, user_stake, current_day)?
+        .saturating_sub(user_stake.rewards_withdrawn);
+
+    let reserved = if pool.current_day(now) < pool.program_days() {
+        bps_of(user_stake.staked_amount, pool.config.early_exit_penalty_bps)
+    } else {
+        0
+    };
+    let available = accrued
+        .saturating_sub(reserved)
+        .min(reward_cap_remaining(&pool.config, user_stake));
+    require!(amount <= available, ErrorCode::InsufficientRewards);
+
+    require!(
+        amount <= pool.staking_budget_remaining,
+        ErrorCode::RewardBudgetExhausted
+    );
+    pool.staking_budget_remaining -= amount;
+
+    // Advance the watermark before paying out
+    user_stake.rewards_withdrawn = user_stake
+        .rewards_withdrawn
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    pool.open_rewards_withdrawn = pool
+        .open_rewards_withdrawn
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    let fee = bps_of(amount, pool.config.reward_fee_bps);
+    let payout = amount - fee;
+
+    let reward_source = reward_account(
+        pool,
+        &accounts.pool_token_account,
+        &accounts.reward_pool_token_account,
+    )?;
+
+    if fee > 0 {
+        let treasury_token_account = accounts
+            .treasury_token_account
+            .as_ref()
+            .ok_or(ErrorCode::MissingTreasuryAccount)?;
+        transfer_rewards(
+            &accounts.token_program,
+            pool,
+            pool_state_key,
+            reward_source,
+            treasury_token_account,
+            fee,
+        )?;
+
+        emit!(RewardFeeTaken {
+            user: user_stake.owner,
+            fee,
+            timestamp: now,
+        });
+    }
+
+    if payout > 0 {
+        transfer_rewards(
+            &accounts.token_program,
+            pool,
+            pool_state_key,
+            reward_source,
+            reward_account(
+                pool,
+                &accounts.user_token_account,
+                &accounts.user_reward_token_account,
+            )?,
+            payout,
+        )?;
+    }
+
+    emit!(RewardsWithdrawn {
+        user: user_stake.owner,
+        amount: payout,
+        timestamp: now,
+    });
+
+    Ok(payout)
+}
+
+/// Like transfer_from_pool_pda, but via transfer_checked so the token program verifies
+/// `mint` and its decimals against both accounts.
+fn transfer_checked_from_pool_pda<'info>(
+    token_program: &Program<'info, Token>,
+    pool_token_account: &Account<'info, TokenAccount>,
+    mint: &Account<'info, Mint>,
+    destination_token_account: &Account<'info, TokenAccount>,
+    pool_state_key: &Pubkey,
+    pool_token_bump: u8,
+    amount: u64,
+) -> Result<()> {
+    let bump = [pool_token_bump];
+    let seeds = pool_token_signer_seeds(pool_state_key, &bump);
+    let signer_seeds = &[&seeds[..]];
+
+    let transfer_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        TransferChecked {
+            from: pool_token_account.to_account_info(),
+            mint: mint.to_account_info(),
+            to: destination_token_account.to_account_info(),
+            authority: pool_token_account.to_account_info(),
+        },
+        signer_seeds,
+    );
+    token::transfer_checked(transfer_ctx, amount, mint.decimals)
+}
+
+/// `separate` when the pool pays rewards in a separate reward_mint, otherwise `default`
+/// (the matching token_mint account).
+fn reward_account<'a, 'info>(
+    pool: &PoolState,
+    default: &'a Account<'info, TokenAccount>,
+    separate: &'a Option<Account<'info, TokenAccount>>,
+) -> Result<&'a Account<'info, TokenAccount>> {
+    if pool.has_reward_mint() {
+        separate
+            .as_ref()
+            .ok_or_else(|| error!(ErrorCode::MissingRewardAccount))
+    } else {
+        Ok(default)
+    }
+}
+
+/// Transfer rewards out of `source` (see reward_account), signing for whichever pool PDA
+/// holds them.
+fn transfer_rewards<'info>(
+    token_program: &Program<'info, Token>,
+    pool: &PoolState,
+    pool_state_key: &Pubkey,
+    source: &Account<'info, TokenAccount>,
+    destination: &Account<'info, TokenAccount>,
+    amount: u64,
+) -> Result<()> {
+    if !pool.has_reward_mint() {
+        return transfer_from_pool_pda(
+            token_program,
+            source,
+            destination,
+            pool_state_key,
+            pool.pool_token_bump,
+            amount,
+        );
+    }
+
+    let seeds = &[
+        seeds::REWARD_POOL_TOKEN,
+        pool_state_key.as_ref(),
+        &[pool.reward_pool_token_bump],
+    ];
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            Transfer {
+                from: source.to_account_info(),
+                to: destination.to_account_info(),
+                authority: source.to_account_info(),
+            },
+            &[&seeds[..]],
+        ),
+        amount,
+    )
+}
+
+/// Accounts a surplus drain pays out of and into (see drain_surplus).
+struct SurplusDrain<'a, 'info> {
+    pool_token_account: &'a Account<'info, TokenAccount>,
+    token_mint: &'a Account<'info, Mint>,
+    reward_pool_token_account: &'a Option<Account<'info, TokenAccount>>,
+    destination_token_account: &'a Account<'info, TokenAccount>,
+    destination_reward_token_account: &'a Option<Account<'info, TokenAccount>>,
+    /// Token account taking a bps share of the token_mint surplus before the destination
+    treasury: Option<(&'a Account<'info, TokenAccount>, u16)>,
+    token_program: &'a Program<'info, Token>,
+}
+
+/// What drain_surplus held back and paid out; `recoverable` includes `treasury_amount`.
+struct DrainedSurplus {
+    reserved: u64,
+    recoverable: u64,
+    treasury_amount: u64,
+    reward_recoverable: u64,
+}
+
+/// Pay out everything the pool holds beyond what open stakes may still claim as rewards
+/// (see outstanding_rewards_bound), sweeping any unspent referral budget along with it.
+/// Callers check their own window or timelock first; this runs the checks every drain shares.
+/// With a separate reward mint the reserve is held in reward_mint, and the pool token
+/// account (unclaimed airdrop + referral budget) has nothing reserved.
+fn drain_surplus(
+    pool: &mut Account<PoolState>,
+    pool_state_key: &Pubkey,
+    program_id: &Pubkey,
+    approval: &Option<Account<ActionApproval>>,
+    drain: SurplusDrain,
+    now: i64,
+) -> Result<DrainedSurplus> {
+    verify_pool_bumps(pool, pool_state_key, program_id)?;
+    verify_approvals(pool, approval)?;
+    verify_snapshots_complete(pool)?;
+    verify_open_stakes_counted(pool)?;
+
+    // Re-check the destination once the window/timelock checks pass; transfer_checked
+    // below also has the token program verify the mint and its decimals
+    require!(
+        drain.destination_token_account.mint == pool.token_mint,
+        ErrorCode::InvalidAdminTokenMint
+    );
+
+    let reserved = outstanding_rewards_bound(pool);
+    let reward_source = reward_account(
+        pool,
+        drain.pool_token_account,
+        drain.reward_pool_token_account,
+    )?;
+    let (recoverable, reward_recoverable) = if pool.has_reward_mint() {
+        (
+            drain.pool_token_account.amount,
+            reward_source.amount.saturating_sub(reserved),
+        )
+    } else {
+        (drain.pool_token_account.amount.saturating_sub(reserved), 0)
+    };
+    require!(
+        recoverable > 0 || reward_recoverable > 0,
+        ErrorCode::NothingToRecover
+    );
+
+    pool.referral_budget = 0;
+
+    // The treasury share is taken first; the destination gets the rest, rounding dust included
+    let treasury_amount = drain.treasury.map_or(0, |(_, bps)| bps_of(recoverable, bps));
+    let shares = drain
+        .treasury
+        .map(|(treasury_token_account, _)| (treasury_token_account, treasury_amount))
+        .into_iter()
+        .chain([(drain.destination_token_account, recoverable - treasury_amount)]);
+    for (destination, amount) in shares {
+        if amount == 0 {
+            continue;
+        }
+        transfer_checked_from_pool_pda(
+            drain.token_program,
+            drain.pool_token_account,
+            drain.token_mint,
+            destination,
+            pool_state_key,
+            pool.pool_token_bump,
+            amount,
+        )?;
+
+        emit!(TokensRecovered {
+            amount,
+            destination: destination.key(),
+            timestamp: now,
+        });
+    }
+
+    if reward_recoverable > 0 {
+        let destination = reward_account(
+            pool,
+            drain.destination_token_account,
+            drain.destination_reward_token_account,
+        )?;
+        transfer_rewards(
+            drain.token_program,
+            pool,
+            pool_state_key,
+            reward_source,
+            destination,
+            reward_recoverable,
+        )?;
+
+        emit!(RewardTokensRecovered {
+            amount: reward_recoverable,
+            destination: destination.key(),
+            timestamp: now,
+        });
+    }
+
+    Ok(DrainedSurplus {
+        reserved,
+        recoverable,
+        treasury_amount,
+        reward_recoverable,
+    })
+}
+
+/// Accounts an unstake pays out of and into; `recipient` already holds the reward mint.
+struct UnstakePayout<'a, 'info> {
+    pool_token_account: &'a Account<'info, TokenAccount>,
+    reward_pool_token_account: &'a Option<Account<'info, TokenAccount>>,
+    recipient: &'a Account<'info, TokenAccount>,
+    treasury_token_account: &'a Option<Account<'info, TokenAccount>>,
+    referrer_token_account: &'a Option<Account<'info, TokenAccount>>,
+    token_program: &'a Program<'info, Token>,
+}
+
+/// Settle and zero a stake: penalty, fee and referral bonus, then the reward payout to
+/// `payout.recipient`. Shared by unstake and unstake_to; the caller closes the stake account.
+fn settle_unstake(
+    pool: &mut Account<PoolState>,
+    pool_state_key: &Pubkey,
+    program_id: &Pubkey,
+    user_stake: &mut Account<UserStake>,
+    payout: UnstakePayout,
+    now: i64,
+) -> Result<()> {
+    verify_pool_bumps(pool, pool_state_key, program_id)?;
+
+    require!(user_stake.staked_amount > 0, ErrorCode::NothingStaked);
+    require!(user_stake.frozen == 0, ErrorCode::StakeFrozen);
+
+    // Anti flash-loan: the stake must be held for at least min_stake_seconds,
+    // and for the lockup of its tier
+    let lock_seconds = pool
+        .config
+        .min_stake_seconds
+        .max(pool.config.lock_min_seconds[user_stake.lock_tier as usize]);
+    require!(
+        now >= user_stake.stake_ts.saturating_add(lock_seconds as i64),
+        ErrorCode::StakeLocked
+    );
+
+    // Cap to the program length for snapshot comparison and reward calculation
+    let current_day = pool.current_day(now).min(pool.program_days());
+    // Block unstaking if previous day's snapshot hasn't been taken yet
+    require!(
+        pool.snapshot_count >= current_day as u8,
+        ErrorCode::SnapshotRequiredFirst
+    );
+    // Rewards accrued during the program stay payable after the claim window
+    // (recover_expired_rewards reserves them). Rewards already paid out via
+    // withdraw_rewards are not paid again.
+    let gross_rewards = calculate_user_rewards(pool, user_stake, current_day)?
+        .saturating_sub(user_stake.rewards_withdrawn);
+
+    let exit_day = pool.current_day(now);
+
+    // Early exit during the staking period forfeits staked_amount * bps / 10000.
+    // Stakes are virtual, so the penalty is taken from the reward payout (capped at it)
+    // and simply stays in pool_token_account.
+    let early_exit = exit_day < pool.program_days();
+    let penalty = if early_exit {
+        bps_of(user_stake.staked_amount, pool.config.early_exit_penalty_bps).min(gross_rewards)
+    } else {
+        0
+    };
+    // Optional lifetime cap relative to principal; the excess simply stays in the pool
+    let uncapped = gross_rewards - penalty;
+    let capped = uncapped.min(reward_cap_remaining(&pool.config, user_stake));
+    if capped < uncapped {
+        emit!(RewardCapped {
+            user: user_stake.owner,
+            capped_amount: uncapped - capped,
+            timestamp: now,
+        });
+    }
+
+    // Never pay out more than is left of the staking budget (floored shares keep the
+    // sum within it; this is the hard ceiling if that ever stops holding)
+    let net_rewards = capped.min(pool.staking_budget_remaining);
+    pool.staking_budget_remaining -= net_rewards;
+
+    if penalty > 0 {
+        emit!(EarlyExitPenalty {
+            user: user_stake.owner,
+            penalty,
+            timestamp: now,
+        });
+    }
+
+    // Protocol fee on distributed rewards, routed to the treasury
+    let fee = bps_of(net_rewards, pool.config.reward_fee_bps);
+    let rewards = net_rewards - fee;
+
+    // Referral bonus is paid on top of the rewards, only from referral_budget and only
+    // while it lasts; a missing referrer account skips it rather than blocking the exit
+    let referral_bonus = if user_stake.referrer != Pubkey::default()
+        && payout.referrer_token_account.is_some()
+    {
+        bps_of(net_rewards, pool.config.referral_bps).min(pool.referral_budget)
+    } else {
+        0
+    };
+    pool.referral_budget -= referral_bonus;
+
+    // Zero the stake and update pool state before any transfer, so the stake
+    // cannot be paid twice even if the close constraint is ever removed
+    let weighted_stake = effective_stake(&pool.config, user_stake)?;
+    let staked_amount = std::mem::take(&mut user_stake.staked_amount);
+    require!(staked_amount > 0, ErrorCode::NothingStaked);
+    // An inconsistent pool (e.g. total_staked below this stake) fails cleanly instead of panicking
+    pool.total_staked = pool
+        .total_staked
+        .checked_sub(weighted_stake)
+        .ok_or(ErrorCode::MathOverflow)?;
+    pool.active_stakers = pool
+        .active_stakers
+        .checked_sub(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    // Saturating: stakes funded before funded_stakers existed were never counted
+    pool.funded_stakers = pool.funded_stakers.saturating_sub(1);
+    pool.total_unstaked = pool
+        .total_unstaked
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    pool.open_rewards_withdrawn = pool
+        .open_rewards_withdrawn
+        .saturating_sub(user_stake.rewards_withdrawn);
+
+    let reward_source = reward_account(
+        pool,
+        payout.pool_token_account,
+        payout.reward_pool_token_account,
+    )?;
+
+    // Reserve invariant: after this payout the pool must still cover every other open
+    // stake's rewards, so an underfunded pool cannot pay early exiters and strand the rest
+    let reward_balance = reward_source.amount.saturating_sub(net_rewards);
+    let reserve_held = if pool.has_reward_mint() {
+        reward_balance >= outstanding_rewards_bound(pool)
+            && payout.pool_token_account.amount.saturating_sub(referral_bonus)
+                >= pool.referral_budget
+    } else {
+        reward_balance.saturating_sub(referral_bonus)
+            >= outstanding_rewards_bound(pool).saturating_add(pool.referral_budget)
+    };
+    require!(reserve_held, ErrorCode::PoolReserveViolation);
+
+    if fee > 0 {
+        let treasury_token_account = payout
+            .treasury_token_account
+            .as_ref()
+            .ok_or(ErrorCode::MissingTreasuryAccount)?;
+        transfer_rewards(
+            payout.token_program,
+            pool,
+            pool_state_key,
+            reward_source,
+            treasury_token_account,
+            fee,
+        )?;
+
+        emit!(RewardFeeTaken {
+            user: user_stake.owner,
+            fee,
+            timestamp: now,
+        });
+    }
+
+    if referral_bonus > 0 {
+        let referrer_token_account = payout.referrer_token_account.as_ref().unwrap();
+        transfer_from_pool_pda(
+            payout.token_program,
+            payout.pool_token_account,
+            referrer_token_account,
+            pool_state_key,
+            pool.pool_token_bump,
+            referral_bonus,
+        )?;
+
+        emit!(ReferralBonusPaid {
+            referrer: user_stake.referrer,
+            user: user_stake.owner,
+            amount: referral_bonus,
+            timestamp: now,
+        });
+    }
+
+    // Transfer tokens via PDA signer (skip if 0 rewards)
+    if rewards > 0 {
+        transfer_rewards(
+            payout.token_program,
+            pool,
+            pool_state_key,
+            reward_source,
+            payout.recipient,
+            rewards,
+        )?;
+    }
+
+    // UserStake account is closed by Anchor's close constraint
+    emit!(Unstaked {
+        user: user_stake.owner,
+        rewards,
+        day: exit_day,
+        timestamp: now,
+    });
+
+    msg!(
+        "Unstaked: {} rewards sent to {}. UserStake account closed.",
+        rewards,
+        user_stake.owner
+    );
+    Ok(())
+}
+
+/// Shared claim preconditions; returns the claim day.
+fn validate_claim(pool: &PoolState, blacklisted: bool, amount: u64, now: i64) -> Result<u64> {
+    // A zero allocation would only create empty ClaimMarker/UserStake accounts
+    require!(amount > 0, ErrorCode::ZeroClaimAmount);
+    require!(pool.active == 1, ErrorCode::PoolNotActive);
+    require!(!is_paused(pool, now), ErrorCode::PoolPaused);
+    require!(pool.terminated == 0, ErrorCode::PoolTerminated);
+    require!(now > pool.start_time, ErrorCode::PoolNotStartedYet);
+
+    // Compliance: blacklisted addresses cannot claim even with a valid proof
+    require!(!blacklisted, ErrorCode::AddressBlacklisted);
+
+    // Determine which day the user is claiming on
+    let current_day = pool.current_day(now);
+
+    // Block claims after the claim window ends (day 40+)
+    require!(current_day < pool.claim_window_days, ErrorCode::StakingPeriodEnded);
+
+    // Admin-set claim deadline can close claims earlier; staking continues regardless
+    require!(now < effective_claim_deadline(pool, now), ErrorCode::ClaimWindowClosed);
+
+    Ok(current_day)
+}
+
+/// Checks `claim_airdrop` would make for this claim, in its order, without changing state.
+fn diagnose_claim(
+    accounts: &DebugClaim,
+    amount: u64,
+    proof: &[[u8; 32]],
+    index: u64,
+    lock_tier: u8,
+    now: i64,
+) -> Result<()> {
+    let pool = &accounts.pool_state;
+    validate_claim(pool, !accounts.blacklist_entry.data_is_empty(), amount, now)?;
+
+    let leaf = compute_leaf(
+        pool.config.merkle_scheme_version,
+        index,
+        &accounts.user.key(),
+        amount,
+    );
+    require!(
+        verify_merkle_proof(proof, &pool.merkle_root, &leaf)?,
+        ErrorCode::InvalidMerkleProof
+    );
+    // claim_airdrop fails creating the existing ClaimMarker
+    require!(
+        accounts.claim_marker.data_is_empty(),
+        ErrorCode::AlreadyClaimed
+    );
+
+    require!((lock_tier as usize) < LOCK_TIERS, ErrorCode::InvalidLockTier);
+    // Reported like any other failing check rather than aborting the view
+    let claimed = pool
+        .total_airdrop_claimed
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    require!(claimed <= pool.airdrop_cap(), ErrorCode::AirdropPoolExhausted);
+    // A claim raises open_staked by its lock-weighted amount
+    let weighted = amount as u128 * pool.config.lock_multiplier_bps(lock_tier) as u128
+        / BPS_DENOMINATOR as u128;
+    let cap = pool.config.max_total_staked;
+    require!(
+        cap == 0 || open_staked(pool) + weighted <= cap as u128,
+        ErrorCode::StakeCapReached
+    );
+    Ok(())
+}
+
+/// Fund the claimer's stake and update the pool's claim totals.
+fn record_claim(
+    pool: &mut PoolState,
+    user_stake: &mut UserStake,
+    user: Pubkey,
+    user_stake_bump: u8,
+    amount: u64,
+    lock_tier: u8,
+    now: i64,
+) -> Result<()> {
+    require!((lock_tier as usize) < LOCK_TIERS, ErrorCode::InvalidLockTier);
+    let current_day = pool.current_day(now);
+
+    // Initialize user stake, or fund one pre-created by open_stake_account
+    if user_stake.owner == Pubkey::default() {
+        user_stake.owner = user;
+        user_stake.bump = user_stake_bump;
+        user_stake.version = USER_STAKE_VERSION;
+        pool.active_stakers = pool
+            .active_stakers
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+    }
+    user_stake.staked_amount = amount;
+    pool.funded_stakers = pool
+        .funded_stakers
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    user_stake.stake_ts = now;
+    user_stake.claim_day = current_day;
+    user_stake.lock_tier = lock_tier;
+
+    // The airdrop is already counted in total_staked at weight 1; add the tier bonus
+    let bonus = effective_stake(&pool.config, user_stake)? - amount;
+    pool.total_staked = pool
+        .total_staked
+        .checked_add(bonus)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    record_airdrop_claimed(pool, amount, current_day, now)?;
+
+    let cap = pool.config.max_total_staked;
+    require!(
+        cap == 0 || open_staked(pool) <= cap as u128,
+        ErrorCode::StakeCapReached
+    );
+    Ok(())
+}
+
+/// Count `amount` against the airdrop cap and the claim day's total.
+fn record_airdrop_claimed(
+    pool: &mut PoolState,
+    amount: u64,
+    current_day: u64,
+    now: i64,
+) -> Result<()> {
+    pool.total_airdrop_claimed = pool
+        .total_airdrop_claimed
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let day_idx = (current_day as usize).min(pool.daily_claimed.len() - 1);
+    pool.daily_claimed[day_idx] = pool.daily_claimed[day_idx]
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    require!(
+        pool.total_airdrop_claimed <= pool.airdrop_cap(),
+        ErrorCode::AirdropPoolExhausted
+    );
+
+    // One-time milestone for indexers when the boundary claim fills the airdrop pool
+    if pool.total_airdrop_claimed == pool.airdrop_cap() && pool.airdrop_exhausted == 0 {
+        pool.airdrop_exhausted = 1;
+        emit!(AirdropExhausted {
+            total_claimed: pool.total_airdrop_claimed,
+            timestamp: now,
+        });
+    }
+
+    Ok(())
+}
+
+/// Message a claim voucher signs: `pool_state || user || amount (LE) || expiry (LE)`.
+/// Binding the pool stops a voucher from being replayed against another pool.
+pub fn voucher_message(pool_state: &Pubkey, user: &Pubkey, amount: u64, expiry: i64) -> Vec<u8> {
+    let mut message = Vec::with_capacity(80);
+    message.extend_from_slice(pool_state.as_ref());
+    message.extend_from_slice(user.as_ref());
+    message.extend_from_slice(&amount.to_le_bytes());
+    message.extend_from_slice(&expiry.to_le_bytes());
+    message
+}
+
+/// Check that the instruction before the current one is an ed25519 program verification of
+/// `signer`'s signature over `message`, with all offsets pointing into its own data.
+fn verify_ed25519_voucher(
+    ix_sysvar: &AccountInfo,
+    signer: &Pubkey,
+    message: &[u8],
+) -> Result<()> {
+    let current = load_current_index_checked(ix_sysvar)?;
+    require!(current > 0, ErrorCode::InvalidVoucher);
+    let ix = load_instruction_at_checked(current as usize - 1, ix_sysvar)?;
+    require!(
+        ix.program_id == ed25519_program::ID && ix.accounts.is_empty(),
+        ErrorCode::InvalidVoucher
+    );
+
+    // Layout: num_signatures (u8), padding (u8), then seven u16 offsets for one signature
+    let data = &ix.data;
+    require!(data.len() >= 16 && data[0] == 1, ErrorCode::InvalidVoucher);
+    let offset = |i: usize| u16::from_le_bytes([data[2 + 2 * i], data[3 + 2 * i]]);
+    let (signature_ix, pubkey_offset, pubkey_ix) = (offset(1), offset(2), offset(3));
+    let (message_offset, message_size, message_ix) = (offset(4), offset(5), offset(6));
+    require!(
+        signature_ix == u16::MAX && pubkey_ix == u16::MAX && message_ix == u16::MAX,
+        ErrorCode::InvalidVoucher
+    );
+
+    let pubkey_offset = pubkey_offset as usize;
+    let message_offset = message_offset as usize;
+    let signed_pubkey = data.get(pubkey_offset..pubkey_offset + 32);
+    let signed_message = data.get(message_offset..message_offset + message_size as usize);
+    require!(
+        signed_pubkey == Some(signer.as_ref()) && signed_message == Some(message),
+        ErrorCode::InvalidVoucher
+    );
+
+    Ok(())
+}
+
+/// Check that the stored `bump` and `pool_token_bump` still derive the pool's PDAs before
+/// they are used for signing (guards against state repopulated by a migration).
+fn verify_pool_bumps(
+    pool: &PoolState,
+    pool_state_key: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<()> {
+    let pool_state_pda = Pubkey::create_program_address(
+        &[seeds::POOL_STATE, pool.token_mint.as_ref(), &[pool.bump]],
+        program_id,
+    )
+    .map_err(|_| error!(ErrorCode::InvalidBump))?;
+    require_keys_eq!(pool_state_pda, *pool_state_key, ErrorCode::InvalidBump);
+
+    let pool_token_pda = Pubkey::create_program_address(
+        &pool_token_signer_seeds(pool_state_key, &[pool.pool_token_bump]),
+        program_id,
+    )
+    .map_err(|_| error!(ErrorCode::InvalidBump))?;
+    require_keys_eq!(pool_token_pda, pool.pool_token_account, ErrorCode::InvalidBump);
+
+    Ok(())
+}
+
+/// Check that every day counted as recorded (`0..snapshot_count`) has its bit in
+/// `snapshot_taken_bitmap`. outstanding_rewards_bound trusts those days' snapshots, and an
+/// unrecorded zero would reserve nothing for its day, so surplus drains check this first.
+fn verify_snapshots_complete(pool: &PoolState) -> Result<()> {
+    let recorded = ((1u64 << pool.snapshot_count) - 1) as u32;
+    require!(
+        pool.snapshot_taken_bitmap & recorded == recorded,
+        ErrorCode::SnapshotsNotCompleted
+    );
+    Ok(())
+}
+
+/// Check that funded stakes still open are visible in `total_staked`. outstanding_rewards_bound
+/// reserves nothing once open_staked reads zero, so if `total_staked` ever drifted down to it
+/// while `funded_stakers` says stakes remain, a surplus drain would take their rewards.
+fn verify_open_stakes_counted(pool: &PoolState) -> Result<()> {
+    require!(
+        pool.funded_stakers == 0 || open_staked(pool) > 0,
+        ErrorCode::OpenStakesUncounted
+    );
+    Ok(())
+}
+
+/// Check that a gated action has enough distinct admin approvals (no-op when approvals are
+/// disabled). The approval account itself is closed by the instruction's `close` constraint.
+fn verify_approvals(pool: &PoolState, approval: &Option<Account<ActionApproval>>) -> Result<()> {
+    if pool.required_approvals == 0 {
+        return Ok(());
+    }
+    let approval = approval.as_ref().ok_or(ErrorCode::InsufficientApprovals)?;
+    require!(
+        approval.approved.count_ones() as u8 >= pool.required_approvals,
+        ErrorCode::InsufficientApprovals
+    );
+    Ok(())
+}
+
+/// Whether the pool is paused at `now`. A pause with an expiry lapses once `now` passes it,
+/// even without an explicit `unpause_pool`.
+fn is_paused(pool: &PoolState, now: i64) -> bool {
+    pool.paused == 1 && (pool.pause_expiry == 0 || now <= pool.pause_expiry)
+}
+
+/// Staking rewards paid out so far, fees included: the budget (schedule plus donations)
+/// minus what remains of it.
+fn total_rewards_paid(pool: &PoolState) -> u64 {
+    pool.staking_pool()
+        .saturating_add(pool.total_donated)
+        .saturating_sub(pool.staking_budget_remaining)
+}
+
+/// Change from `prev` to `current` in bps of `prev` (None when `prev` is zero).
+fn stake_delta_bps(prev: u64, current: u64) -> Option<u64> {
+    if prev == 0 {
+        return None;
+    }
+    let delta = prev.abs_diff(current) as u128 * BPS_DENOMINATOR as u128 / prev as u128;
+    Some(u64::try_from(delta).unwrap_or(u64::MAX))
+}
+
+/// Claims close at the admin-set `claim_deadline`, or at the claim window end when unset (0).
+fn effective_claim_deadline(pool: &PoolState, now: i64) -> i64 {
+    if pool.claim_deadline == 0 {
+        pool.claim_window_end(now)
+    } else {
+        pool.claim_deadline
+    }
+}
+
+/// Check an ascending `daily_rewards` schedule against `curve` (REWARD_CURVE_*): each day, or
+/// each step between days, may be off by REWARD_CURVE_TOLERANCE_BPS of the mean day.
+fn verify_reward_curve(daily_rewards: &[u64], curve: u8) -> Result<()> {
+    let mean = daily_rewards.iter().sum::<u64>() / daily_rewards.len() as u64;
+    let tolerance = bps_of(mean, REWARD_CURVE_TOLERANCE_BPS);
+    let within = |a: u64, b: u64| a.abs_diff(b) <= tolerance;
+
+    let conforms = match curve {
+        REWARD_CURVE_CONSTANT => daily_rewards.iter().all(|&r| within(r, mean)),
+        REWARD_CURVE_LINEAR => {
+            let step = daily_rewards[1] - daily_rewards[0];
+            daily_rewards.windows(2).all(|w| within(w[1] - w[0], step))
+        }
+        _ => true,
+    };
+    require!(conforms, ErrorCode::InvalidDailyRewards);
+    Ok(())
+}
+
+/// `amount * bps / BPS_DENOMINATOR`, rounded down.
+fn bps_of(amount: u64, bps: u16) -> u64 {
+    ((amount as u128)
+        .checked_mul(bps as u128)
+        .unwrap()
+        / BPS_DENOMINATOR as u128) as u64
+}
+
+/// Returns the unix timestamp when the claim window ends (day 40 unless extended).
+pub fn claim_window_end(start_time: i64, claim_window_days: u64, seconds_per_day: u64) -> i64 {
+    start_time + (claim_window_days as i64 * seconds_per_day as i64)
+}
+
+/// Returns the actual elapsed day since pool start (uncapped).
+/// Day 0 = first `seconds_per_day` seconds, Day 1 = the next, etc.
+/// Call sites must cap to `PoolState::program_days` explicitly where needed for array indexing.
+pub fn get_current_day(start_time: i64, now: i64, seconds_per_day: u64) -> u64 {
+    if now <= start_time {
+        return 0;
+    }
+    ((now - start_time) as u64) / seconds_per_day
+}
+
+/// First day a stake earns rewards for.
+///
+/// Stakes are virtual (`total_staked` starts at `AIRDROP_POOL`), so by default every claimer
+/// earns from day 0. With `rewards_from_claim_day` accrual starts at the claim day instead;
+/// the snapshot for that day is taken at crank time, after same-day claims, so whether the
+/// claim day itself counts is left to `include_claim_day`. No stake earns before
+/// `reward_cliff_days`.
+fn reward_start_day(config: &PoolConfig, claim_day: u64) -> u64 {
+    let start = if config.rewards_from_claim_day {
+        claim_day + !config.include_claim_day as u64
+    } else {
+        0
+    };
+    start.max(config.reward_cliff_days)
+}
+
+/// Carry the reward of a recorded day with nothing staked into `carry_reward`, and hand the
+/// carried amount to the next recorded day with stake via `carried_rewards`, so zero-stake
+/// days are not stranded (see `PoolState::day_reward`). Cliff days pay no one either way, so
+/// they neither carry nor receive.
+fn carry_forward_reward(pool: &mut PoolState, d: usize) -> Result<()> {
+    if (d as u64) < pool.config.reward_cliff_days {
+        return Ok(());
+    }
+    if pool.daily_snapshots[d] == 0 {
+        pool.carry_reward = pool
+            .carry_reward
+            .checked_add(pool.daily_rewards[d])
+            .ok_or(ErrorCode::MathOverflow)?;
+    } else {
+        pool.carried_rewards[d] = std::mem::take(&mut pool.carry_reward);
+    }
+    Ok(())
+}
+
+/// Reverse `carry_forward_reward` for the latest recorded day `d` before its snapshot is
+/// rewritten; no later day can have received its carry yet.
+fn undo_carry_forward(pool: &mut PoolState, d: usize) -> Result<()> {
+    if (d as u64) < pool.config.reward_cliff_days {
+        return Ok(());
+    }
+    pool.carry_reward = if pool.daily_snapshots[d] == 0 {
+        pool.carry_reward.checked_sub(pool.daily_rewards[d])
+    } else {
+        pool.carry_reward.checked_add(std::mem::take(&mut pool.carried_rewards[d]))
+    }
+    .ok_or(ErrorCode::MathOverflow)?;
+    Ok(())
+}
+
+/// Stake total used as day `d`'s reward denominator under the pool's reward mode.
+///
+/// A snapshot is a single reading taken at crank time; the averaged mode smooths intraday
+/// stake changes by also weighing the previous day's reading (day 0 has none). It takes the
+/// larger of the two rather than their mean: a mean falls below the day's stake whenever
+/// stake grows, and the day's shares would then add up to more than its reward.
+fn reward_denominator(pool: &PoolState, d: usize) -> u128 {
+    let current = pool.daily_snapshots[d] as u128;
+    if pool.config.reward_mode == REWARD_MODE_AVERAGED && d > 0 {
+        current.max(pool.daily_snapshots[d - 1] as u128)
+    } else {
+        current
+    }
+}
+
+/// Rewards a stake may still be paid under `max_reward_multiple_bps`: a lifetime cap of
+/// `staked_amount * bps / 10000`, less what it already withdrew (u64::MAX when disabled).
+fn reward_cap_remaining(config: &PoolConfig, user_stake: &UserStake) -> u64 {
+    if config.max_reward_multiple_bps == 0 {
+        return u64::MAX;
+    }
+    bps_of(user_stake.staked_amount, config.max_reward_multiple_bps)
+        .saturating_sub(user_stake.rewards_withdrawn)
+}
+
+/// Stake weight used for rewards: staked_amount scaled by the stake's lock tier multiplier.
+/// Fails with MathOverflow when a multiplier above 10000 bps takes it past u64.
+fn effective_stake(config: &PoolConfig, user_stake: &UserStake) -> Result<u64> {
+    let weighted = user_stake.staked_amount as u128
+        * config.lock_multiplier_bps(user_stake.lock_tier) as u128
+        / BPS_DENOMINATOR as u128;
+    u64::try_from(weighted).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
+/// Calculate total accumulated rewards for a stake across snapshotted days up to `current_day`.
+/// Day shares are summed at REWARD_SCALE precision and floored once, so a small stake's
+/// sub-unit shares add up across days instead of each rounding to zero. With
+/// `round_up_rewards` every day's share is already a whole (rounded up) amount.
+fn calculate_user_rewards(
+    pool: &PoolState,
+    user_stake: &UserStake,
+    current_day: u64,
+) -> Result<u64> {
+    let start_day = reward_start_day(&pool.config, user_stake.claim_day);
+    let stake = effective_stake(&pool.config, user_stake)?;
+    let mut total_scaled: u128 = 0;
+
+    for d in (start_day as usize)..(current_day as usize) {
+        total_scaled = total_scaled.saturating_add(reward_for_day(
+            &pool.config,
+            stake,
+            pool.day_reward(d),
+            reward_denominator(pool, d),
+        ));
+    }
+
+    reward_to_u64(total_scaled / REWARD_SCALE)
+}
+
+/// `numerator * REWARD_SCALE / denominator`, floored, without overflowing on the scaled
+/// product (0 when the denominator is zero; saturates rather than wrapping).
+fn scaled_share(numerator: u128, denominator: u128) -> u128 {
+    if denominator == 0 {
+        return 0;
+    }
+    (numerator / denominator)
+        .saturating_mul(REWARD_SCALE)
+        .saturating_add(numerator % denominator * REWARD_SCALE / denominator)
+}
+
+/// A stake's share of one day's reward at REWARD_SCALE: `stake * daily_reward /
+/// snapshot_total`, floored at the scale, or rounded up to a whole amount with
+/// `round_up_rewards` (0 when `snapshot_total` is zero). The single per-day term behind
+/// payouts, `claim_day_reward` and previews, so they cannot disagree on a day.
+fn reward_for_day(
+    config: &PoolConfig,
+    stake: u64,
+    daily_reward: u64,
+    snapshot_total: u128,
+) -> u128 {
+    // u64 * u64 always fits in u128
+    let numerator = stake as u128 * daily_reward as u128;
+    if snapshot_total == 0 {
+        0
+    } else if config.round_up_rewards {
+        numerator.div_ceil(snapshot_total).saturating_mul(REWARD_SCALE)
+    } else {
+        scaled_share(numerator, snapshot_total)
+    }
+}
+
+/// Narrow a reward amount to u64, failing with MathOverflow rather than truncating.
+fn reward_to_u64(amount: u128) -> Result<u64> {
+    u64::try_from(amount).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
+/// Preview a stake's reward for `day`: recorded days (`day < snapshot_count`) use the same
+/// denominator `calculate_user_rewards` pays with; later days the latest recorded snapshot
+/// (or total_staked before the first one). Cliff days preview as 0.
+fn preview_day_reward(pool: &PoolState, user_stake: &UserStake, day: u64) -> Result<u64> {
+    if day < pool.config.reward_cliff_days {
+        return Ok(0);
+    }
+    let snapshot_count = pool.snapshot_count as u64;
+    let snapshot_total = if day < snapshot_count {
+        reward_denominator(pool, day as usize)
+    } else if snapshot_count > 0 {
+        pool.daily_snapshots[(snapshot_count - 1) as usize] as u128
+    } else {
+        pool.total_staked as u128
+    };
+
+    let scaled = reward_for_day(
+        &pool.config,
+        effective_stake(&pool.config, user_stake)?,
+        pool.day_reward(day as usize),
+        snapshot_total,
+    );
+    reward_to_u64(scaled / REWARD_SCALE)
+}
+
+/// Open (claimed, not yet unstaked) lock-weighted stake total:
+/// `total_staked + total_airdrop_claimed - total_liquid_claimed - airdrop_cap`.
+fn open_staked(pool: &PoolState) -> u128 {
+    (pool.total_staked as u128 + pool.total_airdrop_claimed as u128)
+        .saturating_sub(pool.total_liquid_claimed as u128)
+        .saturating_sub(pool.airdrop_cap() as u128)
+}
+
+/// Upper bound on rewards still owed to open stakes.
+///
+/// Each open stake's rewards are its day shares summed at REWARD_SCALE and floored once, so
+/// the open total's scaled shares, rounded up once, bound their sum; with `round_up_rewards`
+/// each stake can round up by at most one more unit a day, so `active_stakers` is added per
+/// day. Days not yet snapshotted reserve the full daily reward. Cliff days pay nothing and
+/// reserve nothing. Rewards open stakes already took via `withdraw_rewards` are not owed
+/// again and are deducted.
+fn outstanding_rewards_bound(pool: &PoolState) -> u64 {
+    let open_staked = open_staked(pool);
+    if open_staked == 0 {
+        return 0;
+    }
+
+    let rounding_slack = if pool.config.round_up_rewards {
+        pool.active_stakers as u128
+    } else {
+        0
+    };
+    let program_days = pool.program_days() as usize;
+    let cliff = (pool.config.reward_cliff_days as usize).min(program_days);
+    // Summed at REWARD_SCALE like calculate_user_rewards, then rounded up
+    let mut total_scaled: u128 = 0;
+    for d in cliff..program_days {
+        let daily = pool.day_reward(d) as u128;
+        let share = if d < pool.snapshot_count as usize {
+            scaled_share(open_staked.checked_mul(daily).unwrap(), reward_denominator(pool, d))
+                + rounding_slack * REWARD_SCALE
+        } else {
+            daily * REWARD_SCALE
+        };
+        total_scaled = total_scaled.checked_add(share).unwrap();
+    }
+    // Carried rewards of zero-stake days are still owed to a later staked day
+    let total = total_scaled
+        .div_ceil(REWARD_SCALE)
+        .checked_add(pool.carry_reward as u128)
+        .unwrap();
+
+    total
+        .saturating_sub(pool.open_rewards_withdrawn as u128)
+        .min(u64::MAX as u128) as u64
+}
+
+/// NFT gate for proof-based claims: when `required_collection` is set, the claimer must hold
+/// at least one token of a mint whose Metaplex metadata has that collection, verified.
+fn verify_nft_gate(
+    config: &PoolConfig,
+    user: &Pubkey,
+    nft_token_account: &Option<Account<TokenAccount>>,
+    nft_metadata: &Option<UncheckedAccount>,
+) -> Result<()> {
+    if config.required_collection == Pubkey::default() {
+        return Ok(());
+    }
+    let (Some(token_account), Some(metadata)) = (nft_token_account, nft_metadata) else {
+        return err!(ErrorCode::MissingRequiredNft);
+    };
+
+    let (metadata_key, _) = Pubkey::find_program_address(
+        &[
+            seeds::METADATA,
+            TOKEN_METADATA_PROGRAM_ID.as_ref(),
+            token_account.mint.as_ref(),
+        ],
+        &TOKEN_METADATA_PROGRAM_ID,
+    );
+    let holds_nft = token_account.owner == *user
+        && token_account.amount >= 1
+        && metadata.key() == metadata_key
+        && *metadata.owner == TOKEN_METADATA_PROGRAM_ID
+        && verified_collection(&metadata.try_borrow_data()?) == Some(config.required_collection);
+    require!(holds_nft, ErrorCode::MissingRequiredNft);
+    Ok(())
+}
+
+/// Verified collection key of a Metaplex metadata account, if any.
+///
+/// Walks the Borsh layout up to `collection`: key, update_authority, mint, name, symbol, uri,
+/// seller_fee_basis_points, creators, primary_sale_happened, is_mutable, edition_nonce,
+/// token_standard.
+fn verified_collection(data: &[u8]) -> Option<Pubkey> {
+    fn read_len(data: &[u8]) -> Option<usize> {
+        Some(u32::from_le_bytes(data.get(..4)?.try_into().ok()?) as usize)
+    }
+
+    let mut rest = data.get(1 + 32 + 32..)?;
+    // name, symbol, uri
+    for _ in 0..3 {
+        rest = rest.get(4 + read_len(rest)?..)?;
+    }
+    rest = rest.get(2..)?; // seller_fee_basis_points
+    // creators: Option<Vec<Creator>>, 34 bytes per creator
+    rest = match *rest.first()? {
+        1 => rest.get(5 + read_len(rest.get(1..)?)?.checked_mul(34)?..)?,
+        _ => rest.get(1..)?,
+    };
+    rest = rest.get(2..)?; // primary_sale_happened, is_mutable
+    // edition_nonce, token_standard: Option<u8>
+    for _ in 0..2 {
+        rest = rest.get(if *rest.first()? == 1 { 2 } else { 1 }..)?;
+    }
+    // collection: Option<{ verified: bool, key: Pubkey }>
+    if rest.get(..2)? != [1, 1] {
+        return None;
+    }
+    Pubkey::try_from(rest.get(2..34)?).ok()
+}
+
+/// Merkle leaf for an allocation under the pool's scheme:
+/// legacy `keccak(user || amount_le)`, indexed `keccak(prefix || index_le || user || amount_le)`.
+fn compute_leaf(scheme_version: u8, index: u64, user: &Pubkey, amount: u64) -> [u8; 32] {
+    if scheme_version == MERKLE_SCHEME_INDEXED {
+        keccak::hashv(&[
+            MERKLE_LEAF_PREFIX,
+            &index.to_le_bytes(),
+            user.as_ref(),
+            &amount.to_le_bytes(),
+        ])
+        .0
+    } else {
+        keccak::hashv(&[user.as_ref(), &amount.to_le_bytes()]).0
+    }
+}
+
+/// Verify a Merkle proof against a root. Proofs deeper than MAX_PROOF_DEPTH are rejected
+/// before any hashing.
+fn verify_merkle_proof(proof: &[[u8; 32]], root: &[u8; 32], leaf: &[u8; 32]) -> Result<bool> {
+    require!(proof.len() <= MAX_PROOF_DEPTH, ErrorCode::ProofTooLong);
+    let mut computed_hash = *leaf;
+    for node in proof.iter() {
+        if computed_hash <= *node {
+            computed_hash = keccak::hashv(&[&computed_hash, node]).0;
+        } else {
+            computed_hash = keccak::hashv(&[node, &computed_hash]).0;
+        }
+    }
+    Ok(computed_hash == *root)
+}
+
+
+// ── Accounts ───────────────────────────────────────────────────────────────────
+
+#[derive(Accounts)]
+pub struct InitializePool<'info> {
+    #[account(
+        mut,
+        constraint = admin.key() == INIT_AUTHORITY @ ErrorCode::Unauthorized,
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + PoolState::INIT_SPACE,
+        seeds = [seeds::POOL_STATE, token_mint.key().as_ref()],
+        bump,
+    )]
+    pub pool_state: Account<'info, PoolState>,
+
+    /// The token mint for this staking pool
+    pub token_mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = admin,
+        seeds = [seeds::POOL_TOKEN, pool_state.key().as_ref()],
+        bump,
+        token::mint = token_mint,
+        token::authority = pool_token_account,
+    )]
+    pub pool_token_account: Account<'info, TokenAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimAirdrop<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
+
+    /// Permanent marker that prevents re-claiming (tiny, ~0.001 SOL)
+    /// This account exists forever to prevent claim-unstake-reclaim attacks
+    #[account(
+        init,
+        payer = user,
+        space = 8 + ClaimMarker::INIT_SPACE,
+        seeds = [seeds::CLAIMED, pool_state.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub claim_marker: Account<'info, ClaimMarker>,
+
+    /// CHECK: Blacklist PDA for the claimer. Only its address (seeds) and emptiness are
+    /// inspected; a non-empty account means the claimer is blacklisted.
+    #[account(
+        seeds = [seeds::BLACKLIST, pool_state.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub blacklist_entry: UncheckedAccount<'info>,
+
+    /// Stake data, closed on unstake (user recovers rent).
+    /// May already exist (empty) if pre-created with open_stake_account; the
+    /// claim marker above guarantees it is never funded twice.
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + UserStake::INIT_SPACE,
+        seeds = [seeds::USER_STAKE, pool_state.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_stake: Account<'info, UserStake>,
+
+    /// Pool's token account - must match the one stored in pool_state
+    #[account(
+        mut,
+        constraint = pool_token_account.key() == pool_state.pool_token_account @ ErrorCode::InvalidPoolTokenAccount,
+    )]
+    pub pool_token_account: Account<'info, TokenAccount>,
+
+    /// User's token account to receive airdropped (and staked) tokens
+    #[account(
+        mut,
+        token::mint = pool_state.token_mint,
+        token::authority = user,
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    /// Claimer's token account holding an NFT of config.required_collection
+    /// (required only when the pool is NFT-gated)
+    pub nft_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: Metaplex metadata of nft_token_account's mint. Address, owner and collection
+    /// are verified in verify_nft_gate.
+    pub nft_metadata: Option<UncheckedAccount<'info>>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimAirdropBatch<'info> {
+    /// Submits the batch and pays rent for every created ClaimMarker and UserStake
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
+
+    /// Pool's token account - must match the one stored in pool_state
+    #[account(
+        mut,
+        constraint = pool_token_account.key() == pool_state.pool_token_account @ ErrorCode::InvalidPoolTokenAccount,
+    )]
+    pub pool_token_account: Account<'info, TokenAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimAirdropSigned<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
+
+    /// Permanent marker that prevents re-claiming (shared with claim_airdrop)
+    #[account(
+        init,
+        payer = user,
+        space = 8 + ClaimMarker::INIT_SPACE,
+        seeds = [seeds::CLAIMED, pool_state.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub claim_marker: Account<'info, ClaimMarker>,
+
+    /// CHECK: Blacklist PDA for the claimer; a non-empty account means the claimer is blacklisted.
+    #[account(
+        seeds = [seeds::BLACKLIST, pool_state.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub blacklist_entry: UncheckedAccount<'info>,
+
+    /// Stake data, closed on unstake (may already exist empty via open_stake_account)
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + UserStake::INIT_SPACE,
+        seeds = [seeds::USER_STAKE, pool_state.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_stake: Account<'info, UserStake>,
+
+    /// Pool's token account - must match the one stored in pool_state
+    #[account(
+        mut,
+        constraint = pool_token_account.key() == pool_state.pool_token_account @ ErrorCode::InvalidPoolTokenAccount,
+    )]
+    pub pool_token_account: Account<'info, TokenAccount>,
+
+    /// User's token account to receive airdropped (and staked) tokens
+    #[account(
+        mut,
+        token::mint = pool_state.token_mint,
+        token::authority = user,
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: Instructions sysvar, read to find the ed25519 verification before this instruction
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimAirdropLiquid<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
+
+    /// Permanent marker that prevents re-claiming (shared with claim_airdrop)
+    #[account(
+        init,
+        payer = user,
+        space = 8 + ClaimMarker::INIT_SPACE,
+        seeds = [seeds::CLAIMED, pool_state.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub claim_marker: Account<'info, ClaimMarker>,
+
+    /// CHECK: Blacklist PDA for the claimer; a non-empty account means the claimer is blacklisted.
+    #[account(
+        seeds = [seeds::BLACKLIST, pool_state.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub blacklist_entry: UncheckedAccount<'info>,
+
+    /// Pool's token account - must match the one stored in pool_state
+    #[account(
+        mut,
+        constraint = pool_token_account.key() == pool_state.pool_token_account @ ErrorCode::InvalidPoolTokenAccount,
+    )]
+    pub pool_token_account: Account<'info, TokenAccount>,
+
+    /// User's token account to receive the airdropped tokens
+    #[account(
+        mut,
+        token::mint = pool_state.token_mint,
+        token::authority = user,
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    /// Claimer's token account holding an NFT of config.required_collection
+    /// (required only when the pool is NFT-gated)
+    pub nft_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: Metaplex metadata of nft_token_account's mint. Address, owner and collection
+    /// are verified in verify_nft_gate.
+    pub nft_metadata: Option<UncheckedAccount<'info>>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct OpenStakeAccount<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
+
+    /// Empty stake data, funded later by claim_airdrop
+    #[account(
+        init,
+        payer = user,
+        space = 8 + UserStake::INIT_SPACE,
+        seeds = [seeds::USER_STAKE, pool_state.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_stake: Account<'info, UserStake>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Snapshot<'info> {
+    /// Anyone, unless `permissioned_snapshots` restricts cranking to `snapshot_authority`
+    #[account(
+        mut,
+        constraint = !pool_state.config.permissioned_snapshots
+            || signer.key() == pool_state.config.snapshot_authority @ ErrorCode::Unauthorized,
+    )]
+    pub signer: Signer<'info>,
+
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
+
+    /// Caller's last crank time (rate limit), created on the caller's first crank
+    #[account(
+        init_if_needed,
+        payer = signer,
+        space = 8 + CrankRecord::INIT_SPACE,
+        seeds = [seeds::CRANK, pool_state.key().as_ref(), signer.key().as_ref()],
+        bump,
+    )]
+    pub crank_record: Account<'info, CrankRecord>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AdminSetSnapshot<'info> {
+    /// Must be the pool admin to correct a snapshot
+    #[account(
+        constraint = admin.key() == pool_state.admin @ ErrorCode::UnauthorizedAdmin,
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
+}
+
+#[derive(Accounts)]
+pub struct Unstake<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
+
+    /// User's stake account - will be closed and rent returned
+    #[account(
+        mut,
+        seeds = [seeds::USER_STAKE, pool_state.key().as_ref(), user.key().as_ref()],
+        bump = user_stake.bump,
+        constraint = user_stake.owner == user.key() @ ErrorCode::InvalidStakeOwner,
+        close = user,
+    )]
+    pub user_stake: Account<'info, UserStake>,
+
+    /// Pool's token account - must match the one stored in pool_state
+    #[account(
+        mut,
+        constraint = pool_token_account.key() == pool_state.pool_token_account @ ErrorCode::InvalidPoolTokenAccount,
+    )]
+    pub pool_token_account: Account<'info, TokenAccount>,
+
+    /// Pool's token mint - needed to create the user's ATA if missing
+    #[account(address = pool_state.token_mint)]
+    pub token_mint: Account<'info, Mint>,
+
+    /// User's associated token account to receive staking rewards - created if missing
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = token_mint,
+        associated_token::authority = user,
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    /// Reward pool token account (required when the pool pays rewards in a separate reward_mint)
+    #[account(
+        mut,
+        constraint = reward_pool_token_account.key() == pool_state.reward_pool_token_account @ ErrorCode::InvalidPoolTokenAccount,
+    )]
+    pub reward_pool_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// User's reward_mint token account (required when the pool pays rewards in a separate reward_mint)
+    #[account(
+        mut,
+        token::mint = pool_state.reward_mint,
+        token::authority = user,
+    )]
+    pub user_reward_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// Treasury's reward token account to receive the reward fee (required when reward_fee_bps > 0)
+    #[account(
+        mut,
+        token::mint = pool_state.reward_mint(),
+        token::authority = pool_state.config.treasury,
+    )]
+    pub treasury_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// Referrer's token account to receive the referral bonus (skipped when absent)
+    #[account(
+        mut,
+        token::mint = pool_state.token_mint,
+        token::authority = user_stake.referrer,
+    )]
+    pub referrer_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(destination: Pubkey)]
+pub struct UnstakeTo<'info> {
+    /// Stake owner; authorizes the exit and gets the stake account's rent back
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
+
+    #[account(
+        mut,
+        seeds = [seeds::USER_STAKE, pool_state.key().as_ref(), user.key().as_ref()],
+        bump = user_stake.bump,
+        constraint = user_stake.owner == user.key() @ ErrorCode::InvalidStakeOwner,
+        close = user,
+    )]
+    pub user_stake: Account<'info, UserStake>,
+
+    #[account(
+        mut,
+        constraint = pool_token_account.key() == pool_state.pool_token_account @ ErrorCode::InvalidPoolTokenAccount,
+    )]
+    pub pool_token_account: Account<'info, TokenAccount>,
+
+    /// Token account receiving the rewards; any owner, but it must hold the reward mint
+    #[account(
+        mut,
+        address = destination,
+        token::mint = pool_state.reward_mint(),
+    )]
+    pub destination_token_account: Account<'info, TokenAccount>,
+
+    /// Reward pool token account (required when the pool pays rewards in a separate reward_mint)
+    #[account(
+        mut,
+        constraint = reward_pool_token_account.key() == pool_state.reward_pool_token_account @ ErrorCode::InvalidPoolTokenAccount,
+    )]
+    pub reward_pool_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// Treasury's reward token account to receive the reward fee (required when reward_fee_bps > 0)
+    #[account(
+        mut,
+        token::mint = pool_state.reward_mint(),
+        token::authority = pool_state.config.treasury,
+    )]
+    pub treasury_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// Referrer's token account to receive the referral bonus (skipped when absent)
+    #[account(
+        mut,
+        token::mint = pool_state.token_mint,
+        token::authority = user_stake.referrer,
+    )]
+    pub referrer_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawRewards<'info> {
+    /// Stake owner or its harvest delegate
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
+
+    /// User's stake account - stays open, watermark advanced
+    #[account(
+        mut,
+        seeds = [seeds::USER_STAKE, pool_state.key().as_ref(), user_stake.owner.as_ref()],
+        bump = user_stake.bump,
+        constraint = user_stake.owner == user.key()
+            || (user_stake.harvest_delegate != Pubkey::default()
+                && user_stake.harvest_delegate == user.key()) @ ErrorCode::InvalidStakeOwner,
+    )]
+    pub user_stake: Account<'info, UserStake>,
+
+    /// Pool's token account - must match the one stored in pool_state
+    #[account(
+        mut,
+        constraint = pool_token_account.key() == pool_state.pool_token_account @ ErrorCode::InvalidPoolTokenAccount,
+    )]
+    pub pool_token_account: Account<'info, TokenAccount>,
+
+    /// Owner's token account to receive the withdrawn rewards
+    #[account(
+        mut,
+        token::mint = pool_state.token_mint,
+        token::authority = user_stake.owner,
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    /// Reward pool token account (required when the pool pays rewards in a separate reward_mint)
+    #[account(
+        mut,
+        constraint = reward_pool_token_account.key() == pool_state.reward_pool_token_account @ ErrorCode::InvalidPoolTokenAccount,
+    )]
+    pub reward_pool_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// Owner's reward_mint token account (required when the pool pays rewards in a separate reward_mint)
+    #[account(
+        mut,
+        token::mint = pool_state.reward_mint,
+        token::authority = user_stake.owner,
+    )]
+    pub user_reward_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// Treasury's reward token account to receive the reward fee (required when reward_fee_bps > 0)
+    #[account(
+        mut,
+        token::mint = pool_state.reward_mint(),
+        token::authority = pool_state.config.treasury,
+    )]
+    pub treasury_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct SetHarvestDelegate<'info> {
+    pub user: Signer<'info>,
+
+    pub pool_state: Account<'info, PoolState>,
+
+    /// User's stake account - only its owner can change the delegate
+    #[account(
+        mut,
+        seeds = [seeds::USER_STAKE, pool_state.key().as_ref(), user.key().as_ref()],
+        bump = user_stake.bump,
+        constraint = user_stake.owner == user.key() @ ErrorCode::InvalidStakeOwner,
+    )]
+    pub user_stake: Account<'info, UserStake>,
+}
+
+#[derive(Accounts)]
+pub struct CloseEmptyStake<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
+
+    /// User's empty stake account - will be closed and rent returned
+    #[account(
+        mut,
+        seeds = [seeds::USER_STAKE, pool_state.key().as_ref(), user.key().as_ref()],
+        bump = user_stake.bump,
+        constraint = user_stake.owner == user.key() @ ErrorCode::InvalidStakeOwner,
+        close = user,
+    )]
+    pub user_stake: Account<'info, UserStake>,
+}
+
+#[derive(Accounts)]
+pub struct CloseClaimMarkerTerminated<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub pool_state: Account<'info, PoolState>,
+
+    /// User's claim marker - will be closed and rent returned
+    #[account(
+        mut,
+        seeds = [seeds::CLAIMED, pool_state.key().as_ref(), user.key().as_ref()],
+        bump = claim_marker.bump,
+        close = user,
+    )]
+    pub claim_marker: Account<'info, ClaimMarker>,
+}
+
+#[derive(Accounts)]
+pub struct CloseCrankRecord<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+
+    pub pool_state: Account<'info, PoolState>,
+
+    /// Caller's crank record - will be closed and rent returned
+    #[account(
+        mut,
+        seeds = [seeds::CRANK, pool_state.key().as_ref(), signer.key().as_ref()],
+        bump = crank_record.bump,
+        close = signer,
+    )]
+    pub crank_record: Account<'info, CrankRecord>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateUserStake<'info> {
+    /// Pays any rent the larger layout needs
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub pool_state: Account<'info, PoolState>,
+
+    /// CHECK: only used to derive the UserStake PDA
+    pub user: UncheckedAccount<'info>,
+
+    /// CHECK: a UserStake in a possibly older layout, which `Account` could not deserialize;
+    /// ownership and address are checked here and the discriminator on deserialization
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [seeds::USER_STAKE, pool_state.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_stake: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CalculateRewards<'info> {
+    pub pool_state: Account<'info, PoolState>,
+
+    /// User's stake account - read-only for reward calculation
+    #[account(
+        seeds = [seeds::USER_STAKE, pool_state.key().as_ref(), user_stake.owner.as_ref()],
+        bump = user_stake.bump,
+    )]
+    pub user_stake: Account<'info, UserStake>,
+}
+
+#[derive(Accounts)]
+pub struct EmitStateCheckpoint<'info> {
+    pub pool_state: Account<'info, PoolState>,
+}
+
+#[derive(Accounts)]
+pub struct GetPoolStats<'info> {
+    pub pool_state: Account<'info, PoolState>,
+
+    /// Pool's token account - must match the one stored in pool_state
+    #[account(
+        constraint = pool_token_account.key() == pool_state.pool_token_account @ ErrorCode::InvalidPoolTokenAccount,
+    )]
+    pub pool_token_account: Account<'info, TokenAccount>,
+}
+
+#[derive(Accounts)]
+pub struct GetUserStake<'info> {
+    pub pool_state: Account<'info, PoolState>,
+
+    /// User's stake account - read-only
+    #[account(
+        seeds = [seeds::USER_STAKE, pool_state.key().as_ref(), user_stake.owner.as_ref()],
+        bump = user_stake.bump,
+    )]
+    pub user_stake: Account<'info, UserStake>,
+}
+
+#[derive(Accounts)]
+pub struct EstimateApr<'info> {
+    pub pool_state: Account<'info, PoolState>,
+}
+
+#[derive(Accounts)]
+pub struct EstimateFinalPayout<'info> {
+    pub pool_state: Account<'info, PoolState>,
+
+    /// User's stake account - read-only
+    #[account(
+        seeds = [seeds::USER_STAKE, pool_state.key().as_ref(), user_stake.owner.as_ref()],
+        bump = user_stake.bump,
+    )]
+    pub user_stake: Account<'info, UserStake>,
+}
+
+#[derive(Accounts)]
+pub struct GetPhase<'info> {
+    pub pool_state: Account<'info, PoolState>,
+}
+
+#[derive(Accounts)]
+pub struct DebugClaim<'info> {
+    /// CHECK: Claimer being diagnosed; only its key is used, no signature needed
+    pub user: UncheckedAccount<'info>,
+
+    pub pool_state: Account<'info, PoolState>,
+
+    /// CHECK: ClaimMarker PDA for the claimer; only its emptiness is inspected
+    #[account(
+        seeds = [seeds::CLAIMED, pool_state.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub claim_marker: UncheckedAccount<'info>,
+
+    /// CHECK: Blacklist PDA for the claimer; only its emptiness is inspected
+    #[account(
+        seeds = [seeds::BLACKLIST, pool_state.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub blacklist_entry: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct IsExpired<'info> {
+    pub pool_state: Account<'info, PoolState>,
+}
+
+#[derive(Accounts)]
+pub struct GetMerkleRoot<'info> {
+    pub pool_state: Account<'info, PoolState>,
+}
+
+#[derive(Accounts)]
+pub struct GetFlags<'info> {
+    pub pool_state: Account<'info, PoolState>,
+}
+
+#[derive(Accounts)]
+pub struct GetSnapshots<'info> {
+    pub pool_state: Account<'info, PoolState>,
+}
+
+#[derive(Accounts)]
+pub struct IsClaimed<'info> {
+    pub pool_state: Account<'info, PoolState>,
+
+    /// CHECK: Address whose claim status is queried; only its key is used (seeds)
+    pub user: UncheckedAccount<'info>,
+
+    /// CHECK: ClaimMarker PDA for the user. Only its address (seeds) and emptiness are
+    /// inspected, so a marker that was never created is accepted.
+    #[account(
+        seeds = [seeds::CLAIMED, pool_state.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub claim_marker: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetClaimStatus<'info> {
+    pub pool_state: Account<'info, PoolState>,
+
+    /// CHECK: Address whose status is queried; only its key is used (seeds)
+    pub user: UncheckedAccount<'info>,
+
+    /// CHECK: ClaimMarker PDA for the user; only its emptiness is inspected
+    #[account(
+        seeds = [seeds::CLAIMED, pool_state.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub claim_marker: UncheckedAccount<'info>,
+
+    /// CHECK: UserStake PDA for the user; deserialized (discriminator-checked) when it exists
+    #[account(
+        seeds = [seeds::USER_STAKE, pool_state.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_stake: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetDailyClaimed<'info> {
+    pub pool_state: Account<'info, PoolState>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyProof<'info> {
+    pub pool_state: Account<'info, PoolState>,
+}
+
+#[derive(Accounts)]
+pub struct RecoverExpiredRewards<'info> {
+    /// Must be the pool admin to recover tokens
+    #[account(
+        mut,
+        constraint = admin.key() == pool_state.admin @ ErrorCode::UnauthorizedAdmin,
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
+
+    /// Accumulated admin approvals (required when pool_state.required_approvals > 0), consumed here
+    #[account(
+        mut,
+        seeds = [seeds::APPROVAL, pool_state.key().as_ref(), &[actions::RECOVER]],
+        bump = approval.bump,
+        close = admin,
+    )]
+    pub approval: Option<Account<'info, ActionApproval>>,
+
+    /// Pool's token account - must match the one stored in pool_state
+    #[account(
+        mut,
+        constraint = pool_token_account.key() == pool_state.pool_token_account @ ErrorCode::InvalidPoolTokenAccount,
+    )]
+    pub pool_token_account: Account<'info, TokenAccount>,
+
+    /// Pool's token mint - passed to transfer_checked
+    #[account(address = pool_state.token_mint)]
+    pub token_mint: Account<'info, Mint>,
+
+    /// Token account of token_mint to receive recovered tokens (the admin's own or e.g. a treasury)
+    #[account(
+        mut,
+        constraint = destination_token_account.mint == pool_state.token_mint @ ErrorCode::InvalidAdminTokenMint,
+    )]
+    pub destination_token_account: Account<'info, TokenAccount>,
+
+    /// Reward pool token account (required when the pool pays rewards in a separate reward_mint)
+    #[account(
+        mut,
+        constraint = reward_pool_token_account.key() == pool_state.reward_pool_token_account @ ErrorCode::InvalidPoolTokenAccount,
+    )]
+    pub reward_pool_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// reward_mint token account to receive recovered rewards (required when the pool pays
+    /// rewards in a separate reward_mint)
+    #[account(
+        mut,
+        token::mint = pool_state.reward_mint,
+    )]
+    pub destination_reward_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+}
 
-    let transfer_ctx = CpiContext::new_with_signer(
-        token_program.to_account_info(),
-        Transfer {
-            from: pool_token_account.to_account_info(),
-            to: destination_token_account.to_account_info(),
-            authority: pool_token_account.to_account_info(),
-        },
-        signer_seeds,
-    );
-    token::transfer(transfer_ctx, amount)
+#[derive(Accounts)]
+pub struct InitiateTermination<'info> {
+    /// Must be the pool admin to terminate
+    #[account(
+        mut,
+        constraint = admin.key() == pool_state.admin @ ErrorCode::UnauthorizedAdmin,
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
+
+    /// Accumulated admin approvals (required when pool_state.required_approvals > 0), consumed here
+    #[account(
+        mut,
+        seeds = [seeds::APPROVAL, pool_state.key().as_ref(), &[actions::TERMINATE]],
+        bump = approval.bump,
+        close = admin,
+    )]
+    pub approval: Option<Account<'info, ActionApproval>>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteDrain<'info> {
+    /// Must be the pool admin to drain
+    #[account(
+        mut,
+        constraint = admin.key() == pool_state.admin @ ErrorCode::UnauthorizedAdmin,
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
+
+    /// Accumulated admin approvals (required when pool_state.required_approvals > 0), consumed here
+    #[account(
+        mut,
+        seeds = [seeds::APPROVAL, pool_state.key().as_ref(), &[actions::DRAIN]],
+        bump = approval.bump,
+        close = admin,
+    )]
+    pub approval: Option<Account<'info, ActionApproval>>,
+
+    /// Pool's token account - must match the one stored in pool_state
+    #[account(
+        mut,
+        constraint = pool_token_account.key() == pool_state.pool_token_account @ ErrorCode::InvalidPoolTokenAccount,
+    )]
+    pub pool_token_account: Account<'info, TokenAccount>,
+
+    /// Pool's token mint - passed to transfer_checked
+    #[account(address = pool_state.token_mint)]
+    pub token_mint: Account<'info, Mint>,
+
+    /// Token account of token_mint to receive drained tokens (the admin's own or e.g. a treasury)
+    #[account(
+        mut,
+        constraint = destination_token_account.mint == pool_state.token_mint @ ErrorCode::InvalidAdminTokenMint,
+    )]
+    pub destination_token_account: Account<'info, TokenAccount>,
+
+    /// Reward pool token account (required when the pool pays rewards in a separate reward_mint)
+    #[account(
+        mut,
+        constraint = reward_pool_token_account.key() == pool_state.reward_pool_token_account @ ErrorCode::InvalidPoolTokenAccount,
+    )]
+    pub reward_pool_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// reward_mint token account to receive recovered rewards (required when the pool pays
+    /// rewards in a separate reward_mint)
+    #[account(
+        mut,
+        token::mint = pool_state.reward_mint,
+    )]
+    pub destination_reward_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct TerminatePoolSplit<'info> {
+    /// Must be the pool admin to drain
+    #[account(
+        mut,
+        constraint = admin.key() == pool_state.admin @ ErrorCode::UnauthorizedAdmin,
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
+
+    /// Accumulated admin approvals for DRAIN (required when pool_state.required_approvals > 0)
+    #[account(
+        mut,
+        seeds = [seeds::APPROVAL, pool_state.key().as_ref(), &[actions::DRAIN]],
+        bump = approval.bump,
+        close = admin,
+    )]
+    pub approval: Option<Account<'info, ActionApproval>>,
+
+    /// Pool's token account - must match the one stored in pool_state
+    #[account(
+        mut,
+        constraint = pool_token_account.key() == pool_state.pool_token_account @ ErrorCode::InvalidPoolTokenAccount,
+    )]
+    pub pool_token_account: Account<'info, TokenAccount>,
+
+    /// Pool's token mint - passed to transfer_checked
+    #[account(address = pool_state.token_mint)]
+    pub token_mint: Account<'info, Mint>,
+
+    /// Token account of token_mint to receive the non-treasury share (the admin's own or any
+    /// other, as in execute_drain)
+    #[account(
+        mut,
+        constraint = destination_token_account.mint == pool_state.token_mint @ ErrorCode::InvalidAdminTokenMint,
+    )]
+    pub destination_token_account: Account<'info, TokenAccount>,
+
+    /// Treasury's token account to receive the treasury_bps share
+    #[account(
+        mut,
+        token::mint = pool_state.token_mint,
+        token::authority = pool_state.config.treasury,
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct PausePool<'info> {
+    /// Must be the pool admin to pause/unpause
+    #[account(
+        constraint = admin.key() == pool_state.admin @ ErrorCode::UnauthorizedAdmin,
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
+}
+
+#[derive(Accounts)]
+pub struct UnpausePool<'info> {
+    /// The pool admin or the config's guardian
+    #[account(
+        constraint = admin.key() == pool_state.admin
+            || admin.key() == pool_state.config.guardian @ ErrorCode::UnauthorizedAdmin,
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
+}
+
+#[derive(Accounts)]
+pub struct FreezeAccrual<'info> {
+    /// Must be the pool admin to freeze/unfreeze accrual
+    #[account(
+        constraint = admin.key() == pool_state.admin @ ErrorCode::UnauthorizedAdmin,
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
+}
+
+#[derive(Accounts)]
+#[instruction(user: Pubkey)]
+pub struct FreezeStake<'info> {
+    /// Must be the pool admin to place or lift a compliance hold
+    #[account(
+        constraint = admin.key() == pool_state.admin @ ErrorCode::UnauthorizedAdmin,
+    )]
+    pub admin: Signer<'info>,
+
+    pub pool_state: Account<'info, PoolState>,
+
+    #[account(
+        mut,
+        seeds = [seeds::USER_STAKE, pool_state.key().as_ref(), user.as_ref()],
+        bump = user_stake.bump,
+    )]
+    pub user_stake: Account<'info, UserStake>,
+}
+
+#[derive(Accounts)]
+pub struct ExtendClaimWindow<'info> {
+    /// Must be the pool admin to extend the claim window
+    #[account(
+        constraint = admin.key() == pool_state.admin @ ErrorCode::UnauthorizedAdmin,
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
+}
+
+#[derive(Accounts)]
+pub struct ResizePoolState<'info> {
+    /// Must be the pool admin to resize the pool; pays for any added rent
+    #[account(
+        mut,
+        constraint = admin.key() == pool_state.admin @ ErrorCode::UnauthorizedAdmin,
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        realloc = 8 + PoolState::INIT_SPACE,
+        realloc::payer = admin,
+        realloc::zero = false,
+    )]
+    pub pool_state: Account<'info, PoolState>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExtendProgram<'info> {
+    /// Must be the pool admin to extend the program; funds the added rewards
+    #[account(
+        constraint = admin.key() == pool_state.admin @ ErrorCode::UnauthorizedAdmin,
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
+
+    /// Pool's token account - must match the one stored in pool_state
+    #[account(
+        mut,
+        constraint = pool_token_account.key() == pool_state.pool_token_account @ ErrorCode::InvalidPoolTokenAccount,
+    )]
+    pub pool_token_account: Account<'info, TokenAccount>,
+
+    /// Receives the added rewards instead when rewards are paid in a separate mint
+    #[account(
+        mut,
+        constraint = reward_pool_token_account.key() == pool_state.reward_pool_token_account @ ErrorCode::InvalidPoolTokenAccount,
+    )]
+    pub reward_pool_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// Admin's token account the added rewards are paid from
+    #[account(
+        mut,
+        token::mint = pool_state.reward_mint(),
+        token::authority = admin,
+    )]
+    pub admin_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct RebalanceDailyRewards<'info> {
+    /// Must be the pool admin to rebalance rewards
+    #[account(
+        constraint = admin.key() == pool_state.admin @ ErrorCode::UnauthorizedAdmin,
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
+
+    /// Pool's token account - must match the one stored in pool_state
+    #[account(
+        mut,
+        constraint = pool_token_account.key() == pool_state.pool_token_account @ ErrorCode::InvalidPoolTokenAccount,
+    )]
+    pub pool_token_account: Account<'info, TokenAccount>,
+
+    /// Admin's token account to receive the freed rewards
+    #[account(
+        mut,
+        token::mint = pool_state.token_mint,
+        token::authority = admin,
+    )]
+    pub admin_token_account: Account<'info, TokenAccount>,
+
+    /// Reward pool token account (required when the pool pays rewards in a separate reward_mint)
+    #[account(
+        mut,
+        constraint = reward_pool_token_account.key() == pool_state.reward_pool_token_account @ ErrorCode::InvalidPoolTokenAccount,
+    )]
+    pub reward_pool_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// Admin's reward_mint token account (required when the pool pays rewards in a separate reward_mint)
+    #[account(
+        mut,
+        token::mint = pool_state.reward_mint,
+        token::authority = admin,
+    )]
+    pub admin_reward_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
 }
 
-/// Returns the unix timestamp when the claim window ends (day 40).
-pub fn claim_window_end(start_time: i64) -> i64 {
-    start_time + (CLAIM_WINDOW_DAYS as i64 * SECONDS_PER_DAY as i64)
-}
+#[derive(Accounts)]
+pub struct FundReferralBudget<'info> {
+    /// Must be the pool admin to fund the referral budget
+    #[account(
+        constraint = admin.key() == pool_state.admin @ ErrorCode::UnauthorizedAdmin,
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
+
+    /// Pool's token account - must match the one stored in pool_state
+    #[account(
+        mut,
+        constraint = pool_token_account.key() == pool_state.pool_token_account @ ErrorCode::InvalidPoolTokenAccount,
+    )]
+    pub pool_token_account: Account<'info, TokenAccount>,
 
-/// Returns the actual elapsed day since pool start (uncapped).
-/// Day 0 = first 86400s, Day 1 = next 86400s, etc.
-/// Call sites must cap to TOTAL_DAYS explicitly where needed for array indexing.
-pub fn get_current_day(start_time: i64, now: i64) -> u64 {
-    if now <= start_time {
-        return 0;
-    }
-    ((now - start_time) as u64) / SECONDS_PER_DAY
+    /// Admin's token account the budget is paid from
+    #[account(
+        mut,
+        token::mint = pool_state.token_mint,
+        token::authority = admin,
+    )]
+    pub admin_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
 }
 
-/// Calculate total accumulated rewards for a user across all snapshotted days.
-fn calculate_user_rewards(
-    staked_amount: u64,
-    current_day: u64,
-    daily_rewards: &[u64; 32],
-    daily_snapshots: &[u64; 32],
-) -> u64 {
-    let mut total_rewards: u128 = 0;
+#[derive(Accounts)]
+pub struct Donate<'info> {
+    pub donor: Signer<'info>,
 
-    for d in 0..(current_day as usize) {
-        let snapshot_total = daily_snapshots[d] as u128;
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
 
-        let user_share = (staked_amount as u128)
-            .checked_mul(daily_rewards[d] as u128)
-            .unwrap()
-            .checked_div(snapshot_total)
-            .unwrap_or(0);
+    /// Pool's token account - must match the one stored in pool_state
+    #[account(
+        mut,
+        constraint = pool_token_account.key() == pool_state.pool_token_account @ ErrorCode::InvalidPoolTokenAccount,
+    )]
+    pub pool_token_account: Account<'info, TokenAccount>,
 
-        total_rewards = total_rewards.checked_add(user_share).unwrap();
-    }
+    /// Reward pool token account (required when the pool pays rewards in a separate reward_mint)
+    #[account(
+        mut,
+        constraint = reward_pool_token_account.key() == pool_state.reward_pool_token_account @ ErrorCode::InvalidPoolTokenAccount,
+    )]
+    pub reward_pool_token_account: Option<Account<'info, TokenAccount>>,
 
-    total_rewards as u64
-}
+    /// Donor's token account of the reward mint the donation is paid from
+    #[account(
+        mut,
+        token::mint = pool_state.reward_mint(),
+        token::authority = donor,
+    )]
+    pub donor_token_account: Account<'info, TokenAccount>,
 
-/// Verify a Merkle proof against a root.
-fn verify_merkle_proof(proof: &[[u8; 32]], root: &[u8; 32], leaf: &[u8; 32]) -> bool {
-    let mut computed_hash = *leaf;
-    for node in proof.iter() {
-        if computed_hash <= *node {
-            computed_hash = keccak::hashv(&[&computed_hash, node]).0;
-        } else {
-            computed_hash = keccak::hashv(&[node, &computed_hash]).0;
-        }
-    }
-    computed_hash == *root
+    pub token_program: Program<'info, Token>,
 }
 
+#[derive(Accounts)]
+pub struct IncreaseAirdropCap<'info> {
+    /// Must be the pool admin to raise the airdrop cap
+    #[account(
+        constraint = admin.key() == pool_state.admin @ ErrorCode::UnauthorizedAdmin,
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
 
-// ── Accounts ───────────────────────────────────────────────────────────────────
+    /// Pool's token account - must match the one stored in pool_state
+    #[account(
+        constraint = pool_token_account.key() == pool_state.pool_token_account @ ErrorCode::InvalidPoolTokenAccount,
+    )]
+    pub pool_token_account: Account<'info, TokenAccount>,
+}
 
 #[derive(Accounts)]
-pub struct InitializePool<'info> {
+pub struct SetRewardMint<'info> {
+    /// Must be the pool admin to change the reward mint
     #[account(
         mut,
-        constraint = admin.key() == INIT_AUTHORITY @ ErrorCode::Unauthorized,
+        constraint = admin.key() == pool_state.admin @ ErrorCode::UnauthorizedAdmin,
     )]
     pub admin: Signer<'info>,
 
-    #[account(
-        init,
-        payer = admin,
-        space = 8 + PoolState::INIT_SPACE,
-        seeds = [seeds::POOL_STATE, token_mint.key().as_ref()],
-        bump,
-    )]
+    #[account(mut)]
     pub pool_state: Account<'info, PoolState>,
 
-    /// The token mint for this staking pool
-    pub token_mint: Account<'info, Mint>,
+    /// Mint rewards are paid in; must differ from the airdrop token
+    #[account(constraint = reward_mint.key() != pool_state.token_mint @ ErrorCode::InvalidRewardMint)]
+    pub reward_mint: Account<'info, Mint>,
 
     #[account(
         init,
         payer = admin,
-        seeds = [seeds::POOL_TOKEN, pool_state.key().as_ref()],
+        seeds = [seeds::REWARD_POOL_TOKEN, pool_state.key().as_ref()],
         bump,
-        token::mint = token_mint,
-        token::authority = pool_token_account,
+        token::mint = reward_mint,
+        token::authority = reward_pool_token_account,
     )]
-    pub pool_token_account: Account<'info, TokenAccount>,
+    pub reward_pool_token_account: Account<'info, TokenAccount>,
 
     pub system_program: Program<'info, System>,
     pub token_program: Program<'info, Token>,
-    pub rent: Sysvar<'info, Rent>,
 }
 
 #[derive(Accounts)]
-pub struct ClaimAirdrop<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
-
-    #[account(mut)]
-    pub pool_state: Account<'info, PoolState>,
-
-    /// Permanent marker that prevents re-claiming (tiny, ~0.001 SOL)
-    /// This account exists forever to prevent claim-unstake-reclaim attacks
+pub struct ActivatePool<'info> {
+    /// Must be the pool admin to activate the pool
     #[account(
-        init,
-        payer = user,
-        space = 8 + ClaimMarker::INIT_SPACE,
-        seeds = [seeds::CLAIMED, pool_state.key().as_ref(), user.key().as_ref()],
-        bump,
+        constraint = admin.key() == pool_state.admin @ ErrorCode::UnauthorizedAdmin,
     )]
-    pub claim_marker: Account<'info, ClaimMarker>,
+    pub admin: Signer<'info>,
 
-    /// Stake data, closed on unstake (user recovers rent)
-    #[account(
-        init,
-        payer = user,
-        space = 8 + UserStake::INIT_SPACE,
-        seeds = [seeds::USER_STAKE, pool_state.key().as_ref(), user.key().as_ref()],
-        bump,
-    )]
-    pub user_stake: Account<'info, UserStake>,
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
 
-    /// Pool's token account - must match the one stored in pool_state
     #[account(
-        mut,
         constraint = pool_token_account.key() == pool_state.pool_token_account @ ErrorCode::InvalidPoolTokenAccount,
     )]
     pub pool_token_account: Account<'info, TokenAccount>,
 
-    /// User's token account to receive airdropped (and staked) tokens
+    /// Required when rewards are paid in a separate mint
     #[account(
-        mut,
-        token::mint = pool_state.token_mint,
-        token::authority = user,
+        constraint = reward_pool_token_account.key() == pool_state.reward_pool_token_account @ ErrorCode::InvalidPoolTokenAccount,
     )]
-    pub user_token_account: Account<'info, TokenAccount>,
-
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
+    pub reward_pool_token_account: Option<Account<'info, TokenAccount>>,
 }
 
 #[derive(Accounts)]
-pub struct Snapshot<'info> {
-    pub signer: Signer<'info>,
+pub struct ConfigureApprovals<'info> {
+    /// Must be the pool admin to configure approvals
+    #[account(
+        constraint = admin.key() == pool_state.admin @ ErrorCode::UnauthorizedAdmin,
+    )]
+    pub admin: Signer<'info>,
 
     #[account(mut)]
     pub pool_state: Account<'info, PoolState>,
 }
 
 #[derive(Accounts)]
-pub struct Unstake<'info> {
+#[instruction(action: u8)]
+pub struct ApproveAction<'info> {
+    /// Must be one of pool_state.admins (checked in the handler)
     #[account(mut)]
-    pub user: Signer<'info>,
+    pub approver: Signer<'info>,
 
-    #[account(mut)]
     pub pool_state: Account<'info, PoolState>,
 
-    /// User's stake account - will be closed and rent returned
+    /// Approval accumulator for this action, created by the first approver
     #[account(
-        mut,
-        seeds = [seeds::USER_STAKE, pool_state.key().as_ref(), user.key().as_ref()],
-        bump = user_stake.bump,
-        constraint = user_stake.owner == user.key() @ ErrorCode::InvalidStakeOwner,
-        close = user,
+        init_if_needed,
+        payer = approver,
+        space = 8 + ActionApproval::INIT_SPACE,
+        seeds = [seeds::APPROVAL, pool_state.key().as_ref(), &[action]],
+        bump,
     )]
-    pub user_stake: Account<'info, UserStake>,
+    pub approval: Account<'info, ActionApproval>,
 
-    /// Pool's token account - must match the one stored in pool_state
-    #[account(
-        mut,
-        constraint = pool_token_account.key() == pool_state.pool_token_account @ ErrorCode::InvalidPoolTokenAccount,
-    )]
-    pub pool_token_account: Account<'info, TokenAccount>,
+    pub system_program: Program<'info, System>,
+}
 
-    /// User's token account to receive staking rewards
+#[derive(Accounts)]
+pub struct SetClaimDeadline<'info> {
+    /// Must be the pool admin to set the claim deadline
     #[account(
-        mut,
-        token::mint = pool_state.token_mint,
-        token::authority = user,
+        constraint = admin.key() == pool_state.admin @ ErrorCode::UnauthorizedAdmin,
     )]
-    pub user_token_account: Account<'info, TokenAccount>,
+    pub admin: Signer<'info>,
 
-    pub token_program: Program<'info, Token>,
+    #[account(mut)]
+    pub pool_state: Account<'info, PoolState>,
 }
 
 #[derive(Accounts)]
-pub struct CalculateRewards<'info> {
+#[instruction(address: Pubkey)]
+pub struct BlacklistAddress<'info> {
+    /// Must be the pool admin to manage the blacklist
+    #[account(
+        mut,
+        constraint = admin.key() == pool_state.admin @ ErrorCode::UnauthorizedAdmin,
+    )]
+    pub admin: Signer<'info>,
+
     pub pool_state: Account<'info, PoolState>,
 
-    /// User's stake account - read-only for reward calculation
     #[account(
-        seeds = [seeds::USER_STAKE, pool_state.key().as_ref(), user_stake.owner.as_ref()],
-        bump = user_stake.bump,
+        init,
+        payer = admin,
+        space = 8 + Blacklist::INIT_SPACE,
+        seeds = [seeds::BLACKLIST, pool_state.key().as_ref(), address.as_ref()],
+        bump,
     )]
-    pub user_stake: Account<'info, UserStake>,
+    pub blacklist_entry: Account<'info, Blacklist>,
+
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct RecoverExpiredRewards<'info> {
-    /// Must be the pool admin to recover tokens
+#[instruction(address: Pubkey)]
+pub struct UnblacklistAddress<'info> {
+    /// Must be the pool admin to manage the blacklist
     #[account(
+        mut,
         constraint = admin.key() == pool_state.admin @ ErrorCode::UnauthorizedAdmin,
     )]
     pub admin: Signer<'info>,
 
-    #[account(mut)]
     pub pool_state: Account<'info, PoolState>,
 
-    /// Pool's token account - must match the one stored in pool_state
+    /// Blacklist entry - closed and rent returned to admin
     #[account(
         mut,
-        constraint = pool_token_account.key() == pool_state.pool_token_account @ ErrorCode::InvalidPoolTokenAccount,
+        seeds = [seeds::BLACKLIST, pool_state.key().as_ref(), address.as_ref()],
+        bump = blacklist_entry.bump,
+        close = admin,
     )]
-    pub pool_token_account: Account<'info, TokenAccount>,
+    pub blacklist_entry: Account<'info, Blacklist>,
+}
+
+// ── State ──────────────────────────────────────────────────────────────────────
+
+/// Pool state for the staking program.
+#[account]
+#[derive(InitSpace)]
+pub struct PoolState {
+    pub admin: Pubkey,              // 32
+    pub token_mint: Pubkey,         // 32
+    pub pool_token_account: Pubkey, // 32
+    pub merkle_root: [u8; 32],      // 32
+    pub start_time: i64,            // 8
+    pub total_staked: u64,          // 8
+    pub total_airdrop_claimed: u64, // 8
+    pub snapshot_count: u8,         // 1  (days 0..snapshot_count are recorded)
+    pub bump: u8,                   // 1
+    pub pool_token_bump: u8,        // 1
+    pub paused: u8,                 // 1  (0 = active, 1 = paused)
+    pub active_stakers: u32,        // 4
+    pub total_unstaked: u32,        // 4
+    pub daily_rewards: [u64; 32],   // 256 (only 0..20 used)
+    pub daily_snapshots: [u64; 32], // 256 (only 0..20 used)
+    pub config: PoolConfig,         // see PoolConfig
+    pub airdrop_exhausted: u8,      // 1  (set once total_airdrop_claimed hits the airdrop cap)
+    pub claim_window_days: u64,     // 8  (CLAIM_WINDOW_DAYS at init, only ever extended)
+    pub claim_deadline: i64,        // 8  (0 = claim window end)
+    pub admins: [Pubkey; 3],        // 96 (approvers for destructive actions)
+    pub required_approvals: u8,     // 1  (0 = approvals disabled)
+    pub terminated: u8,             // 1  (0 = active, 1 = termination initiated)
+    pub termination_ts: i64,        // 8  (unix time termination was initiated)
+    pub daily_claimed: [u64; 32],   // 256 (airdrop claimed per day; day 31 includes later days)
+    pub pause_expiry: i64,          // 8  (0 = paused until unpause_pool)
+    pub pause_reason: u8,           // 1  (PauseReason of the latest pause)
+    pub program_days: u8,           // 1  (days with rewards; 0 = TOTAL_DAYS)
+    pub snapshot_taken_bitmap: u32, // 4  (bit d set once day d is snapshotted)
+    pub staking_pool: u64,          // 8  (sum of daily_rewards; 0 = STAKING_POOL)
+    pub open_rewards_withdrawn: u64, // 8 (withdraw_rewards paid to stakes still open)
+    pub total_liquid_claimed: u64,  // 8  (airdrop claimed via claim_airdrop_liquid, never staked)
+    pub referral_budget: u64,       // 8  (tokens left for referral bonuses, outside staking_pool)
+    pub reward_mint: Pubkey,        // 32 (default = rewards paid in token_mint)
+    pub reward_pool_token_account: Pubkey, // 32 (holds rewards when reward_mint is set)
+    pub reward_pool_token_bump: u8, // 1
+    pub staking_budget_remaining: u64, // 8 (staking_pool minus rewards paid out, fees included)
+    pub active: u8,                 // 1  (set by activate_pool once fully funded)
+    pub frozen_seconds: u64,        // 8  (seconds accrual spent frozen, finished freezes)
+    pub frozen_since: i64,          // 8  (start of the current freeze; 0 = accrual running)
+    pub airdrop_cap: u64,           // 8  (raised by increase_airdrop_cap; 0 = AIRDROP_POOL)
+    pub total_donated: u64,         // 8  (reward tokens received via donate)
+    pub carry_reward: u64,          // 8  (rewards of zero-stake days awaiting a staked day)
+    pub funded_stakers: u32,        // 4  (open stakes with staked_amount > 0)
+    pub carried_rewards: [u64; 32], // 256 (carry_reward paid out on top of day d's reward)
+}
+
+impl PoolState {
+    /// Number of reward days in the program (pools created before `program_days` existed
+    /// read 0 and run for TOTAL_DAYS).
+    pub fn program_days(&self) -> u64 {
+        if self.program_days == 0 {
+            TOTAL_DAYS
+        } else {
+            self.program_days as u64
+        }
+    }
+
+    /// Reward paid out for day `d`: the scheduled `daily_rewards[d]` plus what was carried into
+    /// it, or nothing for a recorded zero-stake day past the cliff, whose reward was carried
+    /// forward instead. `daily_rewards` itself always keeps the configured schedule.
+    pub fn day_reward(&self, d: usize) -> u64 {
+        let recorded = self.snapshot_taken_bitmap & (1 << d) != 0;
+        if recorded && self.daily_snapshots[d] == 0 && d as u64 >= self.config.reward_cliff_days {
+            return 0;
+        }
+        self.daily_rewards[d].saturating_add(self.carried_rewards[d])
+    }
+
+    /// Seconds accrual has spent frozen as of `now`, including a freeze still in progress.
+    pub fn frozen_seconds_at(&self, now: i64) -> u64 {
+        let ongoing = if self.frozen_since == 0 {
+            0
+        } else {
+            now.saturating_sub(self.frozen_since).max(0) as u64
+        };
+        self.frozen_seconds + ongoing
+    }
+
+    /// `start_time` pushed out by the time accrual has spent frozen; program days count
+    /// from here.
+    pub fn accrual_start(&self, now: i64) -> i64 {
+        self.start_time + self.frozen_seconds_at(now) as i64
+    }
+
+    /// Elapsed program day at `now` (uncapped; see get_current_day). Does not advance while
+    /// accrual is frozen.
+    pub fn current_day(&self, now: i64) -> u64 {
+        get_current_day(self.accrual_start(now), now, self.config.seconds_per_day())
+    }
+
+    /// Unix timestamp when the claim window ends, shifted out by any accrual freeze.
+    pub fn claim_window_end(&self, now: i64) -> i64 {
+        claim_window_end(
+            self.accrual_start(now),
+            self.claim_window_days,
+            self.config.seconds_per_day(),
+        )
+    }
+
+    /// Whether rewards are paid in a separate reward_mint from reward_pool_token_account.
+    pub fn has_reward_mint(&self) -> bool {
+        self.reward_mint != Pubkey::default() && self.reward_mint != self.token_mint
+    }
+
+    /// Mint rewards are paid in (token_mint unless set_reward_mint chose another).
+    pub fn reward_mint(&self) -> Pubkey {
+        if self.has_reward_mint() {
+            self.reward_mint
+        } else {
+            self.token_mint
+        }
+    }
+
+    /// Total staking rewards in the schedule (pools created before `staking_pool` existed
+    /// read 0 and hold STAKING_POOL).
+    pub fn staking_pool(&self) -> u64 {
+        if self.staking_pool == 0 {
+            STAKING_POOL
+        } else {
+            self.staking_pool
+        }
+    }
+
+    /// Most airdrop that can be claimed: AIRDROP_POOL unless raised by increase_airdrop_cap.
+    pub fn airdrop_cap(&self) -> u64 {
+        if self.airdrop_cap == 0 {
+            AIRDROP_POOL
+        } else {
+            self.airdrop_cap
+        }
+    }
+}
+
+/// Why the pool was paused; passed to `pause_pool` as its `u8` code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PauseReason {
+    Unspecified = 0,
+    Incident = 1,
+    Maintenance = 2,
+    Upgrade = 3,
+    Compliance = 4,
+    /// Set by `snapshot` when `max_daily_stake_delta_bps` is exceeded
+    CircuitBreaker = 5,
+}
+
+impl PauseReason {
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(Self::Unspecified),
+            1 => Some(Self::Incident),
+            2 => Some(Self::Maintenance),
+            3 => Some(Self::Upgrade),
+            4 => Some(Self::Compliance),
+            5 => Some(Self::CircuitBreaker),
+            _ => None,
+        }
+    }
+}
+
+/// One claim in `claim_airdrop_batch`; the claimer signs and owns its token account.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct BatchClaim {
+    pub amount: u64,
+    pub index: u64,
+    pub proof: Vec<[u8; 32]>,
+}
+
+/// Operator-tunable parameters supplied at initialization.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, InitSpace)]
+pub struct PoolConfig {
+    /// Minimum seconds a stake must be held before it can be unstaked (0 = no lock).
+    pub min_stake_seconds: u64, // 8
+    /// Share of staked_amount (bps) withheld from rewards when unstaking before the staking period ends.
+    pub early_exit_penalty_bps: u16, // 2
+    /// Share of rewards (bps) routed to the treasury on unstake.
+    pub reward_fee_bps: u16, // 2
+    /// Owner of the token account that receives reward fees.
+    pub treasury: Pubkey, // 32
+    /// Start reward accrual at the claim day instead of day 0.
+    pub rewards_from_claim_day: bool, // 1
+    /// Whether the claim day itself earns rewards (only with rewards_from_claim_day).
+    pub include_claim_day: bool, // 1
+    /// Per-day reward denominator: REWARD_MODE_POINT_IN_TIME or REWARD_MODE_AVERAGED.
+    pub reward_mode: u8, // 1
+    /// Key that signs `claim_airdrop_signed` vouchers (default = signed claims disabled).
+    pub claim_signer: Pubkey, // 32
+    /// Merkle leaf scheme: MERKLE_SCHEME_LEGACY or MERKLE_SCHEME_INDEXED.
+    pub merkle_scheme_version: u8, // 1
+    /// Reward weight (bps) per lock tier; 0 = BPS_DENOMINATOR (1x).
+    pub lock_multipliers_bps: [u16; LOCK_TIERS], // 8
+    /// Minimum seconds a stake of each lock tier must be held before unstake.
+    pub lock_min_seconds: [u64; LOCK_TIERS], // 32
+    /// Share of a referred claimer's rewards (bps) paid to the referrer from referral_budget.
+    pub referral_bps: u16, // 2
+    /// Collection whose NFT a claimer must hold to claim with a proof (default = no gate).
+    pub required_collection: Pubkey, // 32
+    /// Length of a program day in seconds; 0 = SECONDS_PER_DAY. Other values need the
+    /// `devnet` feature, so shortened days never reach a mainnet build.
+    pub seconds_per_day: u32, // 4
+    /// Seconds into a day before its snapshot can be written, so stakes arriving early in
+    /// the day still count toward the previous day (0 = right at the day boundary).
+    pub snapshot_earliest_offset_seconds: u32, // 4
+    /// Largest change (bps) in total_staked between consecutive snapshots before `snapshot`
+    /// pauses the pool (0 = no circuit breaker).
+    pub max_daily_stake_delta_bps: u16, // 2
+    /// Cap on the open lock-weighted stake (see `open_staked`) a claim may raise it to
+    /// (0 = unlimited).
+    pub max_total_staked: u64, // 8
+    /// Round each day's reward share up instead of down; payouts stay capped by
+    /// staking_budget_remaining.
+    pub round_up_rewards: bool, // 1
+    /// Only key allowed to call `snapshot` when `permissioned_snapshots` is set.
+    pub snapshot_authority: Pubkey, // 32
+    /// Restrict `snapshot` to `snapshot_authority` (default = anyone can crank).
+    pub permissioned_snapshots: bool, // 1
+    /// Days at the start of the program that pay no rewards to anyone (0 = no cliff); their
+    /// daily_rewards are left as recoverable surplus.
+    pub reward_cliff_days: u64, // 8
+    /// Key allowed to call `unpause_pool` besides the admin, and nothing else
+    /// (default = admin only).
+    pub guardian: Pubkey, // 32
+    /// Shape `daily_rewards` must follow at init: a REWARD_CURVE_* value (default = none).
+    pub reward_curve: u8, // 1
+    /// Most a stake can earn over its life, in bps of its staked_amount (0 = no cap); the
+    /// excess stays in the pool.
+    pub max_reward_multiple_bps: u16, // 2
+}
+
+impl PoolConfig {
+    /// Reject values that would break time arithmetic or payout math.
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.min_stake_seconds <= i64::MAX as u64,
+            ErrorCode::InvalidPoolConfig
+        );
+        require!(
+            self.early_exit_penalty_bps as u64 <= BPS_DENOMINATOR,
+            ErrorCode::InvalidPoolConfig
+        );
+        require!(
+            self.reward_fee_bps as u64 <= BPS_DENOMINATOR,
+            ErrorCode::InvalidPoolConfig
+        );
+        require!(
+            self.reward_fee_bps == 0 || self.treasury != Pubkey::default(),
+            ErrorCode::InvalidPoolConfig
+        );
+        require!(
+            self.reward_mode <= REWARD_MODE_AVERAGED,
+            ErrorCode::InvalidPoolConfig
+        );
+        require!(
+            self.merkle_scheme_version <= MERKLE_SCHEME_INDEXED,
+            ErrorCode::InvalidPoolConfig
+        );
+        require!(
+            self.referral_bps as u64 <= BPS_DENOMINATOR,
+            ErrorCode::InvalidPoolConfig
+        );
+        for (&bps, &seconds) in self.lock_multipliers_bps.iter().zip(&self.lock_min_seconds) {
+            require!(
+                bps == 0 || (bps as u64 >= BPS_DENOMINATOR && bps <= MAX_LOCK_MULTIPLIER_BPS),
+                ErrorCode::InvalidPoolConfig
+            );
+            require!(seconds <= i64::MAX as u64, ErrorCode::InvalidPoolConfig);
+        }
+        require!(
+            self.seconds_per_day == 0
+                || self.seconds_per_day as u64 == SECONDS_PER_DAY
+                || cfg!(feature = "devnet"),
+            ErrorCode::InvalidPoolConfig
+        );
+        require!(
+            (self.snapshot_earliest_offset_seconds as u64) < self.seconds_per_day(),
+            ErrorCode::InvalidPoolConfig
+        );
+        require!(
+            !self.permissioned_snapshots || self.snapshot_authority != Pubkey::default(),
+            ErrorCode::InvalidPoolConfig
+        );
+        require!(
+            self.reward_cliff_days < MAX_PROGRAM_DAYS,
+            ErrorCode::InvalidPoolConfig
+        );
+        require!(
+            self.reward_curve <= REWARD_CURVE_LINEAR,
+            ErrorCode::InvalidPoolConfig
+        );
+        Ok(())
+    }
 
-    /// Admin's token account to receive recovered tokens
-    #[account(
-        mut,
-        token::mint = pool_state.token_mint,
-        token::authority = admin,
-    )]
-    pub admin_token_account: Account<'info, TokenAccount>,
+    /// Length of a program day in seconds.
+    pub fn seconds_per_day(&self) -> u64 {
+        match self.seconds_per_day {
+            0 => SECONDS_PER_DAY,
+            seconds => seconds as u64,
+        }
+    }
 
-    pub token_program: Program<'info, Token>,
+    /// Reward multiplier (bps) for a lock tier.
+    pub fn lock_multiplier_bps(&self, tier: u8) -> u64 {
+        match self.lock_multipliers_bps[tier as usize] {
+            0 => BPS_DENOMINATOR,
+            bps => bps as u64,
+        }
+    }
 }
 
-#[derive(Accounts)]
-pub struct PausePool<'info> {
-    /// Must be the pool admin to pause/unpause
-    #[account(
-        constraint = admin.key() == pool_state.admin @ ErrorCode::UnauthorizedAdmin,
-    )]
-    pub admin: Signer<'info>,
-
-    #[account(mut)]
-    pub pool_state: Account<'info, PoolState>,
+/// Permanent marker that prevents re-claiming after unstake.
+/// Tiny account (~0.001 SOL rent) that stays forever.
+#[account]
+#[derive(InitSpace)]
+pub struct ClaimMarker {
+    pub bump: u8, // 1
 }
 
-// ── State ──────────────────────────────────────────────────────────────────────
+/// Marks an address as blocked from claiming. Exists only while the address is blacklisted.
+#[account]
+#[derive(InitSpace)]
+pub struct Blacklist {
+    pub bump: u8, // 1
+}
 
-/// Pool state for the staking program.
+/// Per-caller snapshot crank rate limit.
 #[account]
 #[derive(InitSpace)]
-pub struct PoolState {
-    pub admin: Pubkey,              // 32
-    pub token_mint: Pubkey,         // 32
-    pub pool_token_account: Pubkey, // 32
-    pub merkle_root: [u8; 32],      // 32
-    pub start_time: i64,            // 8
-    pub total_staked: u64,          // 8
-    pub total_airdrop_claimed: u64, // 8
-    pub snapshot_count: u8,         // 1
-    pub bump: u8,                   // 1
-    pub pool_token_bump: u8,        // 1
-    pub paused: u8,                 // 1  (0 = active, 1 = paused)
-    pub active_stakers: u32,        // 4
-    pub total_unstaked: u32,        // 4
-    pub daily_rewards: [u64; 32],   // 256 (only 0..20 used)
-    pub daily_snapshots: [u64; 32], // 256 (only 0..20 used)
+pub struct CrankRecord {
+    pub last_crank_ts: i64, // 8
+    pub bump: u8,           // 1
 }
 
-/// Permanent marker that prevents re-claiming after unstake.
-/// Tiny account (~0.001 SOL rent) that stays forever.
+/// Admin approvals collected for one destructive action; closed when the action executes.
 #[account]
 #[derive(InitSpace)]
-pub struct ClaimMarker {
-    pub bump: u8, // 1
+pub struct ActionApproval {
+    pub action: u8,   // 1  (see `actions`)
+    pub approved: u8, // 1  (bitmap over pool_state.admins)
+    pub bump: u8,     // 1
 }
 
 /// User stake data. Created on claim, closed on unstake (rent returned).
 #[account]
 #[derive(InitSpace)]
 pub struct UserStake {
-    pub owner: Pubkey,      // 32
-    pub staked_amount: u64, // 8
-    pub bump: u8,           // 1
+    pub owner: Pubkey,          // 32
+    pub staked_amount: u64,     // 8
+    pub bump: u8,               // 1
+    pub stake_ts: i64,          // 8  (unix time the stake was created)
+    pub claim_day: u64,         // 8  (program day the stake was created)
+    pub rewards_withdrawn: u64, // 8  (rewards already paid via withdraw_rewards)
+    pub lock_tier: u8,          // 1  (index into config.lock_multipliers_bps)
+    pub referrer: Pubkey,       // 32 (default = not referred)
+    pub harvest_delegate: Pubkey, // 32 (may sign withdraw_rewards; default = none)
+    pub version: u8,              // 1  (USER_STAKE_VERSION; 0 = written before versioning)
+    pub claimed_days: u32,        // 4  (bit d set = day d paid via claim_day_reward)
+    pub frozen: u8,               // 1  (1 = compliance hold: no unstake or reward withdrawal)
+}
+
+// ── Return Data ────────────────────────────────────────────────────────────────
+
+/// Returned by `get_pool_stats`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct PoolStats {
+    pub total_staked: u64,
+    pub total_airdrop_claimed: u64,
+    pub airdrop_remaining: u64,
+    pub pool_balance: u64,
+}
+
+/// Returned by `snapshot`: whether this call recorded any day, and the day it covered up to.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct SnapshotResult {
+    pub wrote: bool,
+    pub day: u64,
+    pub total_staked: u64,
+}
+
+/// Returned by `get_phase`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct PoolPhase {
+    pub phase: u8, // phases::*
+    pub days_remaining_in_phase: u64,
+}
+
+/// Returned by `debug_claim`: `ErrorCode` number of the first failing check (0 = passes).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ClaimDiagnosis {
+    pub error_code: u32,
+}
+
+/// Returned by `is_expired`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ExpiryStatus {
+    pub expired: bool,
+    pub exit_deadline: i64,
+}
+
+/// Returned by `get_snapshots`: index `d` of each vector is day `d`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct SnapshotHistory {
+    pub daily_snapshots: Vec<u64>,
+    pub daily_rewards: Vec<u64>,
+}
+
+/// Returned by `get_user_stake`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct UserStakeInfo {
+    pub owner: Pubkey,
+    pub staked_amount: u64,
+    pub claim_day: u64,
+    pub pending_rewards: u64,
+}
+
+/// Returned by `get_claim_status`: staking means the UserStake account exists.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ClaimStatus {
+    pub claimed: bool,
+    pub staking: bool,
+    pub staked_amount: u64,
+    pub claim_day: u64,
 }
 
 // ── Events ──────────────────────────────────────────────────────────────────────
@@ -690,6 +5209,8 @@ pub struct PoolInitialized {
     pub admin: Pubkey,
     pub token_mint: Pubkey,
     pub start_time: i64,
+    pub merkle_root: [u8; 32],
+    pub timestamp: i64,
 }
 
 #[event]
@@ -697,33 +5218,312 @@ pub struct AirdropClaimed {
     pub user: Pubkey,
     pub amount: u64,
     pub claim_day: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LiquidAirdropClaimed {
+    pub user: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct UserStakeMigrated {
+    pub user: Pubkey,
+    pub previous_len: u64,
+    pub version: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct HarvestDelegateSet {
+    pub user: Pubkey,
+    pub delegate: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReferralRecorded {
+    pub user: Pubkey,
+    pub referrer: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReferralBonusPaid {
+    pub referrer: Pubkey,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AirdropExhausted {
+    pub total_claimed: u64,
+    pub timestamp: i64,
 }
 
 #[event]
 pub struct SnapshotTaken {
     pub day: u64,
     pub total_staked: u64,
+    pub timestamp: i64,
+}
+
+/// Emitted when `snapshot` pauses the pool on a stake swing above the configured limit
+#[event]
+pub struct CircuitBreakerTripped {
+    pub day: u64,
+    pub delta_bps: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SnapshotCorrected {
+    pub day: u64,
+    pub old: u64,
+    pub new: u64,
+    pub timestamp: i64,
+}
+
+/// Emitted by emit_state_checkpoint; `paused` is the effective pause state (expiry applied).
+#[event]
+pub struct StateCheckpoint {
+    pub total_staked: u64,
+    pub total_airdrop_claimed: u64,
+    pub snapshot_count: u8,
+    pub total_rewards_paid: u64,
+    pub paused: bool,
+    pub terminated: bool,
+    pub timestamp: i64,
+}
+
+/// Emitted when a crank finds nothing to write; see `skip_reasons`
+#[event]
+pub struct SnapshotSkipped {
+    pub day: u64,
+    pub reason_code: u8,
+    pub timestamp: i64,
 }
 
 #[event]
 pub struct Unstaked {
     pub user: Pubkey,
     pub rewards: u64,
+    pub day: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct UnstakedTo {
+    pub user: Pubkey,
+    pub destination: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EarlyExitPenalty {
+    pub user: Pubkey,
+    pub penalty: u64,
+    pub timestamp: i64,
+}
+
+/// Emitted by unstake when `max_reward_multiple_bps` cuts the payout; `capped_amount` is the
+/// part withheld and left in the pool.
+#[event]
+pub struct RewardCapped {
+    pub user: Pubkey,
+    pub capped_amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardFeeTaken {
+    pub user: Pubkey,
+    pub fee: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardsWithdrawn {
+    pub user: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ClaimWindowExtended {
+    pub admin: Pubkey,
+    pub claim_window_days: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProgramDaysExtended {
+    pub admin: Pubkey,
+    pub program_days: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DailyRewardsRebalanced {
+    pub admin: Pubkey,
+    pub staking_pool: u64,
+    pub refunded: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardsCalculated {
+    pub user: Pubkey,
+    pub day: u64,
+    pub reward: u64,
+}
+
+#[event]
+pub struct PoolActivated {
+    pub admin: Pubkey,
+    pub pool_balance: u64,
+    pub reward_balance: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardMintSet {
+    pub admin: Pubkey,
+    pub reward_mint: Pubkey,
+    pub reward_pool_token_account: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReferralBudgetFunded {
+    pub admin: Pubkey,
+    pub amount: u64,
+    pub referral_budget: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct Donated {
+    pub donor: Pubkey,
+    pub amount: u64,
+    pub total_donated: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AirdropCapIncreased {
+    pub admin: Pubkey,
+    pub additional: u64,
+    pub new_cap: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ClaimDeadlineSet {
+    pub admin: Pubkey,
+    pub claim_deadline: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ApprovalsConfigured {
+    pub admin: Pubkey,
+    pub admins: [Pubkey; 3],
+    pub required_approvals: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ActionApproved {
+    pub approver: Pubkey,
+    pub action: u8,
+    pub approvals: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TerminationInitiated {
+    pub admin: Pubkey,
+    pub drain_after: i64,
+    pub timestamp: i64,
+}
+
+/// Emitted by terminate_pool_split with the surplus share sent to each destination.
+#[event]
+pub struct PoolTerminated {
+    pub admin: Pubkey,
+    pub admin_amount: u64,
+    pub treasury_amount: u64,
+    pub treasury_bps: u16,
+    pub timestamp: i64,
 }
 
 #[event]
 pub struct TokensRecovered {
     pub amount: u64,
+    pub destination: Pubkey,
+    pub timestamp: i64,
+}
+
+/// Reward-mint counterpart of TokensRecovered, for pools with a separate reward_mint.
+#[event]
+pub struct RewardTokensRecovered {
+    pub amount: u64,
+    pub destination: Pubkey,
+    pub timestamp: i64,
 }
 
 #[event]
 pub struct PoolPausedEvent {
     pub admin: Pubkey,
+    pub reason: u8, // PauseReason
+    pub timestamp: i64,
 }
 
 #[event]
 pub struct PoolUnpausedEvent {
     pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AccrualFrozen {
+    pub admin: Pubkey,
+    pub day: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AccrualUnfrozen {
+    pub admin: Pubkey,
+    pub frozen_for: u64,
+    pub frozen_seconds: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StakeFreezeSet {
+    pub admin: Pubkey,
+    pub user: Pubkey,
+    pub frozen: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AddressBlacklistedEvent {
+    pub admin: Pubkey,
+    pub address: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AddressUnblacklistedEvent {
+    pub admin: Pubkey,
+    pub address: Pubkey,
+    pub timestamp: i64,
 }
 
 // ── Errors ─────────────────────────────────────────────────────────────────────
@@ -780,4 +5580,267 @@ pub enum ErrorCode {
     StakingPeriodEnded,
     #[msg("Claim window still open - cannot recover until day 40")]
     ClaimWindowStillOpen,
+
+    // ── Config Errors ──────────────────────────────────────────────────────────
+    #[msg("Invalid pool configuration")]
+    InvalidPoolConfig,
+
+    // ── Stake Lock Errors ──────────────────────────────────────────────────────
+    #[msg("Stake is locked - minimum stake duration has not elapsed")]
+    StakeLocked,
+
+    // ── Compliance Errors ──────────────────────────────────────────────────────
+    #[msg("Address is blacklisted - claims are not allowed")]
+    AddressBlacklisted,
+    #[msg("Stake is not empty - use unstake to exit")]
+    StakeNotEmpty,
+
+    // ── Fee Errors ─────────────────────────────────────────────────────────────
+    #[msg("Treasury token account is required when a reward fee is configured")]
+    MissingTreasuryAccount,
+
+    // ── Reward Errors ──────────────────────────────────────────────────────────
+    #[msg("Requested amount exceeds withdrawable accrued rewards")]
+    InsufficientRewards,
+
+    // ── Clock Errors ───────────────────────────────────────────────────────────
+    #[msg("Clock regression - current day is behind the last snapshot")]
+    ClockRegression,
+
+    // ── Claim Window Errors ────────────────────────────────────────────────────
+    #[msg("Claim window can only be extended, while open and up to MAX_CLAIM_WINDOW_DAYS")]
+    InvalidClaimWindowExtension,
+    #[msg("Claims are closed - the claim deadline has passed")]
+    ClaimWindowClosed,
+    #[msg("Claim deadline must be in the future and within the claim window")]
+    InvalidClaimDeadline,
+
+    // ── Approval Errors ────────────────────────────────────────────────────────
+    #[msg("Invalid approval configuration")]
+    InvalidApprovalConfig,
+    #[msg("Signer is not one of the pool's approvers")]
+    NotAnApprover,
+    #[msg("Approver has already approved this action")]
+    AlreadyApproved,
+    #[msg("Not enough admin approvals for this action")]
+    InsufficientApprovals,
+
+    // ── Termination Errors ─────────────────────────────────────────────────────
+    #[msg("Pool is terminated - claims are no longer accepted")]
+    PoolTerminated,
+    #[msg("Pool termination has already been initiated")]
+    AlreadyTerminated,
+    #[msg("Pool termination has not been initiated")]
+    PoolNotTerminated,
+    #[msg("Termination timelock has not elapsed yet")]
+    TimelockNotElapsed,
+
+    // ── Pause Errors ───────────────────────────────────────────────────────────
+    #[msg("Pause duration is too large")]
+    InvalidPauseDuration,
+    #[msg("Unknown pause reason code")]
+    InvalidPauseReason,
+
+    // ── Program Length Errors ──────────────────────────────────────────────────
+    #[msg("Invalid program length - must grow, stay within MAX_PROGRAM_DAYS and match the extra rewards")]
+    InvalidProgramDays,
+    #[msg("Pool has already started - program length is fixed")]
+    PoolAlreadyStarted,
+
+    // ── PDA Errors ─────────────────────────────────────────────────────────────
+    #[msg("Stored bump does not derive the expected PDA")]
+    InvalidBump,
+
+    // ── Snapshot Errors ────────────────────────────────────────────────────────
+    #[msg("Snapshot for this day has already been recorded")]
+    SnapshotAlreadyExists,
+
+    // ── Voucher Errors ─────────────────────────────────────────────────────────
+    #[msg("Claim voucher has expired")]
+    VoucherExpired,
+    #[msg("Invalid claim voucher - missing or mismatched ed25519 signature")]
+    InvalidVoucher,
+
+    // ── Rebalance Errors ───────────────────────────────────────────────────────
+    #[msg("New reward schedule must cover every program day and not exceed the current total")]
+    InvalidRebalance,
+
+    // ── Reserve Errors ─────────────────────────────────────────────────────────
+    #[msg("Pool balance would not cover rewards owed to other open stakes")]
+    PoolReserveViolation,
+
+    // ── Crank Errors ───────────────────────────────────────────────────────────
+    #[msg("This caller already cranked a snapshot today")]
+    CrankTooSoon,
+
+    // ── Lockup Errors ──────────────────────────────────────────────────────────
+    #[msg("Lock tier out of range")]
+    InvalidLockTier,
+
+    // ── Referral Errors ────────────────────────────────────────────────────────
+    #[msg("Referrer must be set and differ from the claimer")]
+    InvalidReferrer,
+
+    // ── NFT Gate Errors ────────────────────────────────────────────────────────
+    #[msg("Claimer does not hold an NFT from the required collection")]
+    MissingRequiredNft,
+
+    // ── Reward Mint Errors ─────────────────────────────────────────────────────
+    #[msg("Reward mint must differ from the pool token mint")]
+    InvalidRewardMint,
+    #[msg("Reward token accounts are required when rewards are paid in a separate mint")]
+    MissingRewardAccount,
+
+    // ── Reward Budget Errors ───────────────────────────────────────────────────
+    #[msg("Payout exceeds the remaining staking reward budget")]
+    RewardBudgetExhausted,
+
+    // ── Activation Errors ──────────────────────────────────────────────────────
+    #[msg("Pool token accounts hold less than the airdrop and staking pools")]
+    PoolUnderfunded,
+    #[msg("Pool has not been activated yet")]
+    PoolNotActive,
+    #[msg("Pool is already active")]
+    PoolAlreadyActive,
+
+    // ── Program Extension Errors ───────────────────────────────────────────────
+    #[msg("Program can only be extended before its final day")]
+    ProgramExtensionTooLate,
+
+    // ── Query Errors ───────────────────────────────────────────────────────────
+    #[msg("Day range is wider than REWARDS_PAGE_SIZE")]
+    RangeTooLarge,
+
+    // ── Admin Token Account Errors ─────────────────────────────────────────────
+    #[msg("Destination token account mint does not match the pool token mint")]
+    InvalidAdminTokenMint,
+
+    // ── Math Errors ────────────────────────────────────────────────────────────
+    #[msg("Arithmetic overflow or underflow in pool accounting")]
+    MathOverflow,
+
+    // ── Snapshot Timing Errors ─────────────────────────────────────────────────
+    #[msg("Too early in the day to write its snapshot")]
+    SnapshotTooEarly,
+
+    // ── Stake Cap Errors ───────────────────────────────────────────────────────
+    #[msg("Claim would raise the open stake above max_total_staked")]
+    StakeCapReached,
+
+    // ── Migration Errors ───────────────────────────────────────────────────────
+    #[msg("UserStake is already in the current layout")]
+    UserStakeAlreadyMigrated,
+
+    // ── Accrual Freeze Errors ──────────────────────────────────────────────────
+    #[msg("Reward accrual is already frozen")]
+    AccrualAlreadyFrozen,
+    #[msg("Reward accrual is not frozen")]
+    AccrualNotFrozen,
+    #[msg("Reward accrual can only be frozen during the program days")]
+    AccrualFreezeOutsideProgram,
+
+    // ── Snapshot Correction Errors ─────────────────────────────────────────────
+    #[msg("Only the snapshot recorded today can be corrected")]
+    SnapshotNotCorrectable,
+    #[msg("Corrected snapshot is below the open stake")]
+    InvalidSnapshotValue,
+
+    // ── Batch Claim Errors ─────────────────────────────────────────────────────
+    #[msg("Batch must hold between 1 and MAX_BATCH_CLAIMS claims")]
+    BatchTooLarge,
+    #[msg("Batch accounts are missing, out of order or do not match their claim")]
+    InvalidBatchAccounts,
+
+    // ── Termination Split Errors ───────────────────────────────────────────────
+    #[msg("treasury_bps must not exceed 10000")]
+    InvalidTreasuryBps,
+    #[msg("terminate_pool_split is not available when rewards use a separate reward_mint")]
+    SplitUnsupportedWithRewardMint,
+
+    // ── Day Claim Errors ───────────────────────────────────────────────────────
+    #[msg("This day's reward was already claimed")]
+    DayAlreadyClaimed,
+
+    // ── Claim Diagnosis Errors ─────────────────────────────────────────────────
+    #[msg("Address has already claimed from this pool")]
+    AlreadyClaimed,
+
+    // ── Initialization Errors ──────────────────────────────────────────────────
+    #[msg("Start time is more than MAX_START_DELAY_SECONDS in the future")]
+    StartTimeTooFar,
+
+    // ── Merkle Proof Errors ────────────────────────────────────────────────────
+    #[msg("Merkle proof is longer than MAX_PROOF_DEPTH")]
+    ProofTooLong,
+
+    // ── Stake Freeze Errors ────────────────────────────────────────────────────
+    #[msg("Stake is frozen by a compliance hold")]
+    StakeFrozen,
+    #[msg("Stake is already frozen")]
+    StakeAlreadyFrozen,
+    #[msg("Stake is not frozen")]
+    StakeNotFrozen,
+
+    // ── Snapshot Integrity Errors ──────────────────────────────────────────────
+    #[msg("A day below snapshot_count was never snapshotted")]
+    SnapshotsNotCompleted,
+
+    // ── Claim Amount Errors ────────────────────────────────────────────────────
+    #[msg("Claim amount must be greater than zero")]
+    ZeroClaimAmount,
+
+    // ── Stake Accounting Errors ────────────────────────────────────────────────
+    #[msg("Funded stakes are open but total_staked counts none of them")]
+    OpenStakesUncounted,
+
+    // ── Batch Claim Signer Errors ──────────────────────────────────────────────
+    #[msg("A batch claimer did not sign the transaction")]
+    BatchClaimerNotSigner,
+
+    // ── Crank Record Errors ────────────────────────────────────────────────────
+    #[msg("Crank record still rate-limits snapshots: days remain and the pool is not terminated")]
+    CrankRecordInUse,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reward_to_u64_rejects_amounts_above_u64() {
+        assert_eq!(reward_to_u64(u64::MAX as u128).unwrap(), u64::MAX);
+        assert_eq!(
+            reward_to_u64(u64::MAX as u128 + 1).unwrap_err(),
+            error!(ErrorCode::MathOverflow)
+        );
+    }
+
+    #[test]
+    fn effective_stake_rejects_weights_above_u64() {
+        let config = PoolConfig {
+            lock_multipliers_bps: [0, 20_000, 0, 0],
+            ..Default::default()
+        };
+        let mut user_stake = UserStake {
+            owner: Pubkey::default(),
+            staked_amount: u64::MAX / 2,
+            bump: 0,
+            stake_ts: 0,
+            claim_day: 0,
+            rewards_withdrawn: 0,
+            lock_tier: 1,
+            referrer: Pubkey::default(),
+            harvest_delegate: Pubkey::default(),
+            version: USER_STAKE_VERSION,
+            claimed_days: 0,
+            frozen: 0,
+        };
+        assert_eq!(effective_stake(&config, &user_stake).unwrap(), u64::MAX - 1);
+
+        user_stake.staked_amount = u64::MAX / 2 + 1;
+        assert_eq!(
+            effective_stake(&config, &user_stake).unwrap_err(),
+            error!(ErrorCode::MathOverflow)
+        );
+    }
 }
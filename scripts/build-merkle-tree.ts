@@ -3,10 +3,12 @@
  *
  * Reads a CSV of (wallet, amount) and outputs a JSON file containing:
  * - merkleRoot: the 32-byte root as a number array (pass directly to initialize_pool)
- * - claims: { [wallet]: { amount, amountRaw, proof } }
+ * - merkleSchemeVersion: leaf scheme (pass as config.merkle_scheme_version)
+ * - claims: { [wallet]: { amount, amountRaw, proof, index } }
  *
  * Usage:
  *   npx ts-node scripts/build-merkle-tree.ts <input.csv> [output.json]
+ *   MERKLE_SCHEME=1 npx ts-node scripts/build-merkle-tree.ts <input.csv>   # indexed leaves
  *
  * CSV format (header required):
  *   wallet,amount
@@ -15,7 +17,9 @@
  * Amount is in human-readable tokens (9 decimals). The script converts to raw lamports.
  *
  * The hashing scheme matches the on-chain contract:
- *   leaf = keccak256(wallet_pubkey_bytes || amount_le_u64_bytes)
+ *   leaf = keccak256(wallet_pubkey_bytes || amount_le_u64_bytes)                  (scheme 0)
+ *   leaf = keccak256(0x00 || index_le_u64 || wallet_pubkey_bytes || amount_le_u64_bytes) (scheme 1)
+ * where index is the entry's 0-based row in the CSV.
  *   node = keccak256(min(left, right) || max(left, right))
  */
 
@@ -28,6 +32,7 @@ import { PublicKey } from "@solana/web3.js";
 
 const TOKEN_DECIMALS = 9;
 const AIRDROP_POOL_RAW = BigInt("67000000000000000"); // 67_000_000 × 10^9
+const MERKLE_SCHEME = parseInt(process.env.MERKLE_SCHEME ?? "0"); // 0 = legacy, 1 = indexed
 
 // ── Merkle tree ─────────────────────────────────────────────────────────────
 
@@ -36,9 +41,16 @@ function hashPair(a: Buffer, b: Buffer): Buffer {
   return Buffer.from(keccak256.arrayBuffer(Buffer.concat([left, right])));
 }
 
-function computeLeaf(walletPubkey: PublicKey, amountRaw: bigint): Buffer {
+function computeLeaf(walletPubkey: PublicKey, amountRaw: bigint, index: number): Buffer {
   const amountBuf = Buffer.alloc(8);
   amountBuf.writeBigUInt64LE(amountRaw);
+  if (MERKLE_SCHEME === 1) {
+    const indexBuf = Buffer.alloc(8);
+    indexBuf.writeBigUInt64LE(BigInt(index));
+    return Buffer.from(
+      keccak256.arrayBuffer(Buffer.concat([Buffer.from([0]), indexBuf, walletPubkey.toBuffer(), amountBuf]))
+    );
+  }
   return Buffer.from(
     keccak256.arrayBuffer(Buffer.concat([walletPubkey.toBuffer(), amountBuf]))
   );
@@ -188,15 +200,15 @@ function main() {
   console.log(`Parsed ${entries.length} entries`);
 
   // Build leaves
-  const leafMap = new Map<string, { leaf: Buffer; entry: AllowlistEntry }>();
+  const leafMap = new Map<string, { leaf: Buffer; entry: AllowlistEntry; index: number }>();
   const leaves: Buffer[] = [];
 
-  for (const entry of entries) {
+  entries.forEach((entry, index) => {
     const pubkey = new PublicKey(entry.wallet);
-    const leaf = computeLeaf(pubkey, entry.amountRaw);
-    leafMap.set(entry.wallet, { leaf, entry });
+    const leaf = computeLeaf(pubkey, entry.amountRaw, index);
+    leafMap.set(entry.wallet, { leaf, entry, index });
     leaves.push(leaf);
-  }
+  });
 
   // Build tree
   const tree = buildTree(leaves);
@@ -208,18 +220,20 @@ function main() {
     amount: string;
     amountRaw: string;
     proof: number[][];
+    index: number;
   }> = {};
 
   let totalAmount = BigInt(0);
 
   for (const entry of entries) {
-    const { leaf } = leafMap.get(entry.wallet)!;
+    const { leaf, index } = leafMap.get(entry.wallet)!;
     const proof = getProof(tree, leaf);
 
     claims[entry.wallet] = {
       amount: entry.amount,
       amountRaw: entry.amountRaw.toString(),
       proof: proof.map((p) => Array.from(p)),
+      index,
     };
 
     totalAmount += entry.amountRaw;
@@ -242,6 +256,7 @@ function main() {
 
   const output = {
     merkleRoot: Array.from(tree.root),
+    merkleSchemeVersion: MERKLE_SCHEME,
     totalEntries: entries.length,
     totalAmount: totalAmount.toString(),
     totalAmountHuman: (Number(totalAmount) / 10 ** TOKEN_DECIMALS).toFixed(TOKEN_DECIMALS),
@@ -2,7 +2,7 @@
  * calculate-rewards.ts
  *
  * Calculate accumulated staking rewards for a specific address.
- * Replicates the on-chain `calculate_user_rewards` logic.
+ * Amounts come from the on-chain `get_user_stake` and `calculate_rewards_range` views.
  *
  * Usage:
  *   yarn rewards:devnet <ADDRESS>
@@ -15,6 +15,7 @@
  */
 
 import { Connection, PublicKey } from "@solana/web3.js";
+import { fetchStakeRewards, viewProgram } from "./utils/rewards";
 
 const SECONDS_PER_DAY = 86400;
 const TOTAL_DAYS = 20;
@@ -131,32 +132,6 @@ function formatTokens(amount: bigint, decimals = 9): string {
   });
 }
 
-/**
- * Replicates on-chain calculate_user_rewards.
- * All users earn from day 0. Uses BigInt for u128 precision.
- */
-function calculateUserRewards(
-  stakedAmount: bigint,
-  snapshotCount: number,
-  dailyRewards: bigint[],
-  dailySnapshots: bigint[]
-): bigint {
-  let totalRewards = 0n;
-
-  for (let d = 0; d < snapshotCount; d++) {
-    const snapshotTotal = dailySnapshots[d];
-    if (snapshotTotal === 0n) {
-      continue;
-    }
-
-    const daily = dailyRewards[d];
-    const userShare = (stakedAmount * daily) / snapshotTotal;
-    totalRewards += userShare;
-  }
-
-  return totalRewards;
-}
-
 async function main() {
   const address = process.argv[2];
   if (!address) {
@@ -220,13 +195,9 @@ async function main() {
   }
   const userStake = parseUserStake(userStakeAccount.data);
 
-  // Calculate rewards (all users earn from day 0)
-  const totalRewards = calculateUserRewards(
-    userStake.stakedAmount,
-    pool.snapshotCount,
-    pool.dailyRewards,
-    pool.dailySnapshots
-  );
+  // Rewards as the program computes them
+  const program = viewProgram(connection, programId, pool.admin);
+  const rewards = await fetchStakeRewards(program, poolState, userStakePda);
 
   // Fetch user SPL token balance
   let walletBalance = 0n;
@@ -250,31 +221,20 @@ async function main() {
   console.log(`Snapshots:      ${pool.snapshotCount}`);
   console.log();
 
-  // Per-day breakdown (all users earn from day 0)
-  let runningTotal = 0n;
-
-  for (let d = 0; d < pool.snapshotCount; d++) {
-    const snapshotTotal = pool.dailySnapshots[d];
-    if (snapshotTotal === 0n) {
-      console.log(
-        `Day ${(d + 1).toString().padStart(2)}:  ${"0.00".padStart(14)} tokens  (snapshot = 0, skipped)`
-      );
-      continue;
-    }
-
-    const daily = pool.dailyRewards[d];
-    const userShare = (userStake.stakedAmount * daily) / snapshotTotal;
-    runningTotal += userShare;
-
+  // Per-day breakdown over snapshotted days, from the stake's reward start day
+  for (const { day, reward } of rewards.perDay) {
+    if (day >= pool.snapshotCount) break;
+    const snapshotTotal = pool.dailySnapshots[day];
     console.log(
-      `Day ${(d + 1).toString().padStart(2)}:  ${formatTokens(userShare).padStart(14)} tokens  (reward: ${formatTokens(userStake.stakedAmount)} * ${formatTokens(daily)} / ${formatTokens(snapshotTotal)})`
+      `Day ${(day + 1).toString().padStart(2)}:  ${formatTokens(reward).padStart(14)} tokens  (daily reward: ${formatTokens(pool.dailyRewards[day])}, snapshot: ${formatTokens(snapshotTotal)})`
     );
   }
 
   console.log();
   console.log(
-    `Total Rewards:  ${formatTokens(totalRewards)} tokens`
+    `Total Rewards:  ${formatTokens(rewards.pending)} tokens`
   );
+  console.log(`(Per-day amounts are floored individually; the total is floored once and excludes rewards already withdrawn)`);
   console.log(`(Airdrop tokens were sent to wallet on claim — unstake returns rewards only)`);
   console.log();
 }
@@ -10,6 +10,8 @@
  *   yarn claim:devnet --check                   # Only check eligibility, don't claim
  *   yarn claim:devnet --check --address <pubkey> # Check eligibility for any address
  *   yarn claim:devnet --yes                     # Skip confirmation prompt
+ *   yarn claim:devnet --tier <0-3>              # Lock tier (longer lockup, higher reward weight)
+ *   yarn claim:devnet --nft-mint <mint>         # NFT held for an NFT-gated pool
  *
  * Required env vars:
  *   ANCHOR_PROVIDER_URL  — RPC endpoint
@@ -27,6 +29,8 @@ import { TOKEN_PROGRAM_ID, getAssociatedTokenAddress } from "@solana/spl-token";
 import * as anchor from "@coral-xyz/anchor";
 import { Program, AnchorProvider, BN } from "@coral-xyz/anchor";
 
+const TOKEN_METADATA_PROGRAM_ID = new PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
 function requireEnv(name: string): string {
   const val = process.env[name];
   if (!val) {
@@ -36,12 +40,14 @@ function requireEnv(name: string): string {
   return val;
 }
 
-function parseArgs(): { walletPath?: string; checkOnly: boolean; address?: string; skipConfirm: boolean } {
+function parseArgs(): { walletPath?: string; checkOnly: boolean; address?: string; skipConfirm: boolean; lockTier: number; nftMint?: string } {
   const args = process.argv.slice(2);
   let walletPath: string | undefined;
   let checkOnly = false;
   let address: string | undefined;
   let skipConfirm = false;
+  let lockTier = 0;
+  let nftMint: string | undefined;
 
   for (let i = 0; i < args.length; i++) {
     if (args[i] === "--wallet" && args[i + 1]) {
@@ -54,16 +60,23 @@ function parseArgs(): { walletPath?: string; checkOnly: boolean; address?: strin
       i++;
     } else if (args[i] === "--yes" || args[i] === "-y") {
       skipConfirm = true;
+    } else if (args[i] === "--tier" && args[i + 1]) {
+      lockTier = parseInt(args[i + 1]);
+      i++;
+    } else if (args[i] === "--nft-mint" && args[i + 1]) {
+      nftMint = args[i + 1];
+      i++;
     }
   }
 
-  return { walletPath, checkOnly, address, skipConfirm };
+  return { walletPath, checkOnly, address, skipConfirm, lockTier, nftMint };
 }
 
 interface ClaimData {
   amount: string;
   amountRaw: string;
   proof: number[][];
+  index?: number; // present for indexed (merkle_scheme_version = 1) trees
 }
 
 interface MerkleJson {
@@ -94,7 +107,7 @@ function parsePoolState(data: Buffer): PoolData {
 }
 
 async function main() {
-  const { walletPath: argWalletPath, checkOnly, address: checkAddress, skipConfirm } = parseArgs();
+  const { walletPath: argWalletPath, checkOnly, address: checkAddress, skipConfirm, lockTier, nftMint } = parseArgs();
 
   const rpcUrl = requireEnv("ANCHOR_PROVIDER_URL");
   const defaultWalletPath = requireEnv("ANCHOR_WALLET");
@@ -253,6 +266,7 @@ async function main() {
   console.log("=".repeat(60));
   console.log(`   Wallet:  ${userPubkey.toBase58()}`);
   console.log(`   Amount:  ${claimData.amount} tokens`);
+  console.log(`   Tier:    ${lockTier}`);
   console.log(`   Network: ${rpcUrl.includes("devnet") ? "DEVNET" : rpcUrl.includes("mainnet") ? "MAINNET" : rpcUrl}`);
   console.log("=".repeat(60));
 
@@ -266,12 +280,24 @@ async function main() {
     console.log("   (--yes flag: skipping confirmation)");
   }
 
+  // NFT gate accounts: the held NFT's token account and its Metaplex metadata PDA
+  let nftTokenAccount: PublicKey | null = null;
+  let nftMetadata: PublicKey | null = null;
+  if (nftMint) {
+    const mint = new PublicKey(nftMint);
+    nftTokenAccount = await getAssociatedTokenAddress(mint, userPubkey);
+    [nftMetadata] = PublicKey.findProgramAddressSync(
+      [Buffer.from("metadata"), TOKEN_METADATA_PROGRAM_ID.toBuffer(), mint.toBuffer()],
+      TOKEN_METADATA_PROGRAM_ID
+    );
+  }
+
   // Submit claim
   console.log("\n📤 Submitting claim transaction...");
 
   try {
     const tx = await program.methods
-      .claimAirdrop(new BN(claimData.amountRaw), claimData.proof)
+      .claimAirdrop(new BN(claimData.amountRaw), claimData.proof, new BN(claimData.index ?? 0), lockTier)
       .accounts({
         user: userPubkey,
         poolState,
@@ -279,6 +305,8 @@ async function main() {
         userStake,
         poolTokenAccount,
         userTokenAccount,
+        nftTokenAccount,
+        nftMetadata,
         systemProgram: SystemProgram.programId,
         tokenProgram: TOKEN_PROGRAM_ID,
       })
@@ -289,7 +317,7 @@ async function main() {
     console.log(`   TX: ${tx}`);
     console.log(`   Amount: ${claimData.amount} tokens`);
     console.log(`\n   Tokens sent to your wallet. Earning staking rewards from day 0.`);
-    console.log(`   Call unstake() before day 40 to collect rewards.`);
+    console.log(`   Call unstake() to collect rewards.`);
   } catch (err: any) {
     const errMsg = err.message || String(err);
 
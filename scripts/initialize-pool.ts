@@ -16,11 +16,27 @@
  *
  * Optional env vars:
  *   START_TIME           — unix timestamp for pool start (default: now)
+ *   LOCK_MULTIPLIERS_BPS — comma-separated reward weight per lock tier, e.g. "10000,12500,15000,20000"
+ *   LOCK_MIN_SECONDS     — comma-separated minimum hold per lock tier, e.g. "0,604800,1209600,1728000"
+ *   REFERRAL_BPS         — share of a referred claimer's rewards paid to the referrer (bps)
+ *   REQUIRED_COLLECTION  — NFT collection a claimer must hold (default: no gate)
+ *   REWARD_MINT          — pay staking rewards in this mint (default: the airdrop token)
+ *   SECONDS_PER_DAY      — length of a program day (default 86400; other values need a
+ *                          program built with --features devnet)
+ *   SNAPSHOT_OFFSET_SECONDS — seconds into a day before its snapshot can be taken (default 0)
+ *   MAX_DAILY_STAKE_DELTA_BPS — stake swing between snapshots that pauses the pool (default 0 = off)
+ *   MAX_TOTAL_STAKED     — cap on open staked base units claims may reach (default 0 = unlimited)
+ *   ROUND_UP_REWARDS     — "true" to round each day's reward share up (default: round down)
+ *   SNAPSHOT_AUTHORITY   — only key allowed to call snapshot (default: anyone can crank)
+ *   REWARD_CLIFF_DAYS    — leading days that pay no rewards (default 0)
  *
  * What this script does:
  *   1. Reads merkle root from the merkle JSON file
  *   2. Calls initialize_pool(start_time, merkle_root, daily_rewards)
  *   3. Transfers 200M tokens from admin ATA to the pool token account
+ *      (with REWARD_MINT: calls set_reward_mint, funds the pool token account with the
+ *      67M airdrop and the reward pool with 133M of the reward mint)
+ *   4. Calls activate_pool, which checks the funding and opens the pool for claims
  */
 
 import * as fs from "fs";
@@ -53,7 +69,16 @@ function requireEnv(name: string): string {
   return val;
 }
 
+/** Parse a comma-separated per-tier list, padding missing tiers with 0 (= default). */
+function parseTiers(val: string | undefined): string[] {
+  const parts = (val ?? "").split(",").map((v) => v.trim()).filter((v) => v !== "");
+  return [0, 1, 2, 3].map((i) => parts[i] ?? "0");
+}
+
 const TOTAL_SUPPLY = BigInt("200000000000000000"); // 200M with 9 decimals
+const AIRDROP_SUPPLY = BigInt("67000000000000000"); // 67M with 9 decimals
+const STAKING_SUPPLY = TOTAL_SUPPLY - AIRDROP_SUPPLY;
+const REWARD_MINT = process.env.REWARD_MINT ? new PublicKey(process.env.REWARD_MINT) : null;
 
 // ── Main ────────────────────────────────────────────────────────────────────
 
@@ -207,13 +232,55 @@ async function main() {
   console.log(`- Merkle Root: [${merkleRoot.slice(0, 4).join(", ")}...]`);
   console.log(`- Start Time: ${startTime} (${new Date(startTime * 1000).toUTCString()})`);
 
+  // Operator tunables (see PoolConfig in lib.rs)
+  const poolConfig = {
+    minStakeSeconds: new BN(process.env.MIN_STAKE_SECONDS ?? "0"),
+    earlyExitPenaltyBps: parseInt(process.env.EARLY_EXIT_PENALTY_BPS ?? "0"),
+    rewardFeeBps: parseInt(process.env.REWARD_FEE_BPS ?? "0"),
+    treasury: process.env.TREASURY ? new PublicKey(process.env.TREASURY) : PublicKey.default,
+    rewardsFromClaimDay: process.env.REWARDS_FROM_CLAIM_DAY === "true",
+    includeClaimDay: process.env.INCLUDE_CLAIM_DAY !== "false",
+    rewardMode: parseInt(process.env.REWARD_MODE ?? "0"),
+    claimSigner: process.env.CLAIM_SIGNER ? new PublicKey(process.env.CLAIM_SIGNER) : PublicKey.default,
+    merkleSchemeVersion: merkleData.merkleSchemeVersion ?? 0,
+    lockMultipliersBps: parseTiers(process.env.LOCK_MULTIPLIERS_BPS).map(Number),
+    lockMinSeconds: parseTiers(process.env.LOCK_MIN_SECONDS).map((v) => new BN(v)),
+    referralBps: parseInt(process.env.REFERRAL_BPS ?? "0"),
+    requiredCollection: process.env.REQUIRED_COLLECTION ? new PublicKey(process.env.REQUIRED_COLLECTION) : PublicKey.default,
+    secondsPerDay: parseInt(process.env.SECONDS_PER_DAY || "0", 10),
+    snapshotEarliestOffsetSeconds: parseInt(process.env.SNAPSHOT_OFFSET_SECONDS || "0", 10),
+    maxDailyStakeDeltaBps: parseInt(process.env.MAX_DAILY_STAKE_DELTA_BPS || "0", 10),
+    maxTotalStaked: new BN(process.env.MAX_TOTAL_STAKED ?? "0"),
+    roundUpRewards: process.env.ROUND_UP_REWARDS === "true",
+    snapshotAuthority: process.env.SNAPSHOT_AUTHORITY ? new PublicKey(process.env.SNAPSHOT_AUTHORITY) : PublicKey.default,
+    permissionedSnapshots: !!process.env.SNAPSHOT_AUTHORITY,
+    rewardCliffDays: new BN(process.env.REWARD_CLIFF_DAYS ?? "0"),
+  };
+  console.log(`- Min Stake Seconds: ${poolConfig.minStakeSeconds.toString()}`);
+  console.log(`- Early Exit Penalty: ${poolConfig.earlyExitPenaltyBps} bps`);
+  console.log(`- Reward Fee: ${poolConfig.rewardFeeBps} bps -> ${poolConfig.treasury.toBase58()}`);
+  console.log(`- Rewards From Claim Day: ${poolConfig.rewardsFromClaimDay} (include claim day: ${poolConfig.includeClaimDay})`);
+  console.log(`- Reward Mode: ${poolConfig.rewardMode === 1 ? "averaged" : "point-in-time"}`);
+  console.log(`- Merkle Scheme: ${poolConfig.merkleSchemeVersion === 1 ? "indexed" : "legacy"}`);
+  console.log(`- Lock Tiers: multipliers [${poolConfig.lockMultipliersBps.join(", ")}] bps, min seconds [${poolConfig.lockMinSeconds.join(", ")}]`);
+  console.log(`- Referral Bonus: ${poolConfig.referralBps} bps`);
+  console.log(`- Required Collection: ${poolConfig.requiredCollection.equals(PublicKey.default) ? "none" : poolConfig.requiredCollection.toBase58()}`);
+  console.log(`- Seconds Per Day: ${poolConfig.secondsPerDay || 86400}`);
+  console.log(`- Snapshot Earliest Offset: ${poolConfig.snapshotEarliestOffsetSeconds}s`);
+  console.log(`- Max Daily Stake Delta: ${poolConfig.maxDailyStakeDeltaBps ? `${poolConfig.maxDailyStakeDeltaBps} bps` : "off"}`);
+  console.log(`- Max Total Staked: ${poolConfig.maxTotalStaked.isZero() ? "unlimited" : poolConfig.maxTotalStaked.toString()}`);
+  console.log(`- Reward Rounding: ${poolConfig.roundUpRewards ? "up" : "down"}`);
+  console.log(`- Reward Cliff: ${poolConfig.rewardCliffDays.toString()} days`);
+  console.log(`- Snapshot Authority: ${poolConfig.permissionedSnapshots ? poolConfig.snapshotAuthority.toBase58() : "anyone"}`);
+  console.log(`- Claim Signer: ${poolConfig.claimSigner.equals(PublicKey.default) ? "disabled" : poolConfig.claimSigner.toBase58()}`);
+
   const confirmed = await askConfirmation("Do you want to proceed with pool initialization?");
   if (!confirmed) {
     console.log("❌ Initialization aborted by user.");
     process.exit(0);
   }
   const tx = await program.methods
-    .initializePool(new BN(startTime), merkleRoot, dailyRewards)
+    .initializePool(new BN(startTime), merkleRoot, dailyRewards, poolConfig)
     .accounts({
       admin: admin.publicKey,
       poolState,
@@ -232,9 +299,29 @@ async function main() {
   }
   console.log(`Pool account size: ${poolAccount.data.length} bytes`);
 
+  // Optional: pay rewards in a separate mint (must happen before start)
+  let rewardPoolTokenAccount: PublicKey | null = null;
+  if (REWARD_MINT) {
+    [rewardPoolTokenAccount] = PublicKey.findProgramAddressSync(
+      [Buffer.from("reward_pool_token"), poolState.toBuffer()],
+      programId
+    );
+    const rewardTx = await program.methods
+      .setRewardMint()
+      .accounts({
+        admin: admin.publicKey,
+        poolState,
+        rewardMint: REWARD_MINT,
+        rewardPoolTokenAccount,
+      })
+      .rpc();
+    console.log(`set_reward_mint tx: ${rewardTx} (rewards paid in ${REWARD_MINT.toBase58()})`);
+  }
+  const poolFunding = REWARD_MINT ? AIRDROP_SUPPLY : TOTAL_SUPPLY;
+
   // ── Step 2: Fund pool with 200M tokens ────────────────────────────────────
 
-  console.log("\n--- Step 2: Fund Pool (200M $FIGHT) ---");
+  console.log(`\n--- Step 2: Fund Pool (${Number(poolFunding) / 1e9} $FIGHT) ---`);
 
   // Get admin's ATA
   const adminAta = await getOrCreateAssociatedTokenAccount(
@@ -247,9 +334,9 @@ async function main() {
   const adminBalance = adminAta.amount;
   console.log(`Admin token balance: ${Number(adminBalance) / 1e9} tokens`);
 
-  if (adminBalance < TOTAL_SUPPLY) {
+  if (adminBalance < poolFunding) {
     console.error(
-      `Insufficient token balance. Need ${Number(TOTAL_SUPPLY) / 1e9}, have ${Number(adminBalance) / 1e9}`
+      `Insufficient token balance. Need ${Number(poolFunding) / 1e9}, have ${Number(adminBalance) / 1e9}`
     );
     process.exit(1);
   }
@@ -260,24 +347,65 @@ async function main() {
     adminAta.address,
     poolTokenAccount,
     admin,
-    TOTAL_SUPPLY
+    poolFunding
   );
   console.log(`Fund tx: ${fundTx}`);
 
+  if (REWARD_MINT && rewardPoolTokenAccount) {
+    const adminRewardAta = await getOrCreateAssociatedTokenAccount(
+      connection,
+      admin,
+      REWARD_MINT,
+      admin.publicKey
+    );
+    if (adminRewardAta.amount < STAKING_SUPPLY) {
+      console.error(
+        `Insufficient reward token balance. Need ${Number(STAKING_SUPPLY) / 1e9}, have ${Number(adminRewardAta.amount) / 1e9}`
+      );
+      process.exit(1);
+    }
+    const rewardFundTx = await transfer(
+      connection,
+      admin,
+      adminRewardAta.address,
+      rewardPoolTokenAccount,
+      admin,
+      STAKING_SUPPLY
+    );
+    console.log(`Reward pool fund tx: ${rewardFundTx}`);
+  }
+
   // Verify pool balance
   const poolTokenInfo = await getAccount(connection, poolTokenAccount);
   console.log(
     `Pool token balance: ${Number(poolTokenInfo.amount) / 1e9} tokens`
   );
 
+  // ── Step 3: Activate pool ─────────────────────────────────────────────────
+
+  console.log("\n--- Step 3: Activate Pool ---");
+  const activateTx = await program.methods
+    .activatePool()
+    .accounts({
+      admin: admin.publicKey,
+      poolState,
+      poolTokenAccount,
+      rewardPoolTokenAccount,
+    })
+    .rpc();
+  console.log(`activate_pool tx: ${activateTx}`);
+
   // ── Done ──────────────────────────────────────────────────────────────────
 
-  console.log("\n=== Pool Initialized & Funded ===");
+  console.log("\n=== Pool Initialized, Funded & Activated ===");
   console.log(`Pool State:         ${poolState.toBase58()}`);
   console.log(`Pool Token Account: ${poolTokenAccount.toBase58()}`);
   console.log(`Token Mint:         ${tokenMint.toBase58()}`);
   console.log(`Start Time:         ${startDate.toUTCString()}`);
-  console.log(`Total Funded:       ${Number(TOTAL_SUPPLY) / 1e9} $FIGHT`);
+  console.log(`Total Funded:       ${Number(poolFunding) / 1e9} $FIGHT`);
+  if (REWARD_MINT) {
+    console.log(`Reward Pool:        ${rewardPoolTokenAccount!.toBase58()} (${Number(STAKING_SUPPLY) / 1e9} reward tokens)`);
+  }
   console.log(`\nUsers can now claim airdrops using their merkle proofs.`);
   console.log(`Admin must call snapshot() daily at 12:00-12:05 AM UTC.`);
 }
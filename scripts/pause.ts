@@ -10,6 +10,8 @@
  *   yarn unpause:devnet            # Unpause the pool
  *   yarn pause:devnet --status     # Check current pause status
  *   yarn pause:devnet --yes        # Skip confirmation
+ *   yarn pause:devnet --duration 86400  # Pause that lapses on its own after 24h
+ *   yarn pause:devnet --reason incident # Record why the pool was paused
  *
  * Required env vars:
  *   ANCHOR_PROVIDER_URL  — RPC endpoint
@@ -23,7 +25,7 @@ import * as path from "path";
 import * as readline from "readline";
 import { Connection, Keypair, PublicKey } from "@solana/web3.js";
 import * as anchor from "@coral-xyz/anchor";
-import { Program, AnchorProvider } from "@coral-xyz/anchor";
+import { Program, AnchorProvider, BN } from "@coral-xyz/anchor";
 
 function requireEnv(name: string): string {
   const val = process.env[name];
@@ -34,10 +36,26 @@ function requireEnv(name: string): string {
   return val;
 }
 
-function parseArgs(): { action: "pause" | "unpause" | "status"; skipConfirm: boolean } {
+// Mirrors the on-chain PauseReason enum
+const PAUSE_REASONS: Record<string, number> = {
+  unspecified: 0,
+  incident: 1,
+  maintenance: 2,
+  upgrade: 3,
+  compliance: 4,
+};
+
+function parseArgs(): {
+  action: "pause" | "unpause" | "status";
+  skipConfirm: boolean;
+  pauseDuration: number;
+  pauseReason: number;
+} {
   const args = process.argv.slice(2);
   let action: "pause" | "unpause" | "status" = "status";
   let skipConfirm = false;
+  let pauseDuration = 0; // 0 = until unpaused
+  let pauseReason = PAUSE_REASONS.unspecified;
 
   // Check script name to determine default action
   const scriptName = process.argv[1] || "";
@@ -47,8 +65,18 @@ function parseArgs(): { action: "pause" | "unpause" | "status"; skipConfirm: boo
     action = "pause";
   }
 
-  for (const arg of args) {
-    if (arg === "--status" || arg === "-s") {
+  for (let i = 0; i < args.length; i++) {
+    const arg = args[i];
+    if (arg === "--duration" || arg === "-d") {
+      pauseDuration = parseInt(args[++i] ?? "0");
+    } else if (arg === "--reason" || arg === "-r") {
+      const name = (args[++i] ?? "").toLowerCase();
+      if (!(name in PAUSE_REASONS)) {
+        console.error(`Unknown pause reason: ${name} (expected one of ${Object.keys(PAUSE_REASONS).join(", ")})`);
+        process.exit(1);
+      }
+      pauseReason = PAUSE_REASONS[name];
+    } else if (arg === "--status" || arg === "-s") {
       action = "status";
     } else if (arg === "--pause" || arg === "pause") {
       action = "pause";
@@ -59,7 +87,7 @@ function parseArgs(): { action: "pause" | "unpause" | "status"; skipConfirm: boo
     }
   }
 
-  return { action, skipConfirm };
+  return { action, skipConfirm, pauseDuration, pauseReason };
 }
 
 interface PoolData {
@@ -96,7 +124,7 @@ async function askConfirmation(question: string): Promise<boolean> {
 }
 
 async function main() {
-  const { action, skipConfirm } = parseArgs();
+  const { action, skipConfirm, pauseDuration, pauseReason } = parseArgs();
 
   const rpcUrl = requireEnv("ANCHOR_PROVIDER_URL");
   const walletPath = requireEnv("ANCHOR_WALLET");
@@ -212,7 +240,7 @@ async function main() {
 
     if (action === "pause") {
       tx = await program.methods
-        .pausePool()
+        .pausePool(new BN(pauseDuration), pauseReason)
         .accounts({
           admin: admin.publicKey,
           poolState,
@@ -26,8 +26,11 @@ import { getAccount } from "@solana/spl-token";
 
 // Constants matching the program
 const TOTAL_DAYS = 20;
-const CLAIM_WINDOW_DAYS = 40;
 const SECONDS_PER_DAY = 86400;
+// Borsh size of PoolConfig (min_stake_seconds, early_exit_penalty_bps, reward_fee_bps,
+// treasury, rewards_from_claim_day, include_claim_day, reward_mode, claim_signer,
+// merkle_scheme_version)
+const POOL_CONFIG_SIZE = 8 + 2 + 2 + 32 + 1 + 1 + 1 + 32 + 1 + 8 + 32 + 2 + 32 + 4 + 4 + 2 + 8 + 1 + 32 + 1 + 8 + 32 + 1 + 2;
 const AIRDROP_POOL = BigInt("67000000000000000"); // 67M with 9 decimals
 const STAKING_POOL = BigInt("133000000000000000"); // 133M with 9 decimals
 const TOTAL_POOL = AIRDROP_POOL + STAKING_POOL; // 200M
@@ -73,6 +76,9 @@ interface PoolData {
   totalUnstaked: number;
   dailyRewards: bigint[];
   dailySnapshots: bigint[];
+  claimWindowDays: number;
+  programDays: number;
+  secondsPerDay: number;
 }
 
 function parsePoolState(data: Buffer): PoolData {
@@ -129,6 +135,26 @@ function parsePoolState(data: Buffer): PoolData {
   for (let i = 0; i < 32; i++) {
     dailySnapshots.push(data.readBigUInt64LE(offset + i * 8));
   }
+  offset += 32 * 8;
+
+  // config (PoolConfig); seconds_per_day is followed by snapshot_earliest_offset_seconds (4)
+  // max_daily_stake_delta_bps (2), max_total_staked (8), round_up_rewards (1),
+  // snapshot_authority (32) and permissioned_snapshots (1) at its end
+  offset += POOL_CONFIG_SIZE;
+  const secondsPerDay = data.readUInt32LE(offset - 95) || SECONDS_PER_DAY;
+
+  // airdrop_exhausted
+  offset += 1;
+
+  const claimWindowDays = Number(data.readBigUInt64LE(offset));
+  offset += 8;
+
+  // claim_deadline, admins, required_approvals, terminated, termination_ts,
+  // daily_claimed, pause_expiry, pause_reason
+  offset += 8 + 3 * 32 + 1 + 1 + 8 + 32 * 8 + 8 + 1;
+
+  // program_days (0 on pools created before it existed)
+  const programDays = (offset < data.length ? data.readUInt8(offset) : 0) || TOTAL_DAYS;
 
   return {
     admin,
@@ -144,6 +170,9 @@ function parsePoolState(data: Buffer): PoolData {
     totalUnstaked,
     dailyRewards,
     dailySnapshots,
+    claimWindowDays,
+    programDays,
+    secondsPerDay,
   };
 }
 
@@ -230,9 +259,9 @@ async function main() {
 
   // Calculate time-based metrics
   const elapsedSeconds = Math.max(0, now - pool.startTime);
-  const currentDay = pool.startTime > now ? 0 : Math.floor(elapsedSeconds / SECONDS_PER_DAY);
-  const daysRemaining = Math.max(0, TOTAL_DAYS - currentDay + 1);
-  const isExpired = currentDay >= CLAIM_WINDOW_DAYS;
+  const currentDay = pool.startTime > now ? 0 : Math.floor(elapsedSeconds / pool.secondsPerDay);
+  const daysRemaining = Math.max(0, pool.programDays - currentDay + 1);
+  const isExpired = currentDay >= pool.claimWindowDays;
 
   // Load merkle data if available
   let merkleData: MerkleJson | null = null;
@@ -309,7 +338,7 @@ async function main() {
   // Calculate rewards metrics
   let totalRewardsConfigured = 0n;
   let rewardsDistributableSoFar = 0n;
-  for (let i = 0; i < TOTAL_DAYS; i++) {
+  for (let i = 0; i < pool.programDays; i++) {
     totalRewardsConfigured += pool.dailyRewards[i];
     if (i < currentDay) {
       rewardsDistributableSoFar += pool.dailyRewards[i];
@@ -325,13 +354,13 @@ async function main() {
   // 2. Subtract pending rewards for active stakers (their share still in the pool)
   // This avoids relying on a hardcoded initial funding amount.
   let totalRewardsDistributed = 0n;
-  for (let d = 0; d < pool.snapshotCount && d < TOTAL_DAYS; d++) {
+  for (let d = 0; d < pool.snapshotCount && d < pool.programDays; d++) {
     totalRewardsDistributed += pool.dailyRewards[d];
   }
 
   let pendingRewardsForActiveStakers = 0n;
   for (const stake of stakes) {
-    for (let d = 0; d < pool.snapshotCount && d < TOTAL_DAYS; d++) {
+    for (let d = 0; d < pool.snapshotCount && d < pool.programDays; d++) {
       const snapshotTotal = pool.dailySnapshots[d];
       if (snapshotTotal === 0n) continue;
       const daily = pool.dailyRewards[d];
@@ -370,9 +399,9 @@ async function main() {
       startTime: pool.startTime,
       startTimeHuman: new Date(pool.startTime * 1000).toUTCString(),
       currentDay,
-      totalDays: TOTAL_DAYS,
+      totalDays: pool.programDays,
       daysRemaining,
-      daysElapsed: Math.min(currentDay, TOTAL_DAYS),
+      daysElapsed: Math.min(currentDay, pool.programDays),
       elapsedTime: formatDuration(elapsedSeconds),
       isExpired,
     },
@@ -408,9 +437,9 @@ async function main() {
       })),
     snapshots: {
       count: pool.snapshotCount,
-      required: Math.min(currentDay, TOTAL_DAYS),
-      missing: Math.max(0, Math.min(currentDay, TOTAL_DAYS) - pool.snapshotCount),
-      daily: pool.dailySnapshots.slice(0, TOTAL_DAYS).map((s, i) => ({
+      required: Math.min(currentDay, pool.programDays),
+      missing: Math.max(0, Math.min(currentDay, pool.programDays) - pool.snapshotCount),
+      daily: pool.dailySnapshots.slice(0, pool.programDays).map((s, i) => ({
         day: i + 1,
         totalStaked: formatTokens(s),
         taken: s > 0n || i < pool.snapshotCount,
@@ -419,7 +448,7 @@ async function main() {
     rewards: {
       totalConfigured: formatTokens(totalRewardsConfigured),
       distributableSoFar: formatTokens(rewardsDistributableSoFar),
-      dailyRewards: pool.dailyRewards.slice(0, TOTAL_DAYS).map((r, i) => ({
+      dailyRewards: pool.dailyRewards.slice(0, pool.programDays).map((r, i) => ({
         day: i + 1,
         amount: formatTokens(r),
         elapsed: i < currentDay,
@@ -471,16 +500,16 @@ async function main() {
   // Time Progress
   log("\n┌─ TIME PROGRESS ─────────────────────────────────────────────────┐");
   log(`│  Started:       ${new Date(pool.startTime * 1000).toUTCString()}`);
-  log(`│  Current Day:   ${currentDay} / ${TOTAL_DAYS} (${daysRemaining} days remaining)`);
+  log(`│  Current Day:   ${currentDay} / ${pool.programDays} (${daysRemaining} days remaining)`);
   log(`│  Elapsed:       ${formatDuration(elapsedSeconds)}`);
 
   // Progress bar
-  const progressPct = Math.min(100, (currentDay / TOTAL_DAYS) * 100);
+  const progressPct = Math.min(100, (currentDay / pool.programDays) * 100);
   const progressBar = "█".repeat(Math.floor(progressPct / 5)) + "░".repeat(20 - Math.floor(progressPct / 5));
   log(`│  Progress:     [${progressBar}] ${progressPct.toFixed(0)}%`);
 
   if (isExpired) {
-    log(`│  ⚠️  CLAIM WINDOW EXPIRED (day ${CLAIM_WINDOW_DAYS}+) - Admin can terminate, recover tokens, and close pool`);
+    log(`│  ⚠️  CLAIM WINDOW EXPIRED (day ${pool.claimWindowDays}+) - Admin can terminate, recover tokens, and close pool`);
   }
   log("└─────────────────────────────────────────────────────────────────┘");
 
@@ -527,7 +556,7 @@ async function main() {
   }
 
   // Snapshots — snapshot for day N is taken on day N+1, so required = currentDay (not currentDay+1)
-  const snapshotsRequired = Math.min(currentDay, TOTAL_DAYS);
+  const snapshotsRequired = Math.min(currentDay, pool.programDays);
   log("\n┌─ SNAPSHOTS ─────────────────────────────────────────────────────┐");
   log(`│  Taken:     ${pool.snapshotCount} / ${snapshotsRequired}`);
   const missingSnapshots = Math.max(0, snapshotsRequired - pool.snapshotCount);
@@ -542,7 +571,7 @@ async function main() {
     log("│");
     log("│  Recent Snapshots:");
     const startIdx = Math.max(0, pool.snapshotCount - 5);
-    for (let i = startIdx; i < Math.min(pool.snapshotCount, TOTAL_DAYS); i++) {
+    for (let i = startIdx; i < Math.min(pool.snapshotCount, pool.programDays); i++) {
       const snap = pool.dailySnapshots[i];
       log(`│    Day ${i.toString().padStart(2)}: ${formatTokens(snap).padStart(20)} tokens staked`);
     }
@@ -552,7 +581,7 @@ async function main() {
   // Rewards
   log("\n┌─ REWARDS ───────────────────────────────────────────────────────┐");
   log(`│  Total Pool:        ${formatTokens(STAKING_POOL)} tokens`);
-  log(`│  Distributable:     ${formatTokens(rewardsDistributableSoFar)} (days 1-${Math.min(currentDay, TOTAL_DAYS)})`);
+  log(`│  Distributable:     ${formatTokens(rewardsDistributableSoFar)} (days 1-${Math.min(currentDay, pool.programDays)})`);
   log("│");
   log("│  Daily Rewards (first 5 and last 5 days):");
   for (let i = 0; i < 5; i++) {
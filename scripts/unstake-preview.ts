@@ -3,6 +3,7 @@
  *
  * Previews what a user would receive if they unstake today.
  * Shows pool info, all snapshots, daily reward breakdown, and final payout.
+ * Reward amounts come from the on-chain `get_user_stake` and `calculate_rewards_range` views.
  *
  * Usage:
  *   yarn unstake-preview:devnet <ADDRESS>
@@ -16,6 +17,7 @@
 
 import { Connection, PublicKey } from "@solana/web3.js";
 import { getAccount } from "@solana/spl-token";
+import { fetchStakeRewards, viewProgram } from "./utils/rewards";
 
 const SECONDS_PER_DAY = 86400;
 const TOTAL_DAYS = 20;
@@ -123,34 +125,6 @@ function formatDuration(seconds: number): string {
   return `${m}m`;
 }
 
-/**
- * Replicates on-chain calculate_user_rewards.
- * All users earn from day 0.
- */
-function calculateUserRewards(
-  stakedAmount: bigint,
-  snapshotCount: number,
-  dailyRewards: bigint[],
-  dailySnapshots: bigint[]
-): { total: bigint; perDay: { day: number; reward: bigint; dailyPool: bigint; snapshot: bigint }[] } {
-  let total = 0n;
-  const perDay: { day: number; reward: bigint; dailyPool: bigint; snapshot: bigint }[] = [];
-
-  for (let d = 0; d < snapshotCount; d++) {
-    const snapshotTotal = dailySnapshots[d];
-    if (snapshotTotal === 0n) {
-      perDay.push({ day: d, reward: 0n, dailyPool: dailyRewards[d], snapshot: 0n });
-      continue;
-    }
-    const daily = dailyRewards[d];
-    const userShare = (stakedAmount * daily) / snapshotTotal;
-    total += userShare;
-    perDay.push({ day: d, reward: userShare, dailyPool: daily, snapshot: snapshotTotal });
-  }
-
-  return { total, perDay };
-}
-
 async function main() {
   const address = process.argv[2];
   if (!address) {
@@ -343,69 +317,68 @@ async function main() {
   console.log("\n-- REWARD BREAKDOWN (if unstake today) -----------------------------");
 
   if (isRewardExpired) {
-    console.log("\n  ** REWARD WINDOW EXPIRED (day > 40) - Rewards = 0 **");
-    console.log(`  Unstaking will return 0 rewards (closes account, recovers ~0.002 SOL rent).`);
-    console.log(`  Your airdrop tokens were already sent to your wallet on claim.`);
-    console.log("\n" + "=".repeat(72) + "\n");
-    process.exit(0);
+    console.log("\n  ** CLAIM WINDOW CLOSED (day 40+) - accrued rewards are still paid on unstake **");
   }
 
-  const { total: totalRewards, perDay } = calculateUserRewards(
-    userStake.stakedAmount,
-    pool.snapshotCount,
-    pool.dailyRewards,
-    pool.dailySnapshots
-  );
+  // Rewards as the program computes them (get_user_stake / calculate_rewards_range views)
+  const program = viewProgram(connection, programId, pool.admin);
+  const rewards = await fetchStakeRewards(program, poolStatePda, userStakePda);
+  const totalRewards = rewards.pending;
+  const programDays = rewards.programDays;
+  const perDay = rewards.perDay.filter((entry) => entry.day < pool.snapshotCount);
 
   console.log();
   console.log("  Idx | Your Reward            | Your Share  | Daily Pool             | Snapshot Total");
   console.log("  ----|------------------------|-------------|------------------------|------------------------");
 
   for (const entry of perDay) {
-    if (entry.snapshot === 0n) {
+    const snapshot = pool.dailySnapshots[entry.day];
+    const dailyPool = pool.dailyRewards[entry.day];
+    if (snapshot === 0n) {
       console.log(
-        `   ${entry.day.toString().padStart(2)} | ${"(skipped - 0 staked)".padStart(22)} |      -      | ${fmt(entry.dailyPool).padStart(22)} | 0`
+        `   ${entry.day.toString().padStart(2)} | ${"(skipped - 0 staked)".padStart(22)} |      -      | ${fmt(dailyPool).padStart(22)} | 0`
       );
     } else {
-      const shareStr = pct(userStake.stakedAmount, entry.snapshot);
+      const shareStr = pct(entry.reward, dailyPool);
       console.log(
         `   ${entry.day.toString().padStart(2)} | ` +
         `${fmt(entry.reward).padStart(22)} | ` +
         `${shareStr.padStart(11)} | ` +
-        `${fmt(entry.dailyPool).padStart(22)} | ` +
-        `${fmt(entry.snapshot)}`
+        `${fmt(dailyPool).padStart(22)} | ` +
+        `${fmt(snapshot)}`
       );
     }
   }
+  if (rewards.startDay > 0) {
+    console.log(`\n  * Rewards start on index ${rewards.startDay} for this stake (cliff / claim day).`);
+  }
+  if (totalRewards < perDay.reduce((sum, entry) => sum + entry.reward, 0n)) {
+    console.log(`  * Rewards already withdrawn are not paid again on unstake.`);
+  }
 
   // Days not yet snapshotted
-  const pendingDays = Math.max(0, Math.min(TOTAL_DAYS, onChainDay) - pool.snapshotCount);
+  const pendingDays = Math.max(0, Math.min(programDays, onChainDay) - pool.snapshotCount);
   if (pendingDays > 0) {
-    console.log(`\n  * ${pendingDays} day(s) pending snapshot (indices ${pool.snapshotCount}-${Math.min(TOTAL_DAYS, onChainDay) - 1}).`);
+    console.log(`\n  * ${pendingDays} day(s) pending snapshot (indices ${pool.snapshotCount}-${Math.min(programDays, onChainDay) - 1}).`);
     console.log(`    These are NOT included in rewards until snapshot is taken.`);
 
-    // Estimate what those days would yield using current total_staked
-    let pendingEstimate = 0n;
-    for (let d = pool.snapshotCount; d < Math.min(TOTAL_DAYS, onChainDay); d++) {
-      if (pool.totalStaked > 0n) {
-        pendingEstimate += (userStake.stakedAmount * pool.dailyRewards[d]) / pool.totalStaked;
-      }
-    }
+    // Estimate what those days would yield, projected on the latest snapshot
+    const pendingEstimate = rewards.perDay
+      .filter((entry) => entry.day >= pool.snapshotCount && entry.day < onChainDay)
+      .reduce((sum, entry) => sum + entry.reward, 0n);
     if (pendingEstimate > 0n) {
       console.log(`    Estimated pending rewards (if snapshot taken now): ~${fmt(pendingEstimate)} tokens`);
     }
   }
 
   // Future days not yet elapsed
-  const futureDays = TOTAL_DAYS - Math.max(pool.snapshotCount, onChainDay);
+  const futureDays = programDays - Math.max(pool.snapshotCount, onChainDay);
   if (futureDays > 0) {
-    let futureEstimate = 0n;
-    const estimateBase = pool.totalStaked > 0n ? pool.totalStaked : 1n;
-    for (let d = Math.max(pool.snapshotCount, onChainDay); d < TOTAL_DAYS; d++) {
-      futureEstimate += (userStake.stakedAmount * pool.dailyRewards[d]) / estimateBase;
-    }
-    console.log(`\n  * ${futureDays} future day(s) remaining (indices ${Math.max(pool.snapshotCount, onChainDay)}-${TOTAL_DAYS - 1}).`);
-    console.log(`    If you stay staked with current pool size: ~${fmt(futureEstimate)} extra tokens (estimate).`);
+    const futureEstimate = rewards.perDay
+      .filter((entry) => entry.day >= Math.max(pool.snapshotCount, onChainDay))
+      .reduce((sum, entry) => sum + entry.reward, 0n);
+    console.log(`\n  * ${futureDays} future day(s) remaining (indices ${Math.max(pool.snapshotCount, onChainDay)}-${programDays - 1}).`);
+    console.log(`    If you stay staked with the latest snapshot's pool size: ~${fmt(futureEstimate)} extra tokens (estimate).`);
   }
 
   // ═══════════════════════════════════════════════════════════════
@@ -1,4 +1,8 @@
+import * as fs from "fs";
+import * as path from "path";
 import BN from "bn.js";
+import { Connection, PublicKey, Transaction, VersionedTransaction } from "@solana/web3.js";
+import { AnchorProvider, Program } from "@coral-xyz/anchor";
 
 // Must match the on-chain constant
 export const STAKING_POOL = new BN("133000000000000000"); // 133M with 9 decimals
@@ -33,3 +37,83 @@ export function computeDailyRewards(): BN[] {
 
   return rewards;
 }
+
+// Must match the on-chain REWARDS_PAGE_SIZE (days per calculate_rewards_range call)
+export const REWARDS_PAGE_SIZE = 16;
+
+/**
+ * Program client for the read-only view instructions. Views are only simulated, never
+ * signed, so `payer` just has to be an existing account (e.g. the pool admin).
+ */
+export function viewProgram(connection: Connection, programId: PublicKey, payer: PublicKey): Program {
+  const idlPath = path.resolve(__dirname, "..", "..", "target", "idl", "memeland_airdrop.json");
+  const idl = JSON.parse(fs.readFileSync(idlPath, "utf-8"));
+
+  const wallet = {
+    publicKey: payer,
+    signTransaction: async <T extends Transaction | VersionedTransaction>(_tx: T): Promise<T> => {
+      throw new Error("read-only wallet");
+    },
+    signAllTransactions: async <T extends Transaction | VersionedTransaction>(_txs: T[]): Promise<T[]> => {
+      throw new Error("read-only wallet");
+    },
+  };
+  const provider = new AnchorProvider(connection, wallet, { commitment: "confirmed" });
+  return new Program({ ...idl, address: programId.toBase58() }, provider);
+}
+
+export interface StakeRewards {
+  /** Rewards unstake would pay now (get_user_stake: snapshotted days, minus withdrawals). */
+  pending: bigint;
+  /** First day the stake earns on (on-chain reward_start_day). */
+  startDay: number;
+  /** Program days with rewards. */
+  programDays: number;
+  /**
+   * calculate_rewards_range for each day from `startDay` to the end of the program.
+   * Days not snapshotted yet are projected on the latest snapshot.
+   */
+  perDay: { day: number; reward: bigint }[];
+}
+
+/**
+ * A stake's rewards as the program computes them, through the get_user_stake and
+ * calculate_rewards_range views, so lock multipliers, REWARD_SCALE, the reward start day,
+ * the denominator mode and withdrawn rewards all match what unstake pays.
+ */
+export async function fetchStakeRewards(
+  program: Program,
+  poolState: PublicKey,
+  userStake: PublicKey
+): Promise<StakeRewards> {
+  const pool: any = await (program.account as any).poolState.fetch(poolState);
+  const stake: any = await (program.account as any).userStake.fetch(userStake);
+
+  const programDays = pool.programDays === 0 ? 20 : pool.programDays;
+  const claimDay = stake.claimDay.toNumber();
+  // Mirrors on-chain reward_start_day
+  const startDay = Math.max(
+    pool.config.rewardsFromClaimDay ? claimDay + (pool.config.includeClaimDay ? 0 : 1) : 0,
+    pool.config.rewardCliffDays.toNumber()
+  );
+
+  const info = await program.methods.getUserStake()
+    .accounts({ poolState, userStake })
+    .view();
+
+  const perDay: { day: number; reward: bigint }[] = [];
+  for (let from = startDay; from < programDays; from += REWARDS_PAGE_SIZE) {
+    const to = Math.min(from + REWARDS_PAGE_SIZE, programDays);
+    const rewards: BN[] = await program.methods.calculateRewardsRange(new BN(from), new BN(to))
+      .accounts({ poolState, userStake })
+      .view();
+    rewards.forEach((reward, i) => perDay.push({ day: from + i, reward: BigInt(reward.toString()) }));
+  }
+
+  return {
+    pending: BigInt(info.pendingRewards.toString()),
+    startDay,
+    programDays,
+    perDay,
+  };
+}
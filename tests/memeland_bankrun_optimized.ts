@@ -5,14 +5,18 @@ import {
   PublicKey, 
   SystemProgram, 
   SYSVAR_RENT_PUBKEY,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+  Ed25519Program,
   LAMPORTS_PER_SOL
 } from "@solana/web3.js";
 import {
   TOKEN_PROGRAM_ID,
+  ASSOCIATED_TOKEN_PROGRAM_ID,
   createInitializeMintInstruction,
   getAssociatedTokenAddressSync,
   createAssociatedTokenAccountInstruction,
   createMintToInstruction,
+  createCloseAccountInstruction,
   unpackAccount,
   MINT_SIZE,
 } from "@solana/spl-token";
@@ -150,11 +154,29 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
     return PublicKey.findProgramAddressSync([Buffer.from("claimed"), poolState.toBuffer(), owner.toBuffer()], program.programId);
   }
 
+  function getApprovalPda(poolState: PublicKey, action: number): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync([Buffer.from("approval"), poolState.toBuffer(), Buffer.from([action])], program.programId);
+  }
+
+  function getBlacklistPda(poolState: PublicKey, address: PublicKey): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync([Buffer.from("blacklist"), poolState.toBuffer(), address.toBuffer()], program.programId);
+  }
+
   // --- Merkle Logic ---
   function computeLeaf(user: PublicKey, amount: any): Buffer {
     return Buffer.from(keccak256(Buffer.concat([user.toBuffer(), amount.toArrayLike(Buffer, "le", 8)])), "hex");
   }
 
+  // Indexed leaf scheme (merkle_scheme_version = 1): keccak(0x00 || index || user || amount)
+  function computeIndexedLeaf(index: number, user: PublicKey, amount: any): Buffer {
+    return Buffer.from(keccak256(Buffer.concat([
+      Buffer.from([0]),
+      new BN(index).toArrayLike(Buffer, "le", 8),
+      user.toBuffer(),
+      amount.toArrayLike(Buffer, "le", 8),
+    ])), "hex");
+  }
+
   function buildMerkleTree(leaves: Buffer[]): Buffer[][] {
     let sortedLeaves = [...leaves].sort(Buffer.compare);
     let layers = [sortedLeaves];
@@ -189,6 +211,149 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
     return proof;
   }
 
+  // --- Pool Helpers ---
+
+  // PoolConfig with every tunable at its neutral default; tests override what they exercise.
+  function defaultPoolConfig(overrides: any = {}) {
+    return {
+      minStakeSeconds: new BN(0),
+      earlyExitPenaltyBps: 0,
+      rewardFeeBps: 0,
+      treasury: PublicKey.default,
+      rewardsFromClaimDay: false,
+      includeClaimDay: true,
+      rewardMode: 0,
+      claimSigner: PublicKey.default,
+      merkleSchemeVersion: 0,
+      lockMultipliersBps: [0, 0, 0, 0],
+      lockMinSeconds: [new BN(0), new BN(0), new BN(0), new BN(0)],
+      referralBps: 0,
+      requiredCollection: PublicKey.default,
+      secondsPerDay: 0,
+      snapshotEarliestOffsetSeconds: 0,
+      maxDailyStakeDeltaBps: 0,
+      maxTotalStaked: new BN(0),
+      roundUpRewards: false,
+      snapshotAuthority: PublicKey.default,
+      permissionedSnapshots: false,
+      rewardCliffDays: new BN(0),
+      guardian: PublicKey.default,
+      rewardCurve: 0,
+      maxRewardMultipleBps: 0,
+      ...overrides,
+    };
+  }
+
+  async function tick(seconds = 1) {
+    const clock = await context.banksClient.getClock();
+    await warpTo(Number(clock.unixTimestamp) + seconds);
+  }
+
+  async function expectError(fn: () => Promise<any>, ...needles: string[]) {
+    try {
+      await fn();
+    } catch (e: any) {
+      const msg = (e.message || "").toString();
+      expect(msg, `Actual error: ${msg}`).to.satisfy((m: string) => needles.some((n) => m.includes(n)));
+      return;
+    }
+    expect.fail(`Expected failure matching ${needles.join(" | ")}`);
+  }
+
+  // Creates a fresh mint + pool starting `startOffset` seconds from the current clock and funds it with TOTAL_POOL.
+  async function setupPool(leaves: Buffer[], config: any = {}, startOffset = 1000, activate = true) {
+    const mint = await createMintBankrun(TOKEN_DECIMALS, admin.publicKey);
+    const [poolState] = getPoolStatePda(mint);
+    const [poolToken] = getPoolTokenPda(poolState);
+    const layers = buildMerkleTree(leaves);
+    const start = Number((await context.banksClient.getClock()).unixTimestamp) + startOffset;
+
+    await program.methods.initializePool(new BN(start), Array.from(getMerkleRoot(layers)), computeDailyRewards(), defaultPoolConfig(config))
+      .accounts({ admin: admin.publicKey, poolState, tokenMint: mint, poolTokenAccount: poolToken, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID, rent: SYSVAR_RENT_PUBKEY })
+      .signers([admin]).rpc();
+
+    const adminAta = await getOrCreateATABankrun(mint, admin.publicKey);
+    await provider.sendAndConfirm(new anchor.web3.Transaction().add(
+      createMintToInstruction(mint, adminAta, admin.publicKey, BigInt(TOTAL_POOL.toString())),
+      createTransferInstruction(adminAta, poolToken, admin.publicKey, BigInt(TOTAL_POOL.toString()))
+    ), [admin]);
+
+    if (activate) await activatePool(poolState, poolToken);
+
+    return { mint, poolState, poolToken, layers, start, adminAta };
+  }
+
+  async function activatePool(poolState: PublicKey, poolTokenAccount: PublicKey, rewardPoolTokenAccount: PublicKey | null = null) {
+    await program.methods.activatePool()
+      .accounts({ admin: admin.publicKey, poolState, poolTokenAccount, rewardPoolTokenAccount })
+      .signers([admin]).rpc();
+  }
+
+  async function claimFor(p: any, user: Keypair, amount: any, lockTier = 0) {
+    const [userStake] = getUserStakePda(p.poolState, user.publicKey);
+    const [claimMarker] = getClaimMarkerPda(p.poolState, user.publicKey);
+    const [blacklistEntry] = getBlacklistPda(p.poolState, user.publicKey);
+    const userAta = await getOrCreateATABankrun(p.mint, user.publicKey, user);
+    await program.methods.claimAirdrop(amount, getMerkleProof(p.layers, computeLeaf(user.publicKey, amount)), new BN(0), lockTier)
+      .accounts({ user: user.publicKey, poolState: p.poolState, claimMarker, blacklistEntry, userStake, poolTokenAccount: p.poolToken, userTokenAccount: userAta, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID })
+      .signers([user]).rpc();
+    return { userStake, claimMarker, userAta };
+  }
+
+  async function snapshotPool(p: any, signer: Keypair = admin) {
+    await program.methods.snapshot().accounts({ signer: signer.publicKey, poolState: p.poolState }).signers([signer]).rpc();
+  }
+
+  // Mirrors calculate_user_rewards: day shares of `denominator(d)` (the day's snapshot by
+  // default) are summed at REWARD_SCALE precision and floored once.
+  const REWARD_SCALE = 10n ** 12n;
+  function accruedRewards(stake: BN, state: any, from: number, to: number, denominator = (d: number) => BigInt(state.dailySnapshots[d].toString())): bigint {
+    let scaled = 0n;
+    for (let d = from; d < to; d++) {
+      const denom = denominator(d);
+      if (denom > 0n) scaled += BigInt(stake.toString()) * BigInt(state.dailyRewards[d].toString()) * REWARD_SCALE / denom;
+    }
+    return scaled / REWARD_SCALE;
+  }
+
+  async function unstakeFor(p: any, user: Keypair, treasuryTokenAccount: PublicKey | null = null, referrerTokenAccount: PublicKey | null = null) {
+    const [userStake] = getUserStakePda(p.poolState, user.publicKey);
+    const userAta = getAssociatedTokenAddressSync(p.mint, user.publicKey);
+    await program.methods.unstake()
+      .accounts({ user: user.publicKey, poolState: p.poolState, userStake, poolTokenAccount: p.poolToken, tokenMint: p.mint, userTokenAccount: userAta, treasuryTokenAccount, referrerTokenAccount, tokenProgram: TOKEN_PROGRAM_ID, associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID, systemProgram: SystemProgram.programId })
+      .signers([user]).rpc();
+    return userAta;
+  }
+
+  // Sends a built method call through bankrun and returns the Anchor events decoded from its logs.
+  async function sendAndGetEvents(methodBuilder: any, signers: Keypair[]) {
+    const tx = await methodBuilder.transaction();
+    tx.recentBlockhash = context.lastBlockhash;
+    tx.feePayer = signers[0].publicKey;
+    tx.sign(...signers);
+    const meta = await context.banksClient.processTransaction(tx);
+    const parser = new anchor.EventParser(program.programId, program.coder);
+    return Array.from(parser.parseLogs(meta.logMessages)) as any[];
+  }
+
+  function findEvent(events: any[], name: string) {
+    return events.find((e) => e.name.toLowerCase() === name.toLowerCase());
+  }
+
+  async function tokenBalance(address: PublicKey): Promise<bigint> {
+    const acc = await getAccountBankrun(address);
+    return acc ? acc.amount : 0n;
+  }
+
+  // Overwrite pool_state.total_staked through the account coder
+  async function setTotalStaked(poolState: PublicKey, value: bigint) {
+    const account = await context.banksClient.getAccount(poolState);
+    const state = program.coder.accounts.decode("poolState", Buffer.from(account.data));
+    state.totalStaked = new BN(value.toString());
+    const data = await program.coder.accounts.encode("poolState", state);
+    await context.setAccount(poolState, { ...account, data: Buffer.concat([data, Buffer.alloc(account.data.length - data.length)]) });
+  }
+
   // --- Setup ---
 
   before(async () => {
@@ -237,7 +402,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
     for (let i = 0; i < TOTAL_DAYS; i++) rewards[i] = rewardsPerDay;
 
     await program.methods
-      .initializePool(new BN(startTime), Array.from(multiMerkleRoot), rewards)
+      .initializePool(new BN(startTime), Array.from(multiMerkleRoot), rewards, defaultPoolConfig())
       .accounts({
         admin: admin.publicKey,
         poolState: poolStatePda,
@@ -263,6 +428,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
       createTransferInstruction(adminAta, poolTokenPda, admin.publicKey, BigInt(totalNeeded.toString()))
     );
     await provider.sendAndConfirm(tx, [admin]);
+    await activatePool(poolStatePda, poolTokenPda);
     
     // Explicitly warp forward a bit to ensure changes are synced and next transactions have unique state
     const currentClock = await context.banksClient.getClock();
@@ -292,7 +458,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
     const aliceAta = await getOrCreateATABankrun(tokenMint, alice.publicKey, alice);
 
     await program.methods
-      .claimAirdrop(aliceAmount, merkleProof)
+      .claimAirdrop(aliceAmount, merkleProof, new BN(0), 0)
       .accounts({
         user: alice.publicKey,
         poolState: poolStatePda,
@@ -351,8 +517,11 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
         poolState: poolStatePda,
         userStake: userStake,
         poolTokenAccount: poolTokenPda,
+        tokenMint: tokenMint,
         userTokenAccount: userAta,
         tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
       })
       .signers([alice])
       .rpc();
@@ -375,8 +544,10 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
       .accounts({
         admin: admin.publicKey,
         poolState: poolStatePda,
+        approval: null,
         poolTokenAccount: poolTokenPda,
-        adminTokenAccount: adminAta,
+        tokenMint: tokenMint,
+        destinationTokenAccount: adminAta,
         tokenProgram: TOKEN_PROGRAM_ID,
       })
       .signers([admin])
@@ -402,7 +573,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
     rewards[19] = rewards[19].add(STAKING_POOL.mod(new BN(20)));
 
     await program.methods
-      .initializePool(new BN(st), Array.from(multiMerkleRoot), rewards)
+      .initializePool(new BN(st), Array.from(multiMerkleRoot), rewards, defaultPoolConfig())
       .accounts({
         admin: admin.publicKey,
         poolState: pState,
@@ -422,6 +593,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
         createTransferInstruction(adminAtaPause, pToken, admin.publicKey, BigInt(TOTAL_POOL.toString()))
     );
     await provider.sendAndConfirm(fundTxPause, [admin]);
+    await activatePool(pState, pToken);
 
     // Setup a user who has claimed so we can test Unstake while paused
     const stateBeforePause = await program.account.poolState.fetch(pState);
@@ -432,11 +604,11 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
     const [aliceStake] = getUserStakePda(pState, alice.publicKey);
     const [aliceMarker] = getClaimMarkerPda(pState, alice.publicKey);
     const aliceAtaPause = await getOrCreateATABankrun(mintPause, alice.publicKey, alice);
-    await program.methods.claimAirdrop(aliceAmount, getMerkleProof(multiMerkleLayers, computeLeaf(alice.publicKey, aliceAmount)))
+    await program.methods.claimAirdrop(aliceAmount, getMerkleProof(multiMerkleLayers, computeLeaf(alice.publicKey, aliceAmount)), new BN(0), 0)
         .accounts({ user: alice.publicKey, poolState: pState, claimMarker: aliceMarker, userStake: aliceStake, poolTokenAccount: pToken, userTokenAccount: aliceAtaPause, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID })
         .signers([alice]).rpc();
 
-    await program.methods.pausePool().accounts({ admin: admin.publicKey, poolState: pState }).signers([admin]).rpc();
+    await program.methods.pausePool(new BN(0), 0).accounts({ admin: admin.publicKey, poolState: pState }).signers([admin]).rpc();
     let state = await program.account.poolState.fetch(pState);
     expect(state.paused).to.equal(1);
 
@@ -445,7 +617,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
         const [bobStake] = getUserStakePda(pState, bob.publicKey);
         const [bobMarker] = getClaimMarkerPda(pState, bob.publicKey);
         const bobAtaPause = await getOrCreateATABankrun(mintPause, bob.publicKey, bob);
-        await program.methods.claimAirdrop(bobAmount, getMerkleProof(multiMerkleLayers, computeLeaf(bob.publicKey, bobAmount)))
+        await program.methods.claimAirdrop(bobAmount, getMerkleProof(multiMerkleLayers, computeLeaf(bob.publicKey, bobAmount)), new BN(0), 0)
             .accounts({
                 user: bob.publicKey,
                 poolState: pState,
@@ -471,8 +643,11 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
             poolState: pState,
             userStake: aliceStake,
             poolTokenAccount: pToken,
+            tokenMint: mintPause,
             userTokenAccount: aliceAtaPause,
             tokenProgram: TOKEN_PROGRAM_ID,
+            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
         }).signers([alice]).rpc();
     const aliceBalAfter = (await getAccountBankrun(aliceAtaPause))!.amount;
     // Should have received airdrop (on claim) + rewards (on unstake)
@@ -501,7 +676,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
         const rewards = Array(32).fill(new BN(0));
         for (let i = 0; i < 20; i++) rewards[i] = STAKING_POOL.div(new BN(20));
 
-        await program.methods.initializePool(new BN(st), Array.from(multiMerkleRoot), rewards)
+        await program.methods.initializePool(new BN(st), Array.from(multiMerkleRoot), rewards, defaultPoolConfig())
             .accounts({
                 admin: admin.publicKey,
                 poolState: ePoolState,
@@ -519,6 +694,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
             createTransferInstruction(eAdminAta, ePoolToken, admin.publicKey, BigInt(TOTAL_POOL.toString()))
         );
         await provider.sendAndConfirm(tx, [admin]);
+        await activatePool(ePoolState, ePoolToken);
 
         // Warp past start time to allow operations
         await warpTo(st + 1);
@@ -532,7 +708,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
 
         try {
             await program.methods
-                .claimAirdrop(bobAmount, aliceProof)
+                .claimAirdrop(bobAmount, aliceProof, new BN(0), 0)
                 .accounts({
                     user: bob.publicKey,
                     poolState: ePoolState,
@@ -562,7 +738,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
         const wrongAmount = aliceAmount.mul(new BN(2));
         try {
             await program.methods
-                .claimAirdrop(wrongAmount, aliceProof)
+                .claimAirdrop(wrongAmount, aliceProof, new BN(0), 0)
                 .accounts({
                     user: alice.publicKey,
                     poolState: ePoolState,
@@ -589,7 +765,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
 
         // First claim
         await program.methods
-            .claimAirdrop(charlieAmount, getMerkleProof(multiMerkleLayers, computeLeaf(charlie.publicKey, charlieAmount)))
+            .claimAirdrop(charlieAmount, getMerkleProof(multiMerkleLayers, computeLeaf(charlie.publicKey, charlieAmount)), new BN(0), 0)
             .accounts({
                 user: charlie.publicKey,
                 poolState: ePoolState,
@@ -610,7 +786,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
         // Second claim
         try {
             await program.methods
-                .claimAirdrop(charlieAmount, getMerkleProof(multiMerkleLayers, computeLeaf(charlie.publicKey, charlieAmount)))
+                .claimAirdrop(charlieAmount, getMerkleProof(multiMerkleLayers, computeLeaf(charlie.publicKey, charlieAmount)), new BN(0), 0)
                 .accounts({
                     user: charlie.publicKey,
                     poolState: ePoolState,
@@ -633,7 +809,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
 
     it("Fails on Unauthorized Administrative Actions", async () => {
         try {
-            await program.methods.pausePool()
+            await program.methods.pausePool(new BN(0), 0)
                 .accounts({ admin: alice.publicKey, poolState: ePoolState })
                 .signers([alice])
                 .rpc();
@@ -674,7 +850,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
 
         const rewards = computeDailyRewards();
 
-        await program.methods.initializePool(new BN(startTime), Array.from(multiMerkleRoot), rewards)
+        await program.methods.initializePool(new BN(startTime), Array.from(multiMerkleRoot), rewards, defaultPoolConfig())
             .accounts({
                 admin: admin.publicKey,
                 poolState: mPoolState,
@@ -693,6 +869,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
             createTransferInstruction(adminAta, mPoolToken, admin.publicKey, BigInt(total.toString()))
         );
         await provider.sendAndConfirm(tx, [admin]);
+        await activatePool(mPoolState, mPoolToken);
     });
 
     it("Simulates Staggered Participant Staking and Proportional Rewards", async () => {
@@ -706,7 +883,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
         const [aliceStake] = getUserStakePda(mPoolState, alice.publicKey);
         const [aliceMarker] = getClaimMarkerPda(mPoolState, alice.publicKey);
         const aliceAtaM = await getOrCreateATABankrun(mPool, alice.publicKey, alice);
-        await program.methods.claimAirdrop(aliceAmount, getMerkleProof(multiMerkleLayers, computeLeaf(alice.publicKey, aliceAmount)))
+        await program.methods.claimAirdrop(aliceAmount, getMerkleProof(multiMerkleLayers, computeLeaf(alice.publicKey, aliceAmount)), new BN(0), 0)
             .accounts({ user: alice.publicKey, poolState: mPoolState, claimMarker: aliceMarker, userStake: aliceStake, poolTokenAccount: mPoolToken, userTokenAccount: aliceAtaM, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID })
             .signers([alice]).rpc();
 
@@ -721,7 +898,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
         const [bobStake] = getUserStakePda(mPoolState, bob.publicKey);
         const [bobMarker] = getClaimMarkerPda(mPoolState, bob.publicKey);
         const bobAtaM = await getOrCreateATABankrun(mPool, bob.publicKey, bob);
-        await program.methods.claimAirdrop(bobAmount, getMerkleProof(multiMerkleLayers, computeLeaf(bob.publicKey, bobAmount)))
+        await program.methods.claimAirdrop(bobAmount, getMerkleProof(multiMerkleLayers, computeLeaf(bob.publicKey, bobAmount)), new BN(0), 0)
             .accounts({ user: bob.publicKey, poolState: mPoolState, claimMarker: bobMarker, userStake: bobStake, poolTokenAccount: mPoolToken, userTokenAccount: bobAtaM, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID })
             .signers([bob]).rpc();
 
@@ -736,7 +913,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
         const [charlieStake] = getUserStakePda(mPoolState, charlie.publicKey);
         const [charlieMarker] = getClaimMarkerPda(mPoolState, charlie.publicKey);
         const charlieAtaM = await getOrCreateATABankrun(mPool, charlie.publicKey, charlie);
-        await program.methods.claimAirdrop(charlieAmount, getMerkleProof(multiMerkleLayers, computeLeaf(charlie.publicKey, charlieAmount)))
+        await program.methods.claimAirdrop(charlieAmount, getMerkleProof(multiMerkleLayers, computeLeaf(charlie.publicKey, charlieAmount)), new BN(0), 0)
             .accounts({ user: charlie.publicKey, poolState: mPoolState, claimMarker: charlieMarker, userStake: charlieStake, poolTokenAccount: mPoolToken, userTokenAccount: charlieAtaM, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID })
             .signers([charlie]).rpc();
 
@@ -749,7 +926,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
         }
 
         // Alice Unstakes — receives rewards only (airdrop was sent on claim)
-        await program.methods.unstake().accounts({ user: alice.publicKey, poolState: mPoolState, userStake: aliceStake, poolTokenAccount: mPoolToken, userTokenAccount: aliceAtaM, tokenProgram: TOKEN_PROGRAM_ID }).signers([alice]).rpc();
+        await program.methods.unstake().accounts({ user: alice.publicKey, poolState: mPoolState, userStake: aliceStake, poolTokenAccount: mPoolToken, tokenMint: mPool, userTokenAccount: aliceAtaM, tokenProgram: TOKEN_PROGRAM_ID, associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID, systemProgram: SystemProgram.programId }).signers([alice]).rpc();
 
         const aliceAcc = await getAccountBankrun(aliceAtaM);
         // ATA has airdrop (from claim) + rewards (from unstake)
@@ -782,7 +959,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
         await warpTo(poolStart - 100);
 
         const rewards = computeDailyRewards();
-        await program.methods.initializePool(new BN(poolStart), Array.from(rMerkleRoot), rewards)
+        await program.methods.initializePool(new BN(poolStart), Array.from(rMerkleRoot), rewards, defaultPoolConfig())
             .accounts({
                 admin: admin.publicKey,
                 poolState: rPoolState,
@@ -799,6 +976,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
             createTransferInstruction(adminAta, rPoolToken, admin.publicKey, BigInt(TOTAL_POOL.toString()))
         );
         await provider.sendAndConfirm(tx, [admin]);
+        await activatePool(rPoolState, rPoolToken);
     });
 
     it("Security: User cannot claim with someone else's proof", async () => {
@@ -809,7 +987,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
         const maliciousAta = await getOrCreateATABankrun(rPool, maliciousUser.publicKey, maliciousUser);
 
         try {
-            await program.methods.claimAirdrop(rAmount, getMerkleProof(rMerkleLayers, computeLeaf(rUser.publicKey, rAmount)))
+            await program.methods.claimAirdrop(rAmount, getMerkleProof(rMerkleLayers, computeLeaf(rUser.publicKey, rAmount)), new BN(0), 0)
                 .accounts({
                     user: maliciousUser.publicKey,
                     poolState: rPoolState,
@@ -839,7 +1017,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
         const rUserAta = await getOrCreateATABankrun(rPool, rUser.publicKey, rUser);
 
         try {
-            await program.methods.claimAirdrop(rAmount, getMerkleProof(rMerkleLayers, computeLeaf(rUser.publicKey, rAmount)))
+            await program.methods.claimAirdrop(rAmount, getMerkleProof(rMerkleLayers, computeLeaf(rUser.publicKey, rAmount)), new BN(0), 0)
                 .accounts({
                     user: rUser.publicKey,
                     poolState: rPoolState,
@@ -871,8 +1049,10 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
             .accounts({
                 admin: admin.publicKey,
                 poolState: rPoolState,
+                approval: null,
                 poolTokenAccount: rPoolToken,
-                adminTokenAccount: adminAta,
+                tokenMint: rPool,
+                destinationTokenAccount: adminAta,
                 tokenProgram: TOKEN_PROGRAM_ID,
             })
             .signers([admin])
@@ -909,7 +1089,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
         poolStart = Math.floor(Date.now() / 1000) + 1000;
         await warpTo(poolStart - 100);
 
-        await program.methods.initializePool(new BN(poolStart), Array.from(tMerkleRoot), computeDailyRewards())
+        await program.methods.initializePool(new BN(poolStart), Array.from(tMerkleRoot), computeDailyRewards(), defaultPoolConfig())
             .accounts({
                 admin: admin.publicKey,
                 poolState: tPoolState,
@@ -925,6 +1105,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
             createMintToInstruction(tPool, adminAta, admin.publicKey, BigInt(TOTAL_POOL.toString())),
             createTransferInstruction(adminAta, tPoolToken, admin.publicKey, BigInt(TOTAL_POOL.toString()))
         ), [admin]);
+        await activatePool(tPoolState, tPoolToken);
     });
 
     it("User gets airdrop on claim and rewards on unstake", async () => {
@@ -937,7 +1118,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
         const tUserAta = await getOrCreateATABankrun(tPool, tUser.publicKey, tUser);
 
         // Claim sends tokens to wallet immediately
-        await program.methods.claimAirdrop(tAmount, getMerkleProof(tMerkleLayers, computeLeaf(tUser.publicKey, tAmount)))
+        await program.methods.claimAirdrop(tAmount, getMerkleProof(tMerkleLayers, computeLeaf(tUser.publicKey, tAmount)), new BN(0), 0)
             .accounts({
                 user: tUser.publicKey,
                 poolState: tPoolState,
@@ -973,8 +1154,11 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
                 poolState: tPoolState,
                 userStake: tStake,
                 poolTokenAccount: tPoolToken,
+                tokenMint: tPool,
                 userTokenAccount: tUserAta,
                 tokenProgram: TOKEN_PROGRAM_ID,
+                associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: SystemProgram.programId,
             })
             .signers([tUser])
             .rpc();
@@ -987,8 +1171,10 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
             .accounts({
                 admin: admin.publicKey,
                 poolState: tPoolState,
+                approval: null,
                 poolTokenAccount: tPoolToken,
-                adminTokenAccount: adminAta,
+                tokenMint: tPool,
+                destinationTokenAccount: adminAta,
                 tokenProgram: TOKEN_PROGRAM_ID,
             }).signers([admin]).rpc();
 
@@ -1016,7 +1202,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
             rewards[0] = rewards[0].add(new BN(1));
 
             try {
-                await program.methods.initializePool(new BN(stSum), Array.from(multiMerkleRoot), rewards)
+                await program.methods.initializePool(new BN(stSum), Array.from(multiMerkleRoot), rewards, defaultPoolConfig())
                     .accounts({
                         admin: admin.publicKey,
                         poolState: pState,
@@ -1050,7 +1236,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
             rewards[1] = dayReward.sub(new BN(100));
 
             try {
-                await program.methods.initializePool(new BN(stOrder), Array.from(multiMerkleRoot), rewards)
+                await program.methods.initializePool(new BN(stOrder), Array.from(multiMerkleRoot), rewards, defaultPoolConfig())
                     .accounts({
                         admin: admin.publicKey,
                         poolState: pState,
@@ -1093,7 +1279,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
             await warpTo(startTime - 100);
 
             const rewards = computeDailyRewards();
-            await program.methods.initializePool(new BN(startTime), Array.from(xMerkleRoot), rewards)
+            await program.methods.initializePool(new BN(startTime), Array.from(xMerkleRoot), rewards, defaultPoolConfig())
                 .accounts({
                     admin: admin.publicKey,
                     poolState: xPoolState,
@@ -1110,6 +1296,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
                 createTransferInstruction(adminAta, xPoolToken, admin.publicKey, BigInt(TOTAL_POOL.toString()))
             );
             await provider.sendAndConfirm(tx, [admin]);
+            await activatePool(xPoolState, xPoolToken);
             
             await warpTo(startTime + SECONDS_PER_DAY + 3600);
             await program.methods.snapshot().accounts({ signer: admin.publicKey, poolState: xPoolState }).signers([admin]).rpc();
@@ -1123,7 +1310,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
                 const [stake] = getUserStakePda(xPoolState, user.publicKey);
                 const [marker] = getClaimMarkerPda(xPoolState, user.publicKey);
                 const userAta = await getOrCreateATABankrun(xPool, user.publicKey, user);
-                await program.methods.claimAirdrop(amountPerUser, getMerkleProof(xMerkleLayers, computeLeaf(user.publicKey, amountPerUser)))
+                await program.methods.claimAirdrop(amountPerUser, getMerkleProof(xMerkleLayers, computeLeaf(user.publicKey, amountPerUser)), new BN(0), 0)
                     .accounts({ user: user.publicKey, poolState: xPoolState, claimMarker: marker, userStake: stake, poolTokenAccount: xPoolToken, userTokenAccount: userAta, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID })
                     .signers([user]).rpc();
             }
@@ -1135,7 +1322,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
             const [marker3] = getClaimMarkerPda(xPoolState, user3.publicKey);
             const user3Ata = await getOrCreateATABankrun(xPool, user3.publicKey, user3);
             try {
-                await program.methods.claimAirdrop(amountPerUser, getMerkleProof(xMerkleLayers, computeLeaf(user3.publicKey, amountPerUser)))
+                await program.methods.claimAirdrop(amountPerUser, getMerkleProof(xMerkleLayers, computeLeaf(user3.publicKey, amountPerUser)), new BN(0), 0)
                     .accounts({ user: user3.publicKey, poolState: xPoolState, claimMarker: marker3, userStake: stake3, poolTokenAccount: xPoolToken, userTokenAccount: user3Ata, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID })
                     .signers([user3]).rpc();
                 expect.fail("Airdrop pool should have been exhausted");
@@ -1170,7 +1357,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
             await warpTo(poolStart - 100);
 
             const rewards = computeDailyRewards();
-            await program.methods.initializePool(new BN(poolStart), Array.from(fMerkleRoot), rewards)
+            await program.methods.initializePool(new BN(poolStart), Array.from(fMerkleRoot), rewards, defaultPoolConfig())
                 .accounts({
                     admin: admin.publicKey,
                     poolState: fPoolState,
@@ -1186,6 +1373,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
                 createMintToInstruction(fPool, adminAta, admin.publicKey, BigInt(TOTAL_POOL.toString())),
                 createTransferInstruction(adminAta, fPoolToken, admin.publicKey, BigInt(TOTAL_POOL.toString()))
             ), [admin]);
+            await activatePool(fPoolState, fPoolToken);
         });
 
         it("All users earn from day 0 regardless of claim day (Day 10 claim)", async () => {
@@ -1201,7 +1389,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
              const [fStake] = getUserStakePda(fPoolState, fUser.publicKey);
              const [fMarker] = getClaimMarkerPda(fPoolState, fUser.publicKey);
              const fUserAta = await getOrCreateATABankrun(fPool, fUser.publicKey, fUser);
-             await program.methods.claimAirdrop(fAmount, getMerkleProof(fMerkleLayers, computeLeaf(fUser.publicKey, fAmount)))
+             await program.methods.claimAirdrop(fAmount, getMerkleProof(fMerkleLayers, computeLeaf(fUser.publicKey, fAmount)), new BN(0), 0)
                  .accounts({ user: fUser.publicKey, poolState: fPoolState, claimMarker: fMarker, userStake: fStake, poolTokenAccount: fPoolToken, userTokenAccount: fUserAta, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID })
                  .signers([fUser]).rpc();
 
@@ -1218,7 +1406,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
              }
 
              await program.methods.unstake()
-                .accounts({ user: fUser.publicKey, poolState: fPoolState, userStake: fStake, poolTokenAccount: fPoolToken, userTokenAccount: fUserAta, tokenProgram: TOKEN_PROGRAM_ID })
+                .accounts({ user: fUser.publicKey, poolState: fPoolState, userStake: fStake, poolTokenAccount: fPoolToken, tokenMint: fPool, userTokenAccount: fUserAta, tokenProgram: TOKEN_PROGRAM_ID, associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID, systemProgram: SystemProgram.programId })
                 .signers([fUser]).rpc();
 
              const bal = (await getAccountBankrun(fUserAta))!.amount;
@@ -1231,7 +1419,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
              console.log("Rewards earned (all 20 days, 10M/67M share):", rewards.toString());
         });
 
-        it("Unstake after expiration (Day 36) returns 0 rewards", async () => {
+        it("Unstake after expiration still pays rewards accrued during the program", async () => {
             // Setup fresh pool for expiry test
             const xMint = await createMintBankrun(TOKEN_DECIMALS, admin.publicKey);
             const [xState] = getPoolStatePda(xMint);
@@ -1242,7 +1430,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
             const xStart = Math.floor(Date.now() / 1000) + 1000;
 
             await warpTo(xStart - 100);
-            await program.methods.initializePool(new BN(xStart), Array.from(getMerkleRoot(xMerkle)), computeDailyRewards())
+            await program.methods.initializePool(new BN(xStart), Array.from(getMerkleRoot(xMerkle)), computeDailyRewards(), defaultPoolConfig())
                 .accounts({ admin: admin.publicKey, poolState: xState, tokenMint: xMint, poolTokenAccount: xToken, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID, rent: SYSVAR_RENT_PUBKEY }).signers([admin]).rpc();
 
             const adminAta = await getOrCreateATABankrun(xMint, admin.publicKey);
@@ -1250,6 +1438,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
                 createMintToInstruction(xMint, adminAta, admin.publicKey, BigInt(TOTAL_POOL.toString())),
                 createTransferInstruction(adminAta, xToken, admin.publicKey, BigInt(TOTAL_POOL.toString()))
             ), [admin]);
+            await activatePool(xState, xToken);
 
             // Claim on Day 1 — tokens sent to user ATA
             await warpTo(xStart + SECONDS_PER_DAY + 3600);
@@ -1258,7 +1447,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
             const [xMarker] = getClaimMarkerPda(xState, xUser.publicKey);
             await fundAccount(xUser.publicKey);
             const xUserAta = await getOrCreateATABankrun(xMint, xUser.publicKey, xUser);
-            await program.methods.claimAirdrop(xAmount, getMerkleProof(xMerkle, computeLeaf(xUser.publicKey, xAmount)))
+            await program.methods.claimAirdrop(xAmount, getMerkleProof(xMerkle, computeLeaf(xUser.publicKey, xAmount)), new BN(0), 0)
                 .accounts({ user: xUser.publicKey, poolState: xState, claimMarker: xMarker, userStake: xStake, poolTokenAccount: xToken, userTokenAccount: xUserAta, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID }).signers([xUser]).rpc();
 
             // Finish snapshots
@@ -1269,15 +1458,15 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
                 await warpTo(Number(currentClock.unixTimestamp) + 1);
             }
 
-            // Warp past claim window — unstake gives 0 rewards
+            // Warp past claim window — accrued rewards are still paid
             await warpTo(xStart + (CLAIM_WINDOW_DAYS + 1) * SECONDS_PER_DAY);
             await program.methods.unstake()
-                .accounts({ user: xUser.publicKey, poolState: xState, userStake: xStake, poolTokenAccount: xToken, userTokenAccount: xUserAta, tokenProgram: TOKEN_PROGRAM_ID })
+                .accounts({ user: xUser.publicKey, poolState: xState, userStake: xStake, poolTokenAccount: xToken, tokenMint: xMint, userTokenAccount: xUserAta, tokenProgram: TOKEN_PROGRAM_ID, associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID, systemProgram: SystemProgram.programId })
                 .signers([xUser]).rpc();
 
             const bal = (await getAccountBankrun(xUserAta))!.amount;
-            // Only airdrop tokens from claim remain (0 rewards added on expired unstake)
-            expect(bal).to.equal(BigInt(xAmount.toString()));
+            // Airdrop tokens from claim plus the rewards accrued over days 0..19
+            expect(bal > BigInt(xAmount.toString())).to.be.true;
         });
     });
   });
@@ -1307,7 +1496,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
       crStart = Math.floor(Date.now() / 1000) + 1000;
       await warpTo(crStart - 100);
 
-      await program.methods.initializePool(new BN(crStart), Array.from(crMerkleRoot), computeDailyRewards())
+      await program.methods.initializePool(new BN(crStart), Array.from(crMerkleRoot), computeDailyRewards(), defaultPoolConfig())
         .accounts({
           admin: admin.publicKey,
           poolState: crPoolState,
@@ -1323,6 +1512,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
         createMintToInstruction(crPool, adminAta, admin.publicKey, BigInt(TOTAL_POOL.toString())),
         createTransferInstruction(adminAta, crPoolToken, admin.publicKey, BigInt(TOTAL_POOL.toString()))
       ), [admin]);
+      await activatePool(crPoolState, crPoolToken);
 
       // User claims on Day 5
       await warpTo(crStart + 5 * SECONDS_PER_DAY + 3600);
@@ -1331,7 +1521,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
       const [crStake] = getUserStakePda(crPoolState, crUser.publicKey);
       const [crMarker] = getClaimMarkerPda(crPoolState, crUser.publicKey);
       const crUserAta = await getOrCreateATABankrun(crPool, crUser.publicKey, crUser);
-      await program.methods.claimAirdrop(crAmount, getMerkleProof(crMerkleLayers, computeLeaf(crUser.publicKey, crAmount)))
+      await program.methods.claimAirdrop(crAmount, getMerkleProof(crMerkleLayers, computeLeaf(crUser.publicKey, crAmount)), new BN(0), 0)
         .accounts({ user: crUser.publicKey, poolState: crPoolState, claimMarker: crMarker, userStake: crStake, poolTokenAccount: crPoolToken, userTokenAccount: crUserAta, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID })
         .signers([crUser]).rpc();
     });
@@ -1388,7 +1578,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
       reStart = Math.floor(Date.now() / 1000) + 1000;
       await warpTo(reStart - 100);
 
-      await program.methods.initializePool(new BN(reStart), Array.from(multiMerkleRoot), computeDailyRewards())
+      await program.methods.initializePool(new BN(reStart), Array.from(multiMerkleRoot), computeDailyRewards(), defaultPoolConfig())
         .accounts({
           admin: admin.publicKey,
           poolState: rePoolState,
@@ -1404,6 +1594,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
         createMintToInstruction(rePool, adminAta, admin.publicKey, BigInt(TOTAL_POOL.toString())),
         createTransferInstruction(adminAta, rePoolToken, admin.publicKey, BigInt(TOTAL_POOL.toString()))
       ), [admin]);
+      await activatePool(rePoolState, rePoolToken);
     });
 
     it("UnauthorizedAdmin: non-admin cannot recover", async () => {
@@ -1415,8 +1606,10 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
           .accounts({
             admin: alice.publicKey,
             poolState: rePoolState,
+            approval: null,
             poolTokenAccount: rePoolToken,
-            adminTokenAccount: aliceAta,
+            tokenMint: rePool,
+            destinationTokenAccount: aliceAta,
             tokenProgram: TOKEN_PROGRAM_ID,
           }).signers([alice]).rpc();
         expect.fail("Should have failed with UnauthorizedAdmin");
@@ -1438,8 +1631,10 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
           .accounts({
             admin: admin.publicKey,
             poolState: rePoolState,
+            approval: null,
             poolTokenAccount: rePoolToken,
-            adminTokenAccount: adminAta,
+            tokenMint: rePool,
+            destinationTokenAccount: adminAta,
             tokenProgram: TOKEN_PROGRAM_ID,
           }).signers([admin]).rpc();
         expect.fail("Should have failed with ClaimWindowStillOpen");
@@ -1459,8 +1654,10 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
         .accounts({
           admin: admin.publicKey,
           poolState: rePoolState,
+          approval: null,
           poolTokenAccount: rePoolToken,
-          adminTokenAccount: adminAta,
+          tokenMint: rePool,
+          destinationTokenAccount: adminAta,
           tokenProgram: TOKEN_PROGRAM_ID,
         }).signers([admin]).rpc();
 
@@ -1474,8 +1671,10 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
           .accounts({
             admin: admin.publicKey,
             poolState: rePoolState,
+            approval: null,
             poolTokenAccount: rePoolToken,
-            adminTokenAccount: adminAta,
+            tokenMint: rePool,
+            destinationTokenAccount: adminAta,
             tokenProgram: TOKEN_PROGRAM_ID,
           }).signers([admin]).rpc();
         expect.fail("Should have failed with NothingToRecover");
@@ -1500,7 +1699,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
       tpStart = Math.floor(Date.now() / 1000) + 1000;
       await warpTo(tpStart - 100);
 
-      await program.methods.initializePool(new BN(tpStart), Array.from(multiMerkleRoot), computeDailyRewards())
+      await program.methods.initializePool(new BN(tpStart), Array.from(multiMerkleRoot), computeDailyRewards(), defaultPoolConfig())
         .accounts({
           admin: admin.publicKey,
           poolState: tpPoolState,
@@ -1516,6 +1715,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
         createMintToInstruction(tpPool, adminAta, admin.publicKey, BigInt(TOTAL_POOL.toString())),
         createTransferInstruction(adminAta, tpPoolToken, admin.publicKey, BigInt(TOTAL_POOL.toString()))
       ), [admin]);
+      await activatePool(tpPoolState, tpPoolToken);
     });
 
     it("ClaimWindowStillOpen: recover before day 40", async () => {
@@ -1527,8 +1727,10 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
           .accounts({
             admin: admin.publicKey,
             poolState: tpPoolState,
+            approval: null,
             poolTokenAccount: tpPoolToken,
-            adminTokenAccount: adminAta,
+            tokenMint: tpPool,
+            destinationTokenAccount: adminAta,
             tokenProgram: TOKEN_PROGRAM_ID,
           }).signers([admin]).rpc();
         expect.fail("Should have failed with ClaimWindowStillOpen");
@@ -1548,8 +1750,10 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
         .accounts({
           admin: admin.publicKey,
           poolState: tpPoolState,
+          approval: null,
           poolTokenAccount: tpPoolToken,
-          adminTokenAccount: adminAta,
+          tokenMint: tpPool,
+          destinationTokenAccount: adminAta,
           tokenProgram: TOKEN_PROGRAM_ID,
         }).signers([admin]).rpc();
 
@@ -1562,8 +1766,10 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
           .accounts({
             admin: admin.publicKey,
             poolState: tpPoolState,
+            approval: null,
             poolTokenAccount: tpPoolToken,
-            adminTokenAccount: adminAta,
+            tokenMint: tpPool,
+            destinationTokenAccount: adminAta,
             tokenProgram: TOKEN_PROGRAM_ID,
           }).signers([admin]).rpc();
         expect.fail("Should have failed with NothingToRecover");
@@ -1588,7 +1794,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
       ppStart = Math.floor(Date.now() / 1000) + 1000;
       await warpTo(ppStart - 100);
 
-      await program.methods.initializePool(new BN(ppStart), Array.from(multiMerkleRoot), computeDailyRewards())
+      await program.methods.initializePool(new BN(ppStart), Array.from(multiMerkleRoot), computeDailyRewards(), defaultPoolConfig())
         .accounts({
           admin: admin.publicKey,
           poolState: ppPoolState,
@@ -1601,13 +1807,13 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
     });
 
     it("AlreadyPaused: pause an already-paused pool", async () => {
-      await program.methods.pausePool().accounts({ admin: admin.publicKey, poolState: ppPoolState }).signers([admin]).rpc();
+      await program.methods.pausePool(new BN(0), 0).accounts({ admin: admin.publicKey, poolState: ppPoolState }).signers([admin]).rpc();
 
       const clock = await context.banksClient.getClock();
       await warpTo(Number(clock.unixTimestamp) + 1);
 
       try {
-        await program.methods.pausePool().accounts({ admin: admin.publicKey, poolState: ppPoolState }).signers([admin]).rpc();
+        await program.methods.pausePool(new BN(0), 0).accounts({ admin: admin.publicKey, poolState: ppPoolState }).signers([admin]).rpc();
         expect.fail("Should have failed with AlreadyPaused");
       } catch (e: any) {
         const msg = (e.message || "").toString();
@@ -1672,7 +1878,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
       srStart = Math.floor(Date.now() / 1000) + 1000;
       await warpTo(srStart - 100);
 
-      await program.methods.initializePool(new BN(srStart), Array.from(srMerkleRoot), computeDailyRewards())
+      await program.methods.initializePool(new BN(srStart), Array.from(srMerkleRoot), computeDailyRewards(), defaultPoolConfig())
         .accounts({
           admin: admin.publicKey,
           poolState: srPoolState,
@@ -1688,6 +1894,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
         createMintToInstruction(srPool, adminAta, admin.publicKey, BigInt(TOTAL_POOL.toString())),
         createTransferInstruction(adminAta, srPoolToken, admin.publicKey, BigInt(TOTAL_POOL.toString()))
       ), [admin]);
+      await activatePool(srPoolState, srPoolToken);
     });
 
     it("unstake without snapshot fails with SnapshotRequiredFirst", async () => {
@@ -1698,7 +1905,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
       const [srStake] = getUserStakePda(srPoolState, srUser.publicKey);
       const [srMarker] = getClaimMarkerPda(srPoolState, srUser.publicKey);
       const srUserAtaClaim = await getOrCreateATABankrun(srPool, srUser.publicKey, srUser);
-      await program.methods.claimAirdrop(srAmount, getMerkleProof(srMerkleLayers, computeLeaf(srUser.publicKey, srAmount)))
+      await program.methods.claimAirdrop(srAmount, getMerkleProof(srMerkleLayers, computeLeaf(srUser.publicKey, srAmount)), new BN(0), 0)
         .accounts({ user: srUser.publicKey, poolState: srPoolState, claimMarker: srMarker, userStake: srStake, poolTokenAccount: srPoolToken, userTokenAccount: srUserAtaClaim, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID })
         .signers([srUser]).rpc();
 
@@ -1713,8 +1920,11 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
             poolState: srPoolState,
             userStake: srStake,
             poolTokenAccount: srPoolToken,
+            tokenMint: srPool,
             userTokenAccount: srUserAta,
             tokenProgram: TOKEN_PROGRAM_ID,
+            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
           }).signers([srUser]).rpc();
         expect.fail("Should have failed with SnapshotRequiredFirst");
       } catch (e: any) {
@@ -1738,7 +1948,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
       await fundAccount(d0User.publicKey);
       await warpTo(d0Start - 100);
 
-      await program.methods.initializePool(new BN(d0Start), Array.from(getMerkleRoot(d0Merkle)), computeDailyRewards())
+      await program.methods.initializePool(new BN(d0Start), Array.from(getMerkleRoot(d0Merkle)), computeDailyRewards(), defaultPoolConfig())
         .accounts({ admin: admin.publicKey, poolState: d0PoolState, tokenMint: d0Pool, poolTokenAccount: d0PoolToken, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID, rent: SYSVAR_RENT_PUBKEY })
         .signers([admin]).rpc();
 
@@ -1747,6 +1957,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
         createMintToInstruction(d0Pool, adminAta, admin.publicKey, BigInt(TOTAL_POOL.toString())),
         createTransferInstruction(adminAta, d0PoolToken, admin.publicKey, BigInt(TOTAL_POOL.toString()))
       ), [admin]);
+      await activatePool(d0PoolState, d0PoolToken);
 
       // Claim on day 0 (just after start, no snapshots taken)
       await warpTo(d0Start + 1);
@@ -1754,7 +1965,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
       const [d0Marker] = getClaimMarkerPda(d0PoolState, d0User.publicKey);
       const d0UserAta = await getOrCreateATABankrun(d0Pool, d0User.publicKey, d0User);
 
-      await program.methods.claimAirdrop(d0Amount, getMerkleProof(d0Merkle, computeLeaf(d0User.publicKey, d0Amount)))
+      await program.methods.claimAirdrop(d0Amount, getMerkleProof(d0Merkle, computeLeaf(d0User.publicKey, d0Amount)), new BN(0), 0)
         .accounts({ user: d0User.publicKey, poolState: d0PoolState, claimMarker: d0Marker, userStake: d0Stake, poolTokenAccount: d0PoolToken, userTokenAccount: d0UserAta, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID })
         .signers([d0User]).rpc();
 
@@ -1763,7 +1974,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
       await warpTo(Number(clock.unixTimestamp) + 1);
 
       await program.methods.unstake()
-        .accounts({ user: d0User.publicKey, poolState: d0PoolState, userStake: d0Stake, poolTokenAccount: d0PoolToken, userTokenAccount: d0UserAta, tokenProgram: TOKEN_PROGRAM_ID })
+        .accounts({ user: d0User.publicKey, poolState: d0PoolState, userStake: d0Stake, poolTokenAccount: d0PoolToken, tokenMint: d0Pool, userTokenAccount: d0UserAta, tokenProgram: TOKEN_PROGRAM_ID, associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID, systemProgram: SystemProgram.programId })
         .signers([d0User]).rpc();
 
       // User should only have the airdrop tokens (0 rewards on day 0)
@@ -1781,7 +1992,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
       const sdStart = Math.floor(Date.now() / 1000) + 1000;
       await warpTo(sdStart - 100);
 
-      await program.methods.initializePool(new BN(sdStart), Array.from(multiMerkleRoot), computeDailyRewards())
+      await program.methods.initializePool(new BN(sdStart), Array.from(multiMerkleRoot), computeDailyRewards(), defaultPoolConfig())
         .accounts({
           admin: admin.publicKey,
           poolState: sdPoolState,
@@ -1817,7 +2028,7 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
       const pastStart = now - 3600;
 
       try {
-        await program.methods.initializePool(new BN(pastStart), Array.from(multiMerkleRoot), computeDailyRewards())
+        await program.methods.initializePool(new BN(pastStart), Array.from(multiMerkleRoot), computeDailyRewards(), defaultPoolConfig())
           .accounts({
             admin: admin.publicKey,
             poolState: pState,
@@ -1833,6 +2044,4132 @@ describe("Memeland Airdrop Staking - Optimized Bankrun Suite", () => {
         expect(msg).to.satisfy((m: string) => m.includes("StartTimeInPast") || m.includes("6000") || m.includes("0x1770"));
       }
     });
+
+    it("accepts a start_time 30 days out", async () => {
+      const p = await setupPool([computeLeaf(Keypair.generate().publicKey, new BN(1))], {}, 30 * SECONDS_PER_DAY, false);
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.startTime.toNumber()).to.equal(p.start);
+    });
+
+    it("StartTimeTooFar: rejects a start_time beyond MAX_START_DELAY_SECONDS", async () => {
+      await expectError(
+        () => setupPool([computeLeaf(Keypair.generate().publicKey, new BN(1))], {}, 10 * 365 * SECONDS_PER_DAY, false),
+        "StartTimeTooFar",
+      );
+    });
+  });
+
+  describe("Minimum stake duration (anti flash-loan)", () => {
+    const msUser = Keypair.generate();
+    const msAmount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+
+    before(async () => {
+      await fundAccount(msUser.publicKey);
+      p = await setupPool([computeLeaf(msUser.publicKey, msAmount)], { minStakeSeconds: new BN(2 * 3600) });
+      await warpTo(p.start + SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+      await claimFor(p, msUser, msAmount);
+    });
+
+    it("unstake before min_stake_seconds elapses fails with StakeLocked", async () => {
+      await tick(3600);
+      await expectError(() => unstakeFor(p, msUser), "StakeLocked");
+    });
+
+    it("unstake after min_stake_seconds elapses succeeds", async () => {
+      await tick(3600 + 1);
+      await unstakeFor(p, msUser);
+      const [stake] = getUserStakePda(p.poolState, msUser.publicKey);
+      expect(await context.banksClient.getAccount(stake)).to.be.null;
+    });
+  });
+
+  describe("Early-exit penalty", () => {
+    const earlyUser = Keypair.generate();
+    const lateUser = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    const penaltyBps = 1000;
+    let penalized: any;
+    let control: any;
+
+    before(async () => {
+      await fundAccount(earlyUser.publicKey);
+      await fundAccount(lateUser.publicKey);
+      const leaves = [computeLeaf(earlyUser.publicKey, amount), computeLeaf(lateUser.publicKey, amount)];
+      penalized = await setupPool(leaves, { earlyExitPenaltyBps: penaltyBps });
+      control = await setupPool(leaves, {}, penalized.start - Number((await context.banksClient.getClock()).unixTimestamp));
+      await warpTo(penalized.start + 1);
+      for (const p of [penalized, control]) {
+        await claimFor(p, earlyUser, amount);
+        await claimFor(p, lateUser, amount);
+      }
+    });
+
+    it("early exit receives rewards reduced by staked_amount * bps / 10000", async () => {
+      await warpTo(penalized.start + 5 * SECONDS_PER_DAY + 60);
+      await snapshotPool(penalized);
+      await snapshotPool(control);
+
+      const penalizedAta = await unstakeFor(penalized, earlyUser);
+      const controlAta = await unstakeFor(control, earlyUser);
+      const penalizedRewards = (await tokenBalance(penalizedAta)) - BigInt(amount.toString());
+      const controlRewards = (await tokenBalance(controlAta)) - BigInt(amount.toString());
+      const penalty = BigInt(amount.toString()) * BigInt(penaltyBps) / 10000n;
+      expect(controlRewards - penalizedRewards).to.equal(penalty);
+    });
+
+    it("exit after the staking period receives full rewards", async () => {
+      await warpTo(penalized.start + 21 * SECONDS_PER_DAY);
+      await snapshotPool(penalized);
+      await snapshotPool(control);
+
+      const penalizedAta = await unstakeFor(penalized, lateUser);
+      const controlAta = await unstakeFor(control, lateUser);
+      expect(await tokenBalance(penalizedAta)).to.equal(await tokenBalance(controlAta));
+    });
+  });
+
+  describe("Blacklist", () => {
+    const blUser = Keypair.generate();
+    const blAmount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+
+    before(async () => {
+      await fundAccount(blUser.publicKey);
+      p = await setupPool([computeLeaf(blUser.publicKey, blAmount)]);
+      await warpTo(p.start + 60);
+    });
+
+    it("blacklisted address cannot claim", async () => {
+      const [blacklistEntry] = getBlacklistPda(p.poolState, blUser.publicKey);
+      await program.methods.blacklistAddress(blUser.publicKey)
+        .accounts({ admin: admin.publicKey, poolState: p.poolState, blacklistEntry, systemProgram: SystemProgram.programId })
+        .signers([admin]).rpc();
+
+      await expectError(() => claimFor(p, blUser, blAmount), "AddressBlacklisted");
+    });
+
+    it("non-admin cannot manage the blacklist", async () => {
+      const [blacklistEntry] = getBlacklistPda(p.poolState, blUser.publicKey);
+      await expectError(() => program.methods.unblacklistAddress(blUser.publicKey)
+        .accounts({ admin: alice.publicKey, poolState: p.poolState, blacklistEntry })
+        .signers([alice]).rpc(), "UnauthorizedAdmin");
+    });
+
+    it("unblacklisted address can claim", async () => {
+      const [blacklistEntry] = getBlacklistPda(p.poolState, blUser.publicKey);
+      await program.methods.unblacklistAddress(blUser.publicKey)
+        .accounts({ admin: admin.publicKey, poolState: p.poolState, blacklistEntry })
+        .signers([admin]).rpc();
+      await tick();
+
+      const { userAta } = await claimFor(p, blUser, blAmount);
+      expect(await tokenBalance(userAta)).to.equal(BigInt(blAmount.toString()));
+    });
+  });
+
+  describe("get_pool_stats view", () => {
+    const statsUsers = [Keypair.generate(), Keypair.generate()];
+    const statsAmounts = [new BN(1_000_000).mul(new BN(1e9)), new BN(3_000_000).mul(new BN(1e9))];
+    let p: any;
+
+    before(async () => {
+      for (const u of statsUsers) await fundAccount(u.publicKey);
+      p = await setupPool(statsUsers.map((u, i) => computeLeaf(u.publicKey, statsAmounts[i])));
+      await warpTo(p.start + 60);
+      for (let i = 0; i < statsUsers.length; i++) await claimFor(p, statsUsers[i], statsAmounts[i]);
+    });
+
+    it("returns totals matching pool state after claims", async () => {
+      const stats = await program.methods.getPoolStats()
+        .accounts({ poolState: p.poolState, poolTokenAccount: p.poolToken })
+        .view();
+      const state = await program.account.poolState.fetch(p.poolState);
+      const claimed = statsAmounts[0].add(statsAmounts[1]);
+
+      expect(stats.totalStaked.toString()).to.equal(state.totalStaked.toString());
+      expect(stats.totalAirdropClaimed.toString()).to.equal(claimed.toString());
+      expect(stats.airdropRemaining.toString()).to.equal(AIRDROP_POOL.sub(claimed).toString());
+      expect(stats.poolBalance.toString()).to.equal(TOTAL_POOL.sub(claimed).toString());
+    });
+  });
+
+  describe("Event timestamps", () => {
+    const evUser = Keypair.generate();
+    const evAmount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+
+    before(async () => {
+      await fundAccount(evUser.publicKey);
+      p = await setupPool([computeLeaf(evUser.publicKey, evAmount)]);
+    });
+
+    it("emitted timestamp matches the mocked clock", async () => {
+      const mocked = p.start + 2 * SECONDS_PER_DAY + 1234;
+      await warpTo(mocked);
+
+      const events = await sendAndGetEvents(
+        program.methods.pausePool(new BN(0), 0).accounts({ admin: admin.publicKey, poolState: p.poolState }),
+        [admin]
+      );
+      const paused = findEvent(events, "PoolPausedEvent");
+      expect(paused.data.timestamp.toNumber()).to.equal(mocked);
+    });
+
+    it("Unstaked carries the exit day and timestamp", async () => {
+      await program.methods.unpausePool().accounts({ admin: admin.publicKey, poolState: p.poolState }).signers([admin]).rpc();
+      await snapshotPool(p);
+      const { userStake, userAta } = await claimFor(p, evUser, evAmount);
+      await tick();
+      const now = Number((await context.banksClient.getClock()).unixTimestamp);
+
+      const events = await sendAndGetEvents(
+        program.methods.unstake().accounts({ user: evUser.publicKey, poolState: p.poolState, userStake, poolTokenAccount: p.poolToken, tokenMint: p.mint, userTokenAccount: userAta, tokenProgram: TOKEN_PROGRAM_ID, associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID, systemProgram: SystemProgram.programId }),
+        [evUser]
+      );
+      const unstaked = findEvent(events, "Unstaked");
+      expect(unstaked.data.day.toNumber()).to.equal(2);
+      expect(unstaked.data.timestamp.toNumber()).to.equal(now);
+    });
+  });
+
+  describe("close_empty_stake", () => {
+    const fullUser = Keypair.generate();
+    const emptyUser = Keypair.generate();
+    const fullAmount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+
+    before(async () => {
+      await fundAccount(fullUser.publicKey);
+      await fundAccount(emptyUser.publicKey);
+      p = await setupPool([computeLeaf(fullUser.publicKey, fullAmount)]);
+      await warpTo(p.start + 60);
+      await claimFor(p, fullUser, fullAmount);
+      // An opened-but-unfunded account is a UserStake with staked_amount == 0
+      const [userStake] = getUserStakePda(p.poolState, emptyUser.publicKey);
+      await program.methods.openStakeAccount()
+        .accounts({ user: emptyUser.publicKey, poolState: p.poolState, userStake, systemProgram: SystemProgram.programId })
+        .signers([emptyUser]).rpc();
+    });
+
+    it("rejects closing a non-empty stake with StakeNotEmpty", async () => {
+      const [userStake] = getUserStakePda(p.poolState, fullUser.publicKey);
+      await expectError(() => program.methods.closeEmptyStake()
+        .accounts({ user: fullUser.publicKey, poolState: p.poolState, userStake })
+        .signers([fullUser]).rpc(), "StakeNotEmpty");
+    });
+
+    it("closes an empty stake and decrements active_stakers", async () => {
+      const [userStake] = getUserStakePda(p.poolState, emptyUser.publicKey);
+      const before = await program.account.poolState.fetch(p.poolState);
+      await program.methods.closeEmptyStake()
+        .accounts({ user: emptyUser.publicKey, poolState: p.poolState, userStake })
+        .signers([emptyUser]).rpc();
+
+      expect(await context.banksClient.getAccount(userStake)).to.be.null;
+      const after = await program.account.poolState.fetch(p.poolState);
+      expect(after.activeStakers).to.equal(before.activeStakers - 1);
+    });
+  });
+
+  describe("Reward fee", () => {
+    const feeUser = Keypair.generate();
+    const noTreasuryUser = Keypair.generate();
+    const treasury = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    const feeBps = 500;
+    let feePool: any;
+    let control: any;
+    let treasuryAta: PublicKey;
+
+    before(async () => {
+      await fundAccount(feeUser.publicKey);
+      await fundAccount(noTreasuryUser.publicKey);
+      await fundAccount(treasury.publicKey);
+      const leaves = [computeLeaf(feeUser.publicKey, amount), computeLeaf(noTreasuryUser.publicKey, amount)];
+      feePool = await setupPool(leaves, { rewardFeeBps: feeBps, treasury: treasury.publicKey });
+      control = await setupPool(leaves, {}, feePool.start - Number((await context.banksClient.getClock()).unixTimestamp));
+      treasuryAta = await getOrCreateATABankrun(feePool.mint, treasury.publicKey, treasury);
+      await warpTo(feePool.start + 1);
+      for (const p of [feePool, control]) {
+        await claimFor(p, feeUser, amount);
+        await claimFor(p, noTreasuryUser, amount);
+      }
+    });
+
+    it("rejects a fee above 10000 bps or without a treasury", async () => {
+      const mint = await createMintBankrun(TOKEN_DECIMALS, admin.publicKey);
+      const [poolState] = getPoolStatePda(mint);
+      const [poolToken] = getPoolTokenPda(poolState);
+      for (const config of [{ rewardFeeBps: 10_001, treasury: treasury.publicKey }, { rewardFeeBps: 100 }]) {
+        await expectError(() => program.methods.initializePool(new BN(feePool.start), Array.from(Buffer.alloc(32)), computeDailyRewards(), defaultPoolConfig(config))
+          .accounts({ admin: admin.publicKey, poolState, tokenMint: mint, poolTokenAccount: poolToken, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID, rent: SYSVAR_RENT_PUBKEY })
+          .signers([admin]).rpc(), "InvalidPoolConfig");
+      }
+    });
+
+    it("requires the treasury token account when a fee is due", async () => {
+      await warpTo(feePool.start + 21 * SECONDS_PER_DAY);
+      await snapshotPool(feePool);
+      await snapshotPool(control);
+      await expectError(() => unstakeFor(feePool, noTreasuryUser), "MissingTreasuryAccount");
+    });
+
+    it("routes rewards * bps / 10000 to the treasury and the rest to the user", async () => {
+      const userAta = await unstakeFor(feePool, feeUser, treasuryAta);
+      const controlAta = await unstakeFor(control, feeUser);
+      const fee = await tokenBalance(treasuryAta);
+      const grossRewards = (await tokenBalance(controlAta)) - BigInt(amount.toString());
+      const netRewards = (await tokenBalance(userAta)) - BigInt(amount.toString());
+
+      expect(fee).to.equal(grossRewards * BigInt(feeBps) / 10000n);
+      expect(netRewards + fee).to.equal(grossRewards);
+    });
+  });
+
+  describe("Unstake creates missing ATA", () => {
+    const ataUser = Keypair.generate();
+    const sink = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+
+    before(async () => {
+      await fundAccount(ataUser.publicKey);
+      p = await setupPool([computeLeaf(ataUser.publicKey, amount)]);
+      await warpTo(p.start + 1);
+    });
+
+    it("user who closed their ATA can unstake and the ATA is recreated and funded", async () => {
+      const { userAta } = await claimFor(p, ataUser, amount);
+      const sinkAta = await getOrCreateATABankrun(p.mint, sink.publicKey);
+      await provider.sendAndConfirm(new anchor.web3.Transaction().add(
+        createTransferInstruction(userAta, sinkAta, ataUser.publicKey, BigInt(amount.toString())),
+        createCloseAccountInstruction(userAta, ataUser.publicKey, ataUser.publicKey)
+      ), [ataUser]);
+      expect(await getAccountBankrun(userAta)).to.be.null;
+
+      await warpTo(p.start + 3 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+      await unstakeFor(p, ataUser);
+
+      expect(await tokenBalance(userAta) > 0n).to.be.true;
+    });
+  });
+
+  describe("Double unstake guard", () => {
+    const dUser = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+
+    before(async () => {
+      await fundAccount(dUser.publicKey);
+      p = await setupPool([computeLeaf(dUser.publicKey, amount)]);
+      await warpTo(p.start + 1);
+      await claimFor(p, dUser, amount);
+    });
+
+    it("second unstake on the closed stake fails with account-not-initialized", async () => {
+      await warpTo(p.start + 2 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+      const userAta = await unstakeFor(p, dUser);
+      const balanceAfterFirst = await tokenBalance(userAta);
+      await tick();
+
+      await expectError(() => unstakeFor(p, dUser), "AccountNotInitialized", "3012", "0xbc4");
+      expect(await tokenBalance(userAta)).to.equal(balanceAfterFirst);
+    });
+  });
+
+  describe("Claim-day reward boundary", () => {
+    const cdUser = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    const claimDay = 2;
+    let including: any;
+    let excluding: any;
+
+    before(async () => {
+      await fundAccount(cdUser.publicKey);
+      const leaves = [computeLeaf(cdUser.publicKey, amount)];
+      including = await setupPool(leaves, { rewardsFromClaimDay: true, includeClaimDay: true });
+      excluding = await setupPool(leaves, { rewardsFromClaimDay: true, includeClaimDay: false },
+        including.start - Number((await context.banksClient.getClock()).unixTimestamp));
+      await warpTo(including.start + claimDay * SECONDS_PER_DAY + 60);
+      for (const p of [including, excluding]) {
+        await snapshotPool(p);
+        await claimFor(p, cdUser, amount);
+      }
+    });
+
+    it("reward sums differ by the claim-day share", async () => {
+      await warpTo(including.start + 5 * SECONDS_PER_DAY + 60);
+      await snapshotPool(including);
+      await snapshotPool(excluding);
+      const state = await program.account.poolState.fetch(including.poolState);
+      // Shares are summed before flooring, so the difference is taken between the two sums
+      const claimDayShare = accruedRewards(amount, state, claimDay, 5) - accruedRewards(amount, state, claimDay + 1, 5);
+
+      const includingAta = await unstakeFor(including, cdUser);
+      const includingRewards = (await tokenBalance(includingAta)) - BigInt(amount.toString());
+      const excludingAta = await unstakeFor(excluding, cdUser);
+      const excludingRewards = (await tokenBalance(excludingAta)) - BigInt(amount.toString());
+
+      expect(claimDayShare > 0n).to.be.true;
+      expect(includingRewards - excludingRewards).to.equal(claimDayShare);
+    });
+  });
+
+  describe("Averaged reward mode", () => {
+    const leaver = Keypair.generate();
+    const stayer = Keypair.generate();
+    const amount = new BN(10_000_000).mul(new BN(1e9));
+    let pointInTime: any;
+    let averaged: any;
+
+    function expectedRewards(state: any, averagedMode: boolean, days: number): bigint {
+      return accruedRewards(amount, state, 0, days, (d) => {
+        const current = BigInt(state.dailySnapshots[d].toString());
+        const previous = d > 0 ? BigInt(state.dailySnapshots[d - 1].toString()) : 0n;
+        return averagedMode && previous > current ? previous : current;
+      });
+    }
+
+    before(async () => {
+      await fundAccount(leaver.publicKey);
+      await fundAccount(stayer.publicKey);
+      const leaves = [computeLeaf(leaver.publicKey, amount), computeLeaf(stayer.publicKey, amount)];
+      pointInTime = await setupPool(leaves);
+      averaged = await setupPool(leaves, { rewardMode: 1 },
+        pointInTime.start - Number((await context.banksClient.getClock()).unixTimestamp));
+      await warpTo(pointInTime.start + 1);
+      for (const p of [pointInTime, averaged]) {
+        await claimFor(p, leaver, amount);
+        await claimFor(p, stayer, amount);
+      }
+    });
+
+    it("rejects an unknown reward mode", async () => {
+      const mint = await createMintBankrun(TOKEN_DECIMALS, admin.publicKey);
+      const [poolState] = getPoolStatePda(mint);
+      const [poolToken] = getPoolTokenPda(poolState);
+      await expectError(() => program.methods.initializePool(new BN(pointInTime.start + SECONDS_PER_DAY), Array.from(Buffer.alloc(32)), computeDailyRewards(), defaultPoolConfig({ rewardMode: 2 }))
+        .accounts({ admin: admin.publicKey, poolState, tokenMint: mint, poolTokenAccount: poolToken, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID, rent: SYSVAR_RENT_PUBKEY })
+        .signers([admin]).rpc(), "InvalidPoolConfig");
+    });
+
+    it("a mid-program stake change is smoothed over two days in averaged mode", async () => {
+      // Leaver exits on day 3, so the day-3 snapshot drops while day 2 still includes the stake
+      await warpTo(pointInTime.start + 3 * SECONDS_PER_DAY + 60);
+      for (const p of [pointInTime, averaged]) {
+        await snapshotPool(p);
+        await unstakeFor(p, leaver);
+      }
+
+      await warpTo(pointInTime.start + 6 * SECONDS_PER_DAY + 60);
+      await snapshotPool(pointInTime);
+      await snapshotPool(averaged);
+      const state = await program.account.poolState.fetch(averaged.poolState);
+
+      const pitAta = await unstakeFor(pointInTime, stayer);
+      const pitRewards = (await tokenBalance(pitAta)) - BigInt(amount.toString());
+      const avgAta = await unstakeFor(averaged, stayer);
+      const avgRewards = (await tokenBalance(avgAta)) - BigInt(amount.toString());
+
+      expect(pitRewards).to.equal(expectedRewards(state, false, 6));
+      expect(avgRewards).to.equal(expectedRewards(state, true, 6));
+      // Weighing the day-3 drop against day 2's higher reading yields a smaller share than point-in-time
+      expect(avgRewards < pitRewards).to.be.true;
+    });
+
+    it("a day whose stake grew pays out no more than its reward", async () => {
+      // The whole airdrop is claimed, so the two stakes are the entire snapshot; the late
+      // 2x-tier claim raises total_staked by its bonus on day 2
+      const early = Keypair.generate();
+      const late = Keypair.generate();
+      const earlyAmount = AIRDROP_POOL.divn(2);
+      const lateAmount = AIRDROP_POOL.sub(earlyAmount);
+      await fundAccount(early.publicKey);
+      await fundAccount(late.publicKey);
+      const p = await setupPool([computeLeaf(early.publicKey, earlyAmount), computeLeaf(late.publicKey, lateAmount)],
+        { rewardMode: 1, lockMultipliersBps: [0, 20_000, 0, 0] });
+      await warpTo(p.start + 60);
+      const { userStake: earlyStake } = await claimFor(p, early, earlyAmount);
+      await warpTo(p.start + 2 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+      const { userStake: lateStake } = await claimFor(p, late, lateAmount, 1);
+      await warpTo(p.start + 3 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.dailySnapshots[2].gt(state.dailySnapshots[1])).to.be.true;
+      let paid = new BN(0);
+      for (const userStake of [earlyStake, lateStake]) {
+        const [day2] = await program.methods.calculateRewardsRange(new BN(2), new BN(3))
+          .accounts({ poolState: p.poolState, userStake })
+          .view();
+        paid = paid.add(day2);
+      }
+      expect(paid.lte(state.dailyRewards[2])).to.be.true;
+    });
+
+    it("claim_day_reward waits for the current day's snapshot", async () => {
+      // Unrecorded days would otherwise be priced on the previous day's reading
+      const user = Keypair.generate();
+      await fundAccount(user.publicKey);
+      const p = await setupPool([computeLeaf(user.publicKey, amount)], { rewardMode: 1 });
+      await warpTo(p.start + 60);
+      const { userStake, userAta } = await claimFor(p, user, amount);
+      await warpTo(p.start + 3 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+      await warpTo(p.start + 5 * SECONDS_PER_DAY + 60);
+
+      const claimDay = () => program.methods.claimDayReward(new BN(1))
+        .accounts({ user: user.publicKey, poolState: p.poolState, userStake, poolTokenAccount: p.poolToken, userTokenAccount: userAta, treasuryTokenAccount: null, tokenProgram: TOKEN_PROGRAM_ID })
+        .signers([user]).rpc();
+      await expectError(claimDay, "SnapshotRequiredFirst");
+
+      await snapshotPool(p);
+      const [dayShare] = await program.methods.calculateRewardsRange(new BN(1), new BN(2)).accounts({ poolState: p.poolState, userStake }).view();
+      const before = await tokenBalance(userAta);
+      await claimDay();
+      expect(await tokenBalance(userAta)).to.equal(before + BigInt(dayShare.toString()));
+    });
+  });
+
+  describe("open_stake_account", () => {
+    const openUser = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+    let userStake: PublicKey;
+
+    before(async () => {
+      await fundAccount(openUser.publicKey);
+      p = await setupPool([computeLeaf(openUser.publicKey, amount)]);
+      [userStake] = getUserStakePda(p.poolState, openUser.publicKey);
+    });
+
+    it("opens an empty stake before the pool starts", async () => {
+      const before = await program.account.poolState.fetch(p.poolState);
+      await program.methods.openStakeAccount()
+        .accounts({ user: openUser.publicKey, poolState: p.poolState, userStake, systemProgram: SystemProgram.programId })
+        .signers([openUser]).rpc();
+
+      const stake = await program.account.userStake.fetch(userStake);
+      expect(stake.owner.toBase58()).to.equal(openUser.publicKey.toBase58());
+      expect(stake.stakedAmount.toNumber()).to.equal(0);
+      const after = await program.account.poolState.fetch(p.poolState);
+      expect(after.activeStakers).to.equal(before.activeStakers + 1);
+    });
+
+    it("claim funds the opened stake without counting a new staker", async () => {
+      await warpTo(p.start + SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+      const before = await program.account.poolState.fetch(p.poolState);
+      await claimFor(p, openUser, amount);
+
+      const stake = await program.account.userStake.fetch(userStake);
+      expect(stake.stakedAmount.toString()).to.equal(amount.toString());
+      expect(stake.claimDay.toNumber()).to.equal(1);
+      const after = await program.account.poolState.fetch(p.poolState);
+      expect(after.activeStakers).to.equal(before.activeStakers);
+    });
+
+    it("funded stake unstakes with rewards and is closed", async () => {
+      await warpTo(p.start + 4 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+      const userAta = await unstakeFor(p, openUser);
+
+      expect(await tokenBalance(userAta) > BigInt(amount.toString())).to.be.true;
+      expect(await context.banksClient.getAccount(userStake)).to.be.null;
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.activeStakers).to.equal(0);
+    });
+  });
+
+  describe("withdraw_rewards", () => {
+    const wUser = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+    let control: any;
+
+    function withdraw(pool: any, value: BN) {
+      const [userStake] = getUserStakePda(pool.poolState, wUser.publicKey);
+      return program.methods.withdrawRewards(value)
+        .accounts({ user: wUser.publicKey, poolState: pool.poolState, userStake, poolTokenAccount: pool.poolToken, userTokenAccount: getAssociatedTokenAddressSync(pool.mint, wUser.publicKey), treasuryTokenAccount: null, tokenProgram: TOKEN_PROGRAM_ID })
+        .signers([wUser]).rpc();
+    }
+
+    before(async () => {
+      await fundAccount(wUser.publicKey);
+      const leaves = [computeLeaf(wUser.publicKey, amount)];
+      p = await setupPool(leaves);
+      control = await setupPool(leaves, {}, p.start - Number((await context.banksClient.getClock()).unixTimestamp));
+      await warpTo(p.start + 1);
+      await claimFor(p, wUser, amount);
+      await claimFor(control, wUser, amount);
+      await warpTo(p.start + 3 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+    });
+
+    it("rejects withdrawing more than accrued", async () => {
+      const state = await program.account.poolState.fetch(p.poolState);
+      const accrued = new BN(accruedRewards(amount, state, 0, 3).toString());
+      await expectError(() => withdraw(p, accrued.addn(1)), "InsufficientRewards");
+    });
+
+    it("partial withdrawal keeps the stake and leaves the remainder claimable", async () => {
+      const userAta = getAssociatedTokenAddressSync(p.mint, wUser.publicKey);
+      const before = await tokenBalance(userAta);
+      const partial = new BN(1_000).mul(new BN(1e9));
+      await withdraw(p, partial);
+      expect((await tokenBalance(userAta)) - before).to.equal(BigInt(partial.toString()));
+
+      const [userStake] = getUserStakePda(p.poolState, wUser.publicKey);
+      const stake = await program.account.userStake.fetch(userStake);
+      expect(stake.stakedAmount.toString()).to.equal(amount.toString());
+      expect(stake.rewardsWithdrawn.toString()).to.equal(partial.toString());
+
+      await warpTo(p.start + 6 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+      await snapshotPool(control);
+      await unstakeFor(p, wUser);
+      const controlAta = await unstakeFor(control, wUser);
+      // Withdrawn + remaining rewards equal what a single unstake pays
+      expect((await tokenBalance(userAta)) - BigInt(amount.toString()))
+        .to.equal((await tokenBalance(controlAta)) - BigInt(amount.toString()));
+    });
+  });
+
+  describe("AirdropExhausted event", () => {
+    const whale = Keypair.generate();
+    const latecomer = Keypair.generate();
+    const dust = new BN(1);
+    let p: any;
+
+    before(async () => {
+      await fundAccount(whale.publicKey);
+      await fundAccount(latecomer.publicKey);
+      p = await setupPool([computeLeaf(whale.publicKey, AIRDROP_POOL), computeLeaf(latecomer.publicKey, dust)]);
+      await warpTo(p.start + 60);
+    });
+
+    it("fires on the claim that fills the airdrop pool", async () => {
+      const [userStake] = getUserStakePda(p.poolState, whale.publicKey);
+      const [claimMarker] = getClaimMarkerPda(p.poolState, whale.publicKey);
+      const [blacklistEntry] = getBlacklistPda(p.poolState, whale.publicKey);
+      const userAta = await getOrCreateATABankrun(p.mint, whale.publicKey, whale);
+      const events = await sendAndGetEvents(
+        program.methods.claimAirdrop(AIRDROP_POOL, getMerkleProof(p.layers, computeLeaf(whale.publicKey, AIRDROP_POOL)), new BN(0), 0)
+          .accounts({ user: whale.publicKey, poolState: p.poolState, claimMarker, blacklistEntry, userStake, poolTokenAccount: p.poolToken, userTokenAccount: userAta, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID }),
+        [whale]
+      );
+
+      const exhausted = findEvent(events, "AirdropExhausted");
+      expect(exhausted).to.not.be.undefined;
+      expect(exhausted.data.totalClaimed.toString()).to.equal(AIRDROP_POOL.toString());
+      expect((await program.account.poolState.fetch(p.poolState)).airdropExhausted).to.equal(1);
+    });
+
+    it("later claims are rejected and do not re-fire", async () => {
+      await expectError(() => claimFor(p, latecomer, dust), "AirdropPoolExhausted");
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.airdropExhausted).to.equal(1);
+      expect(state.totalAirdropClaimed.toString()).to.equal(AIRDROP_POOL.toString());
+    });
+  });
+
+  describe("Snapshot clock regression", () => {
+    let p: any;
+
+    before(async () => {
+      p = await setupPool([computeLeaf(Keypair.generate().publicKey, new BN(1))]);
+      await warpTo(p.start + 5 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+    });
+
+    it("rejects a snapshot on a regressed clock and keeps snapshot_count", async () => {
+      await warpTo(p.start + 2 * SECONDS_PER_DAY + 60);
+      await expectError(() => snapshotPool(p), "ClockRegression");
+      expect((await program.account.poolState.fetch(p.poolState)).snapshotCount).to.equal(5);
+    });
+
+    it("resumes once the clock catches up", async () => {
+      await warpTo(p.start + 6 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+      expect((await program.account.poolState.fetch(p.poolState)).snapshotCount).to.equal(6);
+    });
+  });
+
+  describe("extend_claim_window", () => {
+    const lateUser = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+
+    function extend(days: number, signer: Keypair = admin) {
+      return program.methods.extendClaimWindow(new BN(days))
+        .accounts({ admin: signer.publicKey, poolState: p.poolState })
+        .signers([signer]).rpc();
+    }
+
+    before(async () => {
+      await fundAccount(lateUser.publicKey);
+      p = await setupPool([computeLeaf(lateUser.publicKey, amount)]);
+      await warpTo(p.start + 60);
+    });
+
+    it("rejects a zero extension and non-admin callers", async () => {
+      await expectError(() => extend(0), "InvalidClaimWindowExtension");
+      await expectError(() => extend(5, alice), "UnauthorizedAdmin");
+    });
+
+    it("extension pushes the deadline out", async () => {
+      await extend(10);
+      expect((await program.account.poolState.fetch(p.poolState)).claimWindowDays.toNumber()).to.equal(CLAIM_WINDOW_DAYS + 10);
+
+      // Claims past the original day-40 deadline are accepted
+      await warpTo(p.start + (CLAIM_WINDOW_DAYS + 5) * SECONDS_PER_DAY);
+      await snapshotPool(p);
+      const { userAta } = await claimFor(p, lateUser, amount);
+      expect(await tokenBalance(userAta)).to.equal(BigInt(amount.toString()));
+
+      await expectError(() => program.methods.recoverExpiredRewards()
+        .accounts({ admin: admin.publicKey, poolState: p.poolState, approval: null, poolTokenAccount: p.poolToken, tokenMint: p.mint, destinationTokenAccount: p.adminAta, tokenProgram: TOKEN_PROGRAM_ID })
+        .signers([admin]).rpc(), "ClaimWindowStillOpen");
+    });
+
+    it("cannot extend once the window has closed", async () => {
+      await warpTo(p.start + (CLAIM_WINDOW_DAYS + 10) * SECONDS_PER_DAY);
+      await expectError(() => extend(5), "InvalidClaimWindowExtension");
+    });
+  });
+
+  describe("Recovery reserves accrued rewards", () => {
+    const holder = Keypair.generate();
+    const amount = new BN(10_000_000).mul(new BN(1e9));
+    let p: any;
+    let control: any;
+
+    function recover(pool: any) {
+      return program.methods.recoverExpiredRewards()
+        .accounts({ admin: admin.publicKey, poolState: pool.poolState, approval: null, poolTokenAccount: pool.poolToken, tokenMint: pool.mint, destinationTokenAccount: pool.adminAta, tokenProgram: TOKEN_PROGRAM_ID })
+        .signers([admin]).rpc();
+    }
+
+    before(async () => {
+      await fundAccount(holder.publicKey);
+      const leaves = [computeLeaf(holder.publicKey, amount)];
+      p = await setupPool(leaves);
+      control = await setupPool(leaves, {}, p.start - Number((await context.banksClient.getClock()).unixTimestamp));
+      await warpTo(p.start + 1);
+      await claimFor(p, holder, amount);
+      await claimFor(control, holder, amount);
+      await warpTo(p.start + TOTAL_DAYS * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+      await snapshotPool(control);
+    });
+
+    it("a user who never exited still receives accrued rewards after recovery", async () => {
+      // Control: the same stake exits before expiry
+      const controlAta = await unstakeFor(control, holder);
+      const expectedRewards = (await tokenBalance(controlAta)) - BigInt(amount.toString());
+
+      await warpTo(p.start + (CLAIM_WINDOW_DAYS + 1) * SECONDS_PER_DAY);
+      await recover(p);
+      expect(await tokenBalance(p.poolToken) >= expectedRewards).to.be.true;
+
+      const userAta = await unstakeFor(p, holder);
+      expect((await tokenBalance(userAta)) - BigInt(amount.toString())).to.equal(expectedRewards);
+    });
+
+    it("the reserve is released once the stake is closed", async () => {
+      await recover(p);
+      expect(await tokenBalance(p.poolToken)).to.equal(0n);
+    });
+  });
+
+  describe("Claim deadline", () => {
+    const early = Keypair.generate();
+    const late = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+
+    function setDeadline(ts: number, signer: Keypair = admin) {
+      return program.methods.setClaimDeadline(new BN(ts))
+        .accounts({ admin: signer.publicKey, poolState: p.poolState })
+        .signers([signer]).rpc();
+    }
+
+    before(async () => {
+      await fundAccount(early.publicKey);
+      await fundAccount(late.publicKey);
+      p = await setupPool([computeLeaf(early.publicKey, amount), computeLeaf(late.publicKey, amount)]);
+      await warpTo(p.start + 60);
+    });
+
+    it("rejects deadlines in the past, beyond the claim window, or from non-admins", async () => {
+      await expectError(() => setDeadline(p.start), "InvalidClaimDeadline");
+      await expectError(() => setDeadline(p.start + (CLAIM_WINDOW_DAYS + 1) * SECONDS_PER_DAY), "InvalidClaimDeadline");
+      await expectError(() => setDeadline(p.start + 5 * SECONDS_PER_DAY, alice), "UnauthorizedAdmin");
+    });
+
+    it("claims are rejected after the deadline while unstakes still work", async () => {
+      await setDeadline(p.start + 2 * SECONDS_PER_DAY);
+      await claimFor(p, early, amount);
+
+      await warpTo(p.start + 3 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+      await expectError(() => claimFor(p, late, amount), "ClaimWindowClosed");
+
+      const userAta = await unstakeFor(p, early);
+      expect(await tokenBalance(userAta) > BigInt(amount.toString())).to.be.true;
+    });
+
+    it("cannot be moved once it has passed", async () => {
+      await expectError(() => setDeadline(p.start + 10 * SECONDS_PER_DAY), "ClaimWindowClosed");
+    });
+  });
+
+  describe("Admin approvals for destructive actions", () => {
+    const RECOVER = 0;
+    const approverA = Keypair.generate();
+    const approverB = Keypair.generate();
+    const outsider = Keypair.generate();
+    let p: any;
+    let approval: PublicKey;
+
+    function approve(signer: Keypair) {
+      return program.methods.approveAction(RECOVER)
+        .accounts({ approver: signer.publicKey, poolState: p.poolState, approval, systemProgram: SystemProgram.programId })
+        .signers([signer]).rpc();
+    }
+
+    function recover(withApproval: boolean) {
+      return program.methods.recoverExpiredRewards()
+        .accounts({ admin: admin.publicKey, poolState: p.poolState, approval: withApproval ? approval : null, poolTokenAccount: p.poolToken, tokenMint: p.mint, destinationTokenAccount: p.adminAta, tokenProgram: TOKEN_PROGRAM_ID })
+        .signers([admin]).rpc();
+    }
+
+    before(async () => {
+      for (const kp of [approverA, approverB, outsider]) await fundAccount(kp.publicKey);
+      p = await setupPool([computeLeaf(outsider.publicKey, new BN(1))]);
+      [approval] = getApprovalPda(p.poolState, RECOVER);
+    });
+
+    it("rejects a threshold above the number of approvers", async () => {
+      await expectError(() => program.methods.configureApprovals([admin.publicKey, approverA.publicKey, PublicKey.default], 3)
+        .accounts({ admin: admin.publicKey, poolState: p.poolState })
+        .signers([admin]).rpc(), "InvalidApprovalConfig");
+    });
+
+    it("configures a 2-of-3 threshold once", async () => {
+      await program.methods.configureApprovals([admin.publicKey, approverA.publicKey, approverB.publicKey], 2)
+        .accounts({ admin: admin.publicKey, poolState: p.poolState })
+        .signers([admin]).rpc();
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.requiredApprovals).to.equal(2);
+
+      await tick();
+      await expectError(() => program.methods.configureApprovals([admin.publicKey, approverA.publicKey, approverB.publicKey], 1)
+        .accounts({ admin: admin.publicKey, poolState: p.poolState })
+        .signers([admin]).rpc(), "InvalidApprovalConfig");
+    });
+
+    it("rejects approvals from non-approvers and duplicates", async () => {
+      await expectError(() => approve(outsider), "NotAnApprover");
+      await approve(approverA);
+      await tick();
+      await expectError(() => approve(approverA), "AlreadyApproved");
+    });
+
+    it("recovery with fewer than the threshold approvals is rejected", async () => {
+      await warpTo(p.start + (CLAIM_WINDOW_DAYS + 1) * SECONDS_PER_DAY);
+      await expectError(() => recover(false), "InsufficientApprovals");
+      await expectError(() => recover(true), "InsufficientApprovals");
+    });
+
+    it("recovery with the threshold succeeds and consumes the approvals", async () => {
+      await approve(approverB);
+      await recover(true);
+      expect(await tokenBalance(p.poolToken)).to.equal(0n);
+      expect(await context.banksClient.getAccount(approval)).to.be.null;
+    });
+  });
+
+  describe("Termination timelock", () => {
+    const TIMELOCK_SECONDS = 172_800;
+    const staker = Keypair.generate();
+    const latecomer = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+
+    function drain() {
+      return program.methods.executeDrain()
+        .accounts({ admin: admin.publicKey, poolState: p.poolState, approval: null, poolTokenAccount: p.poolToken, tokenMint: p.mint, destinationTokenAccount: p.adminAta, tokenProgram: TOKEN_PROGRAM_ID })
+        .signers([admin]).rpc();
+    }
+
+    before(async () => {
+      await fundAccount(staker.publicKey);
+      await fundAccount(latecomer.publicKey);
+      p = await setupPool([computeLeaf(staker.publicKey, amount), computeLeaf(latecomer.publicKey, amount)]);
+      await warpTo(p.start + 60);
+      await claimFor(p, staker, amount);
+    });
+
+    it("draining requires an initiated termination", async () => {
+      await expectError(() => drain(), "PoolNotTerminated");
+    });
+
+    it("initiating blocks new claims", async () => {
+      await warpTo(p.start + 2 * SECONDS_PER_DAY + 60);
+      await program.methods.initiateTermination()
+        .accounts({ admin: admin.publicKey, poolState: p.poolState, approval: null })
+        .signers([admin]).rpc();
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.terminated).to.equal(1);
+
+      await expectError(() => claimFor(p, latecomer, amount), "PoolTerminated");
+    });
+
+    it("draining before the timelock elapses is rejected", async () => {
+      await expectError(() => drain(), "TimelockNotElapsed");
+    });
+
+    it("users can still unstake during the timelock", async () => {
+      await tick(3600);
+      await snapshotPool(p);
+      const userAta = await unstakeFor(p, staker);
+      expect(await tokenBalance(userAta) > BigInt(amount.toString())).to.be.true;
+    });
+
+    it("draining after the timelock succeeds", async () => {
+      const state = await program.account.poolState.fetch(p.poolState);
+      await warpTo(state.terminationTs.toNumber() + TIMELOCK_SECONDS);
+      const adminBefore = await tokenBalance(p.adminAta);
+      const poolBefore = await tokenBalance(p.poolToken);
+      await drain();
+      expect(await tokenBalance(p.poolToken)).to.equal(0n);
+      expect(await tokenBalance(p.adminAta)).to.equal(adminBefore + poolBefore);
+    });
+  });
+
+  describe("verify_proof view", () => {
+    const vUser = Keypair.generate();
+    const other = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+
+    before(async () => {
+      p = await setupPool([computeLeaf(vUser.publicKey, amount), computeLeaf(other.publicKey, amount)]);
+    });
+
+    it("returns true for a valid proof", async () => {
+      const proof = getMerkleProof(p.layers, computeLeaf(vUser.publicKey, amount));
+      const ok = await program.methods.verifyProof(amount, proof, vUser.publicKey, new BN(0))
+        .accounts({ poolState: p.poolState })
+        .view();
+      expect(ok).to.be.true;
+    });
+
+    it("returns false for a tampered proof or amount", async () => {
+      const proof = getMerkleProof(p.layers, computeLeaf(vUser.publicKey, amount));
+      const tampered = proof.map((node) => { const copy = [...node]; copy[0] ^= 1; return copy; });
+      expect(await program.methods.verifyProof(amount, tampered, vUser.publicKey, new BN(0)).accounts({ poolState: p.poolState }).view()).to.be.false;
+      expect(await program.methods.verifyProof(amount.addn(1), proof, vUser.publicKey, new BN(0)).accounts({ poolState: p.poolState }).view()).to.be.false;
+    });
+  });
+
+  describe("Per-day claimed tracking", () => {
+    const day0User = Keypair.generate();
+    const day2UserA = Keypair.generate();
+    const day2UserB = Keypair.generate();
+    const amountA = new BN(1_000_000).mul(new BN(1e9));
+    const amountB = new BN(2_500_000).mul(new BN(1e9));
+    let p: any;
+
+    before(async () => {
+      for (const kp of [day0User, day2UserA, day2UserB]) await fundAccount(kp.publicKey);
+      p = await setupPool([computeLeaf(day0User.publicKey, amountA), computeLeaf(day2UserA.publicKey, amountA), computeLeaf(day2UserB.publicKey, amountB)]);
+    });
+
+    it("records claims on the day they happen", async () => {
+      await warpTo(p.start + 60);
+      await claimFor(p, day0User, amountA);
+      await warpTo(p.start + 2 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+      await claimFor(p, day2UserA, amountA);
+      await claimFor(p, day2UserB, amountB);
+
+      const daily = await program.methods.getDailyClaimed().accounts({ poolState: p.poolState }).view();
+      expect(daily.length).to.equal(3);
+      expect(daily[0].toString()).to.equal(amountA.toString());
+      expect(daily[1].toString()).to.equal("0");
+      expect(daily[2].toString()).to.equal(amountA.add(amountB).toString());
+    });
+  });
+
+  describe("Pause auto-expiry", () => {
+    const pUser = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    const duration = 3600;
+    let p: any;
+
+    before(async () => {
+      await fundAccount(pUser.publicKey);
+      p = await setupPool([computeLeaf(pUser.publicKey, amount)]);
+      await warpTo(p.start + 60);
+    });
+
+    it("claims fail during the pause window", async () => {
+      await program.methods.pausePool(new BN(duration), 0)
+        .accounts({ admin: admin.publicKey, poolState: p.poolState })
+        .signers([admin]).rpc();
+      await expectError(() => claimFor(p, pUser, amount), "PoolPaused");
+    });
+
+    it("claims succeed after pause_expiry without an unpause call", async () => {
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.paused).to.equal(1);
+      await warpTo(state.pauseExpiry.toNumber() + 1);
+
+      const { userAta } = await claimFor(p, pUser, amount);
+      expect(await tokenBalance(userAta)).to.equal(BigInt(amount.toString()));
+    });
+  });
+
+  describe("Pause reason", () => {
+    let p: any;
+
+    before(async () => {
+      p = await setupPool([computeLeaf(Keypair.generate().publicKey, new BN(1))]);
+    });
+
+    it("rejects an unknown reason code", async () => {
+      await expectError(
+        () => program.methods.pausePool(new BN(0), 5)
+          .accounts({ admin: admin.publicKey, poolState: p.poolState })
+          .signers([admin]).rpc(),
+        "InvalidPauseReason"
+      );
+    });
+
+    it("stores the reason and emits it in PoolPausedEvent", async () => {
+      const events = await sendAndGetEvents(
+        program.methods.pausePool(new BN(0), 2).accounts({ admin: admin.publicKey, poolState: p.poolState }),
+        [admin]
+      );
+      expect(findEvent(events, "PoolPausedEvent").data.reason).to.equal(2);
+
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.pauseReason).to.equal(2);
+    });
+  });
+
+  describe("Resize pool state", () => {
+    let p: any;
+
+    before(async () => {
+      p = await setupPool([computeLeaf(Keypair.generate().publicKey, new BN(1))], {}, 1000, false);
+    });
+
+    function resize(newDays: number, extra: BN[]) {
+      return program.methods.resizePoolState(newDays, extra)
+        .accounts({ admin: admin.publicKey, poolState: p.poolState, systemProgram: SystemProgram.programId })
+        .signers([admin]).rpc();
+    }
+
+    it("rejects a length above MAX_PROGRAM_DAYS", async () => {
+      const last = (await program.account.poolState.fetch(p.poolState)).dailyRewards[TOTAL_DAYS - 1];
+      await expectError(() => resize(33, Array(13).fill(last)), "InvalidProgramDays");
+    });
+
+    it("grows a 20-day pool to 28 days before start", async () => {
+      const last = (await program.account.poolState.fetch(p.poolState)).dailyRewards[TOTAL_DAYS - 1];
+      await resize(28, Array(8).fill(last));
+
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.programDays).to.equal(28);
+      expect(state.dailyRewards[27].toString()).to.equal(last.toString());
+    });
+
+    it("activation requires the added days to be funded", async () => {
+      await expectError(() => activatePool(p.poolState, p.poolToken), "PoolUnderfunded");
+
+      const state = await program.account.poolState.fetch(p.poolState);
+      const added = BigInt(state.stakingPool.sub(STAKING_POOL).toString());
+      await provider.sendAndConfirm(new anchor.web3.Transaction().add(
+        createMintToInstruction(p.mint, p.poolToken, admin.publicKey, added)
+      ), [admin]);
+      await activatePool(p.poolState, p.poolToken);
+    });
+
+    it("rejects resizing an active pool", async () => {
+      const last = (await program.account.poolState.fetch(p.poolState)).dailyRewards[27];
+      await expectError(() => resize(30, Array(2).fill(last)), "PoolAlreadyActive");
+    });
+
+    it("snapshots the added days", async () => {
+      await warpTo(p.start + 28 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.snapshotCount).to.equal(28);
+      expect(state.dailySnapshots[27].gt(new BN(0))).to.be.true;
+    });
+
+    it("rejects resizing after start", async () => {
+      await expectError(() => resize(30, Array(2).fill(new BN(0))), "PoolAlreadyStarted");
+    });
+  });
+
+  describe("Bump validation", () => {
+    const bUser = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+
+    before(async () => {
+      await fundAccount(bUser.publicKey);
+      p = await setupPool([computeLeaf(bUser.publicKey, amount)]);
+      await warpTo(p.start + 60);
+      await claimFor(p, bUser, amount);
+      await tick();
+    });
+
+    it("unstake fails when the stored pool_token_bump is corrupted", async () => {
+      const account = await context.banksClient.getAccount(p.poolState);
+      const state = program.coder.accounts.decode("poolState", Buffer.from(account.data));
+      state.poolTokenBump = (state.poolTokenBump + 1) % 256;
+      const data = await program.coder.accounts.encode("poolState", state);
+      await context.setAccount(p.poolState, { ...account, data: Buffer.concat([data, Buffer.alloc(account.data.length - data.length)]) });
+
+      await expectError(() => unstakeFor(p, bUser), "InvalidBump");
+    });
+  });
+
+  describe("get_user_stake view", () => {
+    const gUser = Keypair.generate();
+    const amount = new BN(2_000_000).mul(new BN(1e9));
+    let p: any;
+    let userStake: PublicKey;
+
+    before(async () => {
+      await fundAccount(gUser.publicKey);
+      p = await setupPool([computeLeaf(gUser.publicKey, amount)]);
+      await warpTo(p.start + 60);
+      ({ userStake } = await claimFor(p, gUser, amount));
+      await warpTo(p.start + 4 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+    });
+
+    it("returns pending rewards of the per-day calculate_rewards shares", async () => {
+      const info = await program.methods.getUserStake()
+        .accounts({ poolState: p.poolState, userStake })
+        .view();
+
+      let sum = new BN(0);
+      for (let day = 0; day < 4; day++) {
+        const tx = await program.methods.calculateRewards(new BN(day))
+          .accounts({ poolState: p.poolState, userStake })
+          .transaction();
+        tx.recentBlockhash = context.lastBlockhash;
+        tx.feePayer = admin.publicKey;
+        tx.sign(admin);
+        const meta = await context.banksClient.simulateTransaction(tx);
+        const line = meta.meta.logMessages.find((l: string) => l.includes(`Day ${day} reward:`));
+        sum = sum.add(new BN(line.split("reward: ")[1]));
+      }
+
+      expect(info.owner.toBase58()).to.equal(gUser.publicKey.toBase58());
+      expect(info.stakedAmount.toString()).to.equal(amount.toString());
+      expect(info.claimDay.toNumber()).to.equal(0);
+      // Summed before flooring: at least the floored per-day sum, less than one unit more per day
+      const pending = info.pendingRewards;
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(pending.toString()).to.equal(accruedRewards(amount, state, 0, 4).toString());
+      expect(pending.gte(sum) && pending.lt(sum.addn(4))).to.be.true;
+    });
+  });
+
+  describe("Snapshot overwrite protection", () => {
+    const zUser = Keypair.generate();
+    let p: any;
+
+    before(async () => {
+      await fundAccount(zUser.publicKey);
+      p = await setupPool([computeLeaf(zUser.publicKey, AIRDROP_POOL)]);
+      await warpTo(p.start + 60);
+      await claimFor(p, zUser, AIRDROP_POOL);
+      await tick();
+      await unstakeFor(p, zUser);
+    });
+
+    it("records a genuine zero total_staked and marks the day", async () => {
+      await warpTo(p.start + 2 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.totalStaked.toString()).to.equal("0");
+      expect(state.dailySnapshots[0].toString()).to.equal("0");
+      expect(state.snapshotTakenBitmap).to.equal(0b11);
+    });
+
+    it("a later crank does not overwrite the recorded zero", async () => {
+      await warpTo(p.start + 3 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.dailySnapshots[0].toString()).to.equal("0");
+      expect(state.dailySnapshots[1].toString()).to.equal("0");
+      expect(state.snapshotTakenBitmap).to.equal(0b111);
+      expect(state.snapshotCount).to.equal(3);
+    });
+  });
+
+  describe("Signed voucher claims", () => {
+    const claimSigner = Keypair.generate();
+    const vUser = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+
+    function voucherMessage(user: PublicKey, amt: any, expiry: number) {
+      return Buffer.concat([
+        p.poolState.toBuffer(),
+        user.toBuffer(),
+        amt.toArrayLike(Buffer, "le", 8),
+        new BN(expiry).toTwos(64).toArrayLike(Buffer, "le", 8),
+      ]);
+    }
+
+    async function claimSigned(user: Keypair, amt: any, expiry: number, signer: Keypair = claimSigner) {
+      const [userStake] = getUserStakePda(p.poolState, user.publicKey);
+      const [claimMarker] = getClaimMarkerPda(p.poolState, user.publicKey);
+      const [blacklistEntry] = getBlacklistPda(p.poolState, user.publicKey);
+      const userAta = await getOrCreateATABankrun(p.mint, user.publicKey, user);
+      const ed25519Ix = Ed25519Program.createInstructionWithPrivateKey({
+        privateKey: signer.secretKey,
+        message: voucherMessage(user.publicKey, amt, expiry),
+      });
+      await program.methods.claimAirdropSigned(amt, new BN(expiry), 0)
+        .accounts({ user: user.publicKey, poolState: p.poolState, claimMarker, blacklistEntry, userStake, poolTokenAccount: p.poolToken, userTokenAccount: userAta, instructionsSysvar: SYSVAR_INSTRUCTIONS_PUBKEY, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID })
+        .preInstructions([ed25519Ix])
+        .signers([user]).rpc();
+      return { userStake, userAta };
+    }
+
+    before(async () => {
+      await fundAccount(vUser.publicKey);
+      p = await setupPool([computeLeaf(Keypair.generate().publicKey, new BN(1))], { claimSigner: claimSigner.publicKey });
+      await warpTo(p.start + 60);
+    });
+
+    it("rejects an expired voucher", async () => {
+      await expectError(() => claimSigned(vUser, amount, p.start + 30), "VoucherExpired");
+    });
+
+    it("rejects a voucher signed by the wrong key", async () => {
+      await expectError(() => claimSigned(vUser, amount, p.start + 3600, Keypair.generate()), "InvalidVoucher");
+    });
+
+    it("claims with a valid voucher", async () => {
+      const { userStake, userAta } = await claimSigned(vUser, amount, p.start + 3600);
+
+      expect(await tokenBalance(userAta)).to.equal(BigInt(amount.toString()));
+      const stake = await program.account.userStake.fetch(userStake);
+      expect(stake.stakedAmount.toString()).to.equal(amount.toString());
+    });
+  });
+
+  describe("Indexed merkle leaves", () => {
+    const iUser = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+    let proof: number[][];
+
+    async function claimIndexed(index: number) {
+      const [userStake] = getUserStakePda(p.poolState, iUser.publicKey);
+      const [claimMarker] = getClaimMarkerPda(p.poolState, iUser.publicKey);
+      const [blacklistEntry] = getBlacklistPda(p.poolState, iUser.publicKey);
+      const userAta = await getOrCreateATABankrun(p.mint, iUser.publicKey, iUser);
+      await program.methods.claimAirdrop(amount, proof, new BN(index), 0)
+        .accounts({ user: iUser.publicKey, poolState: p.poolState, claimMarker, blacklistEntry, userStake, poolTokenAccount: p.poolToken, userTokenAccount: userAta, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID })
+        .signers([iUser]).rpc();
+      return userAta;
+    }
+
+    before(async () => {
+      await fundAccount(iUser.publicKey);
+      const leaves = [
+        computeIndexedLeaf(0, Keypair.generate().publicKey, amount),
+        computeIndexedLeaf(1, iUser.publicKey, amount),
+      ];
+      p = await setupPool(leaves, { merkleSchemeVersion: 1 });
+      proof = getMerkleProof(p.layers, leaves[1]);
+      await warpTo(p.start + 60);
+    });
+
+    it("rejects a proof claimed with a mismatched index", async () => {
+      await expectError(() => claimIndexed(0), "InvalidMerkleProof");
+    });
+
+    it("accepts the proof at its own index", async () => {
+      const userAta = await claimIndexed(1);
+      expect(await tokenBalance(userAta)).to.equal(BigInt(amount.toString()));
+    });
+  });
+
+  describe("Rebalance daily rewards", () => {
+    let p: any;
+
+    before(async () => {
+      p = await setupPool([computeLeaf(Keypair.generate().publicKey, new BN(1))]);
+    });
+
+    function rebalance(newRewards: any[]) {
+      return program.methods.rebalanceDailyRewards(newRewards)
+        .accounts({ admin: admin.publicKey, poolState: p.poolState, poolTokenAccount: p.poolToken, adminTokenAccount: p.adminAta, tokenProgram: TOKEN_PROGRAM_ID })
+        .signers([admin]).rpc();
+    }
+
+    it("rejects a schedule larger than the current total", async () => {
+      const larger = computeDailyRewards();
+      larger[TOTAL_DAYS - 1] = larger[TOTAL_DAYS - 1].addn(1);
+      await expectError(() => rebalance(larger), "InvalidRebalance");
+    });
+
+    it("refunds the admin exactly the freed amount", async () => {
+      const halved = computeDailyRewards().map((r: any) => r.divn(2));
+      const newSum = halved.reduce((a: any, b: any) => a.add(b), new BN(0));
+      const before = await tokenBalance(p.adminAta);
+
+      await rebalance(halved);
+
+      const refunded = (await tokenBalance(p.adminAta)) - before;
+      expect(refunded.toString()).to.equal(STAKING_POOL.sub(newSum).toString());
+
+      const state = await program.account.poolState.fetch(p.poolState);
+      const stored = state.dailyRewards.slice(0, TOTAL_DAYS).reduce((a: any, b: any) => a.add(b), new BN(0));
+      expect(state.stakingPool.toString()).to.equal(newSum.toString());
+      expect(stored.toString()).to.equal(newSum.toString());
+    });
+
+    it("rejects a schedule that breaks the pool's reward curve", async () => {
+      const curved = await setupPool([computeLeaf(Keypair.generate().publicKey, new BN(1))], { rewardCurve: 1 });
+      const halved = computeDailyRewards().map((r: any) => r.divn(2));
+      halved[TOTAL_DAYS - 1] = halved[TOTAL_DAYS - 1].add(halved[0]);
+      await expectError(
+        () => program.methods.rebalanceDailyRewards(halved)
+          .accounts({ admin: admin.publicKey, poolState: curved.poolState, poolTokenAccount: curved.poolToken, adminTokenAccount: curved.adminAta, tokenProgram: TOKEN_PROGRAM_ID })
+          .signers([admin]).rpc(),
+        "InvalidDailyRewards"
+      );
+    });
+
+    it("rejects rebalancing after start", async () => {
+      await warpTo(p.start + 60);
+      const halved = computeDailyRewards().map((r: any) => r.divn(4));
+      await expectError(() => rebalance(halved), "PoolAlreadyStarted");
+    });
+  });
+
+  describe("estimate_apr view", () => {
+    const aUser = Keypair.generate();
+    const amount = new BN(2_000_000).mul(new BN(1e9));
+    let p: any;
+
+    before(async () => {
+      await fundAccount(aUser.publicKey);
+      p = await setupPool([computeLeaf(aUser.publicKey, amount)]);
+      await warpTo(p.start + 60);
+      await claimFor(p, aUser, amount);
+      await tick();
+      await unstakeFor(p, aUser);
+    });
+
+    it("matches daily_rewards[day] * 365 * 10000 / total_staked", async () => {
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.totalStaked.toString()).to.equal(AIRDROP_POOL.sub(amount).toString());
+
+      for (const day of [0, 7, TOTAL_DAYS - 1]) {
+        const apr = await program.methods.estimateApr(new BN(day)).accounts({ poolState: p.poolState }).view();
+        const expected = state.dailyRewards[day].muln(365).muln(10_000).div(state.totalStaked);
+        expect(apr.toString()).to.equal(expected.toString());
+      }
+    });
+
+    it("rejects a day outside the program", async () => {
+      await expectError(
+        () => program.methods.estimateApr(new BN(TOTAL_DAYS)).accounts({ poolState: p.poolState }).view(),
+        "InvalidDay"
+      );
+    });
+  });
+
+  describe("get_phase view", () => {
+    let p: any;
+
+    before(async () => {
+      p = await setupPool([computeLeaf(Keypair.generate().publicKey, new BN(1))]);
+    });
+
+    async function phase() {
+      const r = await program.methods.getPhase().accounts({ poolState: p.poolState }).view();
+      return { phase: r.phase, days: r.daysRemainingInPhase.toNumber() };
+    }
+
+    it("NotStarted before start_time", async () => {
+      expect(await phase()).to.deep.equal({ phase: 0, days: 1 });
+    });
+
+    it("Active from start_time through the last program day", async () => {
+      await warpTo(p.start);
+      expect(await phase()).to.deep.equal({ phase: 1, days: TOTAL_DAYS });
+      await warpTo(p.start + TOTAL_DAYS * SECONDS_PER_DAY - 1);
+      expect(await phase()).to.deep.equal({ phase: 1, days: 1 });
+    });
+
+    it("ExitWindow from the end of the program until the claim window ends", async () => {
+      await warpTo(p.start + TOTAL_DAYS * SECONDS_PER_DAY);
+      expect(await phase()).to.deep.equal({ phase: 2, days: 40 - TOTAL_DAYS });
+    });
+
+    it("Expired once the claim window ends", async () => {
+      await warpTo(p.start + 40 * SECONDS_PER_DAY);
+      expect(await phase()).to.deep.equal({ phase: 3, days: 0 });
+    });
+  });
+
+  describe("Unstake reserve invariant", () => {
+    const early = Keypair.generate();
+    const later = Keypair.generate();
+    const amount = new BN(10_000_000).mul(new BN(1e9));
+    let p: any;
+
+    before(async () => {
+      for (const u of [early, later]) await fundAccount(u.publicKey);
+      p = await setupPool([computeLeaf(early.publicKey, amount), computeLeaf(later.publicKey, amount)]);
+      await warpTo(p.start + 60);
+      await claimFor(p, early, amount);
+      await claimFor(p, later, amount);
+      await warpTo(p.start + 3 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+    });
+
+    it("reverts instead of paying out rewards owed to other stakers", async () => {
+      const [earlyStake] = getUserStakePda(p.poolState, early.publicKey);
+      const info = await program.methods.getUserStake().accounts({ poolState: p.poolState, userStake: earlyStake }).view();
+
+      // Underfund the pool: just enough for the early exiter's own rewards
+      const account = await context.banksClient.getAccount(p.poolToken);
+      const data = Buffer.from(account.data);
+      data.writeBigUInt64LE(BigInt(info.pendingRewards.toString()), 64);
+      await context.setAccount(p.poolToken, { ...account, data });
+
+      await expectError(() => unstakeFor(p, early), "PoolReserveViolation");
+      expect(await tokenBalance(p.poolToken)).to.equal(BigInt(info.pendingRewards.toString()));
+    });
+  });
+
+  describe("Liquid claims", () => {
+    const lUser = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+
+    before(async () => {
+      await fundAccount(lUser.publicKey);
+      p = await setupPool([computeLeaf(lUser.publicKey, amount)]);
+      await warpTo(p.start + 60);
+    });
+
+    it("transfers tokens without creating a UserStake or touching total_staked", async () => {
+      const [claimMarker] = getClaimMarkerPda(p.poolState, lUser.publicKey);
+      const [blacklistEntry] = getBlacklistPda(p.poolState, lUser.publicKey);
+      const [userStake] = getUserStakePda(p.poolState, lUser.publicKey);
+      const userAta = await getOrCreateATABankrun(p.mint, lUser.publicKey, lUser);
+      await program.methods.claimAirdropLiquid(amount, getMerkleProof(p.layers, computeLeaf(lUser.publicKey, amount)), new BN(0))
+        .accounts({ user: lUser.publicKey, poolState: p.poolState, claimMarker, blacklistEntry, poolTokenAccount: p.poolToken, userTokenAccount: userAta, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID })
+        .signers([lUser]).rpc();
+
+      expect(await tokenBalance(userAta)).to.equal(BigInt(amount.toString()));
+      expect(await context.banksClient.getAccount(userStake)).to.be.null;
+
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.totalStaked.toString()).to.equal(AIRDROP_POOL.toString());
+      expect(state.totalAirdropClaimed.toString()).to.equal(amount.toString());
+      expect(state.totalLiquidClaimed.toString()).to.equal(amount.toString());
+    });
+
+    it("blocks re-claiming through the shared ClaimMarker", async () => {
+      try {
+        await claimFor(p, lUser, amount);
+        expect.fail("Should have failed: claim marker already exists");
+      } catch (e: any) {
+        const msg = (e.message || "").toString();
+        expect(msg).to.satisfy((m: string) => m.includes("already in use") || m.includes("0x0"));
+      }
+    });
+
+    it("does not change recorded snapshots", async () => {
+      await warpTo(p.start + SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.dailySnapshots[0].toString()).to.equal(AIRDROP_POOL.toString());
+    });
+  });
+
+  describe("Snapshot crank rate limit", () => {
+    const other = Keypair.generate();
+    let p: any;
+
+    before(async () => {
+      await fundAccount(other.publicKey);
+      p = await setupPool([computeLeaf(Keypair.generate().publicKey, new BN(1))]);
+      await warpTo(p.start + SECONDS_PER_DAY + 60);
+    });
+
+    it("rejects a second crank by the same caller on the same day", async () => {
+      await snapshotPool(p);
+      await warpTo(p.start + SECONDS_PER_DAY + 120);
+      await expectError(() => snapshotPool(p), "CrankTooSoon");
+    });
+
+    it("still lets a different caller crank that day", async () => {
+      await snapshotPool(p, other);
+      const [crankRecord] = PublicKey.findProgramAddressSync(
+        [Buffer.from("crank"), p.poolState.toBuffer(), other.publicKey.toBuffer()], program.programId
+      );
+      const record = await program.account.crankRecord.fetch(crankRecord);
+      expect(record.lastCrankTs.toNumber()).to.equal(p.start + SECONDS_PER_DAY + 120);
+    });
+
+    it("allows the first caller again on the next day", async () => {
+      await warpTo(p.start + 2 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+    });
+  });
+
+  describe("Lockup tiers", () => {
+    const base = Keypair.generate();
+    const locked = Keypair.generate();
+    const outOfRange = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+
+    before(async () => {
+      for (const u of [base, locked, outOfRange]) await fundAccount(u.publicKey);
+      p = await setupPool(
+        [computeLeaf(base.publicKey, amount), computeLeaf(locked.publicKey, amount), computeLeaf(outOfRange.publicKey, amount)],
+        {
+          lockMultipliersBps: [10_000, 20_000, 0, 0],
+          lockMinSeconds: [new BN(0), new BN(5 * SECONDS_PER_DAY), new BN(0), new BN(0)],
+        }
+      );
+      await warpTo(p.start + 60);
+      await claimFor(p, base, amount, 0);
+      await claimFor(p, locked, amount, 1);
+      await warpTo(p.start + 3 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+    });
+
+    it("weights the pool denominator by the tier multiplier", async () => {
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.totalStaked.toString()).to.equal(AIRDROP_POOL.add(amount).toString());
+    });
+
+    it("pays a higher-tier staker proportionally more for the same amount", async () => {
+      const pending = async (u: Keypair) => {
+        const [userStake] = getUserStakePda(p.poolState, u.publicKey);
+        const info = await program.methods.getUserStake().accounts({ poolState: p.poolState, userStake }).view();
+        return BigInt(info.pendingRewards.toString());
+      };
+      const basePending = await pending(base);
+      const lockedPending = await pending(locked);
+      expect(basePending > 0n).to.be.true;
+      // Each day's share is floored separately, so allow one unit per day
+      const diff = lockedPending - 2n * basePending;
+      expect(diff >= 0n && diff <= 3n).to.be.true;
+    });
+
+    it("enforces the tier's minimum stake duration on unstake", async () => {
+      await expectError(() => unstakeFor(p, locked), "StakeLocked");
+      await unstakeFor(p, base);
+    });
+
+    it("rejects an out-of-range lock tier", async () => {
+      await expectError(() => claimFor(p, outOfRange, amount, 4), "InvalidLockTier");
+    });
+  });
+
+  describe("Referral bonus", () => {
+    const referrer = Keypair.generate();
+    const first = Keypair.generate();
+    const second = Keypair.generate();
+    const selfReferred = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+    let referrerAta: PublicKey;
+
+    async function claimReferred(user: Keypair, ref: PublicKey) {
+      const [userStake] = getUserStakePda(p.poolState, user.publicKey);
+      const [claimMarker] = getClaimMarkerPda(p.poolState, user.publicKey);
+      const [blacklistEntry] = getBlacklistPda(p.poolState, user.publicKey);
+      const userAta = await getOrCreateATABankrun(p.mint, user.publicKey, user);
+      await program.methods.claimAirdropReferred(amount, getMerkleProof(p.layers, computeLeaf(user.publicKey, amount)), new BN(0), 0, ref)
+        .accounts({ user: user.publicKey, poolState: p.poolState, claimMarker, blacklistEntry, userStake, poolTokenAccount: p.poolToken, userTokenAccount: userAta, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID })
+        .signers([user]).rpc();
+    }
+
+    before(async () => {
+      for (const u of [first, second, selfReferred]) await fundAccount(u.publicKey);
+      p = await setupPool([first, second, selfReferred].map((u) => computeLeaf(u.publicKey, amount)), { referralBps: 1000 });
+      referrerAta = await getOrCreateATABankrun(p.mint, referrer.publicKey);
+      await warpTo(p.start + 60);
+      await claimReferred(first, referrer.publicKey);
+      await claimReferred(second, referrer.publicKey);
+      await warpTo(p.start + 3 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+    });
+
+    it("rejects a claimer referring themselves", async () => {
+      await expectError(() => claimReferred(selfReferred, selfReferred.publicKey), "InvalidReferrer");
+    });
+
+    it("pays the referrer referral_bps of the claimer's rewards from the budget", async () => {
+      const [userStake] = getUserStakePda(p.poolState, first.publicKey);
+      const info = await program.methods.getUserStake().accounts({ poolState: p.poolState, userStake }).view();
+      const bonus = BigInt(info.pendingRewards.toString()) / 10n;
+
+      // Budget covers exactly one bonus
+      await provider.sendAndConfirm(new anchor.web3.Transaction().add(
+        createMintToInstruction(p.mint, p.adminAta, admin.publicKey, bonus)
+      ), [admin]);
+      await program.methods.fundReferralBudget(new BN(bonus.toString()))
+        .accounts({ admin: admin.publicKey, poolState: p.poolState, poolTokenAccount: p.poolToken, adminTokenAccount: p.adminAta, tokenProgram: TOKEN_PROGRAM_ID })
+        .signers([admin]).rpc();
+
+      const userAta = await unstakeFor(p, first, null, referrerAta);
+      expect(await tokenBalance(referrerAta)).to.equal(bonus);
+      expect(await tokenBalance(userAta)).to.equal(BigInt(amount.toString()) + BigInt(info.pendingRewards.toString()));
+      expect((await program.account.poolState.fetch(p.poolState)).referralBudget.toString()).to.equal("0");
+    });
+
+    it("stops bonuses once the budget is exhausted without failing the unstake", async () => {
+      const before = await tokenBalance(referrerAta);
+      const userAta = await unstakeFor(p, second, null, referrerAta);
+      expect(await tokenBalance(referrerAta)).to.equal(before);
+      expect(await tokenBalance(userAta) > BigInt(amount.toString())).to.be.true;
+    });
+  });
+
+  describe("NFT-gated claims", () => {
+    const TOKEN_METADATA_PROGRAM_ID = new PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+    const collection = Keypair.generate().publicKey;
+    const holder = Keypair.generate();
+    const nonHolder = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+    let nftTokenAccount: PublicKey;
+    let nftMetadata: PublicKey;
+
+    // Minimal Metaplex MetadataV1: empty strings, no creators, verified collection
+    function metadataData(mint: PublicKey, collectionKey: PublicKey): Buffer {
+      return Buffer.concat([
+        Buffer.from([4]),                         // key: MetadataV1
+        Keypair.generate().publicKey.toBuffer(),  // update_authority
+        mint.toBuffer(),
+        Buffer.alloc(12),                         // name, symbol, uri (empty)
+        Buffer.alloc(2),                          // seller_fee_basis_points
+        Buffer.from([0, 0, 1, 0, 0]),             // creators, primary_sale, is_mutable, edition_nonce, token_standard
+        Buffer.from([1, 1]),                      // collection: Some { verified: true, ...
+        collectionKey.toBuffer(),
+      ]);
+    }
+
+    async function claimGated(user: Keypair, tokenAccount: PublicKey | null, metadata: PublicKey | null) {
+      const [userStake] = getUserStakePda(p.poolState, user.publicKey);
+      const [claimMarker] = getClaimMarkerPda(p.poolState, user.publicKey);
+      const [blacklistEntry] = getBlacklistPda(p.poolState, user.publicKey);
+      const userAta = await getOrCreateATABankrun(p.mint, user.publicKey, user);
+      await program.methods.claimAirdrop(amount, getMerkleProof(p.layers, computeLeaf(user.publicKey, amount)), new BN(0), 0)
+        .accounts({ user: user.publicKey, poolState: p.poolState, claimMarker, blacklistEntry, userStake, poolTokenAccount: p.poolToken, userTokenAccount: userAta, nftTokenAccount: tokenAccount, nftMetadata: metadata, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID })
+        .signers([user]).rpc();
+      return userAta;
+    }
+
+    before(async () => {
+      for (const u of [holder, nonHolder]) await fundAccount(u.publicKey);
+      p = await setupPool([holder, nonHolder].map((u) => computeLeaf(u.publicKey, amount)), { requiredCollection: collection });
+
+      const nftMint = await createMintBankrun(0, admin.publicKey);
+      nftTokenAccount = await getOrCreateATABankrun(nftMint, holder.publicKey);
+      await provider.sendAndConfirm(new anchor.web3.Transaction().add(
+        createMintToInstruction(nftMint, nftTokenAccount, admin.publicKey, 1n)
+      ), [admin]);
+      [nftMetadata] = PublicKey.findProgramAddressSync(
+        [Buffer.from("metadata"), TOKEN_METADATA_PROGRAM_ID.toBuffer(), nftMint.toBuffer()], TOKEN_METADATA_PROGRAM_ID
+      );
+      await context.setAccount(nftMetadata, {
+        lamports: LAMPORTS_PER_SOL,
+        data: metadataData(nftMint, collection),
+        owner: TOKEN_METADATA_PROGRAM_ID,
+        executable: false,
+      });
+      await warpTo(p.start + 60);
+    });
+
+    it("rejects a claimer without an NFT from the collection", async () => {
+      await expectError(() => claimGated(nonHolder, null, null), "MissingRequiredNft");
+      await expectError(() => claimGated(nonHolder, nftTokenAccount, nftMetadata), "MissingRequiredNft");
+    });
+
+    it("lets a collection holder claim", async () => {
+      const userAta = await claimGated(holder, nftTokenAccount, nftMetadata);
+      expect(await tokenBalance(userAta)).to.equal(BigInt(amount.toString()));
+    });
+  });
+
+  describe("Separate reward mint", () => {
+    const sameUser = Keypair.generate();
+    const splitUser = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let same: any;
+    let split: any;
+    let rewardMint: PublicKey;
+    let rewardPool: PublicKey;
+
+    async function pendingRewards(p: any, user: Keypair): Promise<bigint> {
+      const [userStake] = getUserStakePda(p.poolState, user.publicKey);
+      const info = await program.methods.getUserStake().accounts({ poolState: p.poolState, userStake }).view();
+      return BigInt(info.pendingRewards.toString());
+    }
+
+    before(async () => {
+      for (const u of [sameUser, splitUser]) await fundAccount(u.publicKey);
+      same = await setupPool([computeLeaf(sameUser.publicKey, amount)]);
+      split = await setupPool([computeLeaf(splitUser.publicKey, amount)], {}, 1000, false);
+
+      rewardMint = await createMintBankrun(TOKEN_DECIMALS, admin.publicKey);
+      [rewardPool] = PublicKey.findProgramAddressSync([Buffer.from("reward_pool_token"), split.poolState.toBuffer()], program.programId);
+      await program.methods.setRewardMint()
+        .accounts({ admin: admin.publicKey, poolState: split.poolState, rewardMint, rewardPoolTokenAccount: rewardPool, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID })
+        .signers([admin]).rpc();
+      await provider.sendAndConfirm(new anchor.web3.Transaction().add(
+        createMintToInstruction(rewardMint, rewardPool, admin.publicKey, BigInt(STAKING_POOL.toString()))
+      ), [admin]);
+      await activatePool(split.poolState, split.poolToken, rewardPool);
+
+      await warpTo(Math.max(same.start, split.start) + 60);
+      await claimFor(same, sameUser, amount);
+      await claimFor(split, splitUser, amount);
+      await warpTo(Math.max(same.start, split.start) + 3 * SECONDS_PER_DAY + 60);
+      await snapshotPool(same);
+      await snapshotPool(split);
+    });
+
+    it("same mint: rewards are paid from the pool token account in token_mint", async () => {
+      const state = await program.account.poolState.fetch(same.poolState);
+      expect(state.rewardMint.equals(PublicKey.default)).to.be.true;
+
+      const pending = await pendingRewards(same, sameUser);
+      const poolBefore = await tokenBalance(same.poolToken);
+      const userAta = await unstakeFor(same, sameUser);
+      expect(await tokenBalance(userAta)).to.equal(BigInt(amount.toString()) + pending);
+      expect(await tokenBalance(same.poolToken)).to.equal(poolBefore - pending);
+    });
+
+    it("distinct mint: requires the reward accounts", async () => {
+      await expectError(() => unstakeFor(split, splitUser), "MissingRewardAccount");
+    });
+
+    it("distinct mint: rewards are paid in reward_mint from the reward pool", async () => {
+      const pending = await pendingRewards(split, splitUser);
+      expect(pending > 0n).to.be.true;
+      const [userStake] = getUserStakePda(split.poolState, splitUser.publicKey);
+      const userAta = getAssociatedTokenAddressSync(split.mint, splitUser.publicKey);
+      const userRewardAta = await getOrCreateATABankrun(rewardMint, splitUser.publicKey, splitUser);
+      const poolBefore = await tokenBalance(split.poolToken);
+
+      await program.methods.unstake()
+        .accounts({ user: splitUser.publicKey, poolState: split.poolState, userStake, poolTokenAccount: split.poolToken, tokenMint: split.mint, userTokenAccount: userAta, rewardPoolTokenAccount: rewardPool, userRewardTokenAccount: userRewardAta, treasuryTokenAccount: null, referrerTokenAccount: null, tokenProgram: TOKEN_PROGRAM_ID, associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID, systemProgram: SystemProgram.programId })
+        .signers([splitUser]).rpc();
+
+      expect(await tokenBalance(userRewardAta)).to.equal(pending);
+      expect(await tokenBalance(userAta)).to.equal(BigInt(amount.toString()));
+      expect(await tokenBalance(rewardPool)).to.equal(BigInt(STAKING_POOL.toString()) - pending);
+      expect(await tokenBalance(split.poolToken)).to.equal(poolBefore);
+    });
+  });
+
+  describe("Staking reward budget", () => {
+    const users = Array.from({ length: 8 }, () => Keypair.generate());
+    // Unequal allocations (1..8 parts) that together exhaust AIRDROP_POOL
+    const part = AIRDROP_POOL.div(new BN(36));
+    const amounts = users.map((_, i) => part.mul(new BN(i + 1)));
+    amounts[7] = amounts[7].add(AIRDROP_POOL.sub(part.mul(new BN(36))));
+    let p: any;
+
+    before(async () => {
+      for (const u of users) await fundAccount(u.publicKey);
+      p = await setupPool(users.map((u, i) => computeLeaf(u.publicKey, amounts[i])));
+      await warpTo(p.start + 60);
+      for (let i = 0; i < users.length; i++) await claimFor(p, users[i], amounts[i]);
+      await warpTo(p.start + (TOTAL_DAYS + 1) * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+    });
+
+    it("starts at STAKING_POOL", async () => {
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.stakingBudgetRemaining.toString()).to.equal(STAKING_POOL.toString());
+    });
+
+    it("never pays more than the staking pool across many unstakes", async () => {
+      let paid = 0n;
+      for (let i = 0; i < users.length; i++) {
+        const userAta = await unstakeFor(p, users[i]);
+        paid += (await tokenBalance(userAta)) - BigInt(amounts[i].toString());
+      }
+
+      const staking = BigInt(STAKING_POOL.toString());
+      expect(paid <= staking).to.be.true;
+      // Only per-day flooring is lost: at most one unit per user per day
+      expect(staking - paid <= BigInt(users.length * TOTAL_DAYS)).to.be.true;
+
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.stakingBudgetRemaining.toString()).to.equal((staking - paid).toString());
+    });
+  });
+
+  describe("is_claimed view", () => {
+    const user = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+
+    async function isClaimed(): Promise<boolean> {
+      const [claimMarker] = getClaimMarkerPda(p.poolState, user.publicKey);
+      return program.methods.isClaimed().accounts({ poolState: p.poolState, user: user.publicKey, claimMarker }).view();
+    }
+
+    before(async () => {
+      await fundAccount(user.publicKey);
+      p = await setupPool([computeLeaf(user.publicKey, amount)]);
+      await warpTo(p.start + 60);
+    });
+
+    it("returns false before the claim", async () => {
+      expect(await isClaimed()).to.equal(false);
+    });
+
+    it("returns true after the claim", async () => {
+      await claimFor(p, user, amount);
+      expect(await isClaimed()).to.equal(true);
+    });
+  });
+
+  describe("Snapshot return data", () => {
+    const other = Keypair.generate();
+    let p: any;
+
+    async function snapshotResult(signer: Keypair) {
+      const tx = await program.methods.snapshot().accounts({ signer: signer.publicKey, poolState: p.poolState }).transaction();
+      tx.recentBlockhash = context.lastBlockhash;
+      tx.feePayer = signer.publicKey;
+      tx.sign(signer);
+      const meta = await context.banksClient.processTransaction(tx);
+      return program.coder.types.decode("SnapshotResult", Buffer.from(meta.returnData.data));
+    }
+
+    before(async () => {
+      await fundAccount(other.publicKey);
+      p = await setupPool([computeLeaf(Keypair.generate().publicKey, new BN(1))]);
+      await warpTo(p.start + 2 * SECONDS_PER_DAY + 60);
+    });
+
+    it("reports wrote = true for the first snapshot of the day and false for the next", async () => {
+      const first = await snapshotResult(admin);
+      expect(first.wrote).to.equal(true);
+      expect(first.day.toNumber()).to.equal(2);
+      expect(first.totalStaked.toString()).to.equal(AIRDROP_POOL.toString());
+
+      const second = await snapshotResult(other);
+      expect(second.wrote).to.equal(false);
+      expect(second.day.toNumber()).to.equal(2);
+    });
+  });
+
+  describe("Pool activation", () => {
+    const user = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+
+    before(async () => {
+      await fundAccount(user.publicKey);
+      const mint = await createMintBankrun(TOKEN_DECIMALS, admin.publicKey);
+      const [poolState] = getPoolStatePda(mint);
+      const [poolToken] = getPoolTokenPda(poolState);
+      const layers = buildMerkleTree([computeLeaf(user.publicKey, amount)]);
+      const start = Number((await context.banksClient.getClock()).unixTimestamp) + 1000;
+
+      await program.methods.initializePool(new BN(start), Array.from(getMerkleRoot(layers)), computeDailyRewards(), defaultPoolConfig())
+        .accounts({ admin: admin.publicKey, poolState, tokenMint: mint, poolTokenAccount: poolToken, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID, rent: SYSVAR_RENT_PUBKEY })
+        .signers([admin]).rpc();
+
+      // Only the airdrop is deposited; the staking pool is missing
+      const adminAta = await getOrCreateATABankrun(mint, admin.publicKey);
+      await provider.sendAndConfirm(new anchor.web3.Transaction().add(
+        createMintToInstruction(mint, adminAta, admin.publicKey, BigInt(TOTAL_POOL.toString())),
+        createTransferInstruction(adminAta, poolToken, admin.publicKey, BigInt(AIRDROP_POOL.toString()))
+      ), [admin]);
+
+      p = { mint, poolState, poolToken, layers, start, adminAta };
+      await warpTo(start + 60);
+    });
+
+    it("rejects claims before activation", async () => {
+      await expectError(() => claimFor(p, user, amount), "PoolNotActive");
+    });
+
+    it("rejects activation of an underfunded pool", async () => {
+      await expectError(() => activatePool(p.poolState, p.poolToken), "PoolUnderfunded");
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.active).to.equal(0);
+    });
+
+    it("activates once fully funded and then accepts claims", async () => {
+      await provider.sendAndConfirm(new anchor.web3.Transaction().add(
+        createTransferInstruction(p.adminAta, p.poolToken, admin.publicKey, BigInt(STAKING_POOL.toString()))
+      ), [admin]);
+      await activatePool(p.poolState, p.poolToken);
+      expect((await program.account.poolState.fetch(p.poolState)).active).to.equal(1);
+
+      await expectError(() => activatePool(p.poolState, p.poolToken), "PoolAlreadyActive");
+
+      const { userAta } = await claimFor(p, user, amount);
+      expect(await tokenBalance(userAta)).to.equal(BigInt(amount.toString()));
+    });
+  });
+
+  describe("Configurable day length (devnet builds)", () => {
+    const DAY = 2;
+    const leaver = Keypair.generate();
+    const stayer = Keypair.generate();
+    const amount = new BN(5_000_000).mul(new BN(1e9));
+    let p: any;
+
+    function expectedRewards(state: any, days: number): bigint {
+      return accruedRewards(amount, state, 0, days);
+    }
+
+    async function snapshotDay(day: number) {
+      await warpTo(p.start + day * DAY + 1);
+      await snapshotPool(p);
+    }
+
+    before(async function () {
+      for (const u of [leaver, stayer]) await fundAccount(u.publicKey);
+      try {
+        p = await setupPool([computeLeaf(leaver.publicKey, amount), computeLeaf(stayer.publicKey, amount)], { secondsPerDay: DAY });
+      } catch (e: any) {
+        // Shortened days are rejected unless the program is built with `--features devnet`
+        expect((e.message || "").toString()).to.include("InvalidPoolConfig");
+        this.skip();
+      }
+      await warpTo(p.start + 1);
+      await claimFor(p, leaver, amount);
+      await claimFor(p, stayer, amount);
+    });
+
+    it("runs the 20-day program in 40 seconds with rewards accruing per short day", async () => {
+      for (let day = 1; day <= 10; day++) await snapshotDay(day);
+      const midState = await program.account.poolState.fetch(p.poolState);
+      expect(midState.snapshotCount).to.equal(10);
+
+      const leaverAta = await unstakeFor(p, leaver);
+      expect(await tokenBalance(leaverAta)).to.equal(BigInt(amount.toString()) + expectedRewards(midState, 10));
+
+      for (let day = 11; day <= 21; day++) await snapshotDay(day);
+      const endState = await program.account.poolState.fetch(p.poolState);
+      expect(endState.snapshotCount).to.equal(20);
+      // The leaver's stake left total_staked from day 10 on
+      expect(endState.dailySnapshots[10].lt(endState.dailySnapshots[9])).to.be.true;
+
+      const stayerAta = await unstakeFor(p, stayer);
+      expect(await tokenBalance(stayerAta)).to.equal(BigInt(amount.toString()) + expectedRewards(endState, 20));
+    });
+
+    it("closes the claim window after claim_window_days short days", async () => {
+      await warpTo(p.start + 40 * DAY + 1);
+      const phase = await program.methods.getPhase().accounts({ poolState: p.poolState }).view();
+      expect(phase.phase).to.equal(3);
+    });
+  });
+
+  describe("Mid-flight program extension", () => {
+    const user = Keypair.generate();
+    const amount = new BN(2_000_000).mul(new BN(1e9));
+    let p: any;
+    let last: BN;
+
+    function extend(additionalDays: number, rewards: BN[]) {
+      return program.methods.extendProgram(additionalDays, rewards)
+        .accounts({ admin: admin.publicKey, poolState: p.poolState, poolTokenAccount: p.poolToken, rewardPoolTokenAccount: null, adminTokenAccount: p.adminAta, tokenProgram: TOKEN_PROGRAM_ID })
+        .signers([admin]).rpc();
+    }
+
+    before(async () => {
+      await fundAccount(user.publicKey);
+      p = await setupPool([computeLeaf(user.publicKey, amount)]);
+      last = (await program.account.poolState.fetch(p.poolState)).dailyRewards[TOTAL_DAYS - 1];
+      await provider.sendAndConfirm(new anchor.web3.Transaction().add(
+        createMintToInstruction(p.mint, p.adminAta, admin.publicKey, BigInt(last.muln(3).toString()))
+      ), [admin]);
+      await warpTo(p.start + 60);
+      await claimFor(p, user, amount);
+    });
+
+    it("rejects rewards that break the ascending curve", async () => {
+      await warpTo(p.start + 10 * SECONDS_PER_DAY + 60);
+      await expectError(() => extend(3, [last, last, last.subn(1)]), "InvalidDailyRewardsOrder");
+    });
+
+    it("extends the running 20-day program to 23 days and takes the added rewards", async () => {
+      const poolBefore = await tokenBalance(p.poolToken);
+      const before = await program.account.poolState.fetch(p.poolState);
+      await extend(3, [last, last, last]);
+
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.programDays).to.equal(23);
+      expect(state.dailyRewards[22].toString()).to.equal(last.toString());
+      expect(state.stakingPool.sub(before.stakingPool).toString()).to.equal(last.muln(3).toString());
+      expect(state.stakingBudgetRemaining.sub(before.stakingBudgetRemaining).toString()).to.equal(last.muln(3).toString());
+      expect(await tokenBalance(p.poolToken)).to.equal(poolBefore + BigInt(last.muln(3).toString()));
+    });
+
+    it("rejects an extension on the final day", async () => {
+      await warpTo(p.start + 22 * SECONDS_PER_DAY + 60);
+      await expectError(() => extend(1, [last]), "ProgramExtensionTooLate");
+    });
+
+    it("snapshots and pays rewards for the added days", async () => {
+      await warpTo(p.start + 24 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.snapshotCount).to.equal(23);
+
+      const expected = accruedRewards(amount, state, 0, 23);
+      const userAta = await unstakeFor(p, user);
+      expect(await tokenBalance(userAta)).to.equal(BigInt(amount.toString()) + expected);
+    });
+  });
+
+  describe("calculate_rewards event", () => {
+    const user = Keypair.generate();
+    const amount = new BN(3_000_000).mul(new BN(1e9));
+    let p: any;
+    let userStake: PublicKey;
+
+    before(async () => {
+      await fundAccount(user.publicKey);
+      p = await setupPool([computeLeaf(user.publicKey, amount)]);
+      await warpTo(p.start + 60);
+      ({ userStake } = await claimFor(p, user, amount));
+      await warpTo(p.start + 3 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+    });
+
+    it("emits RewardsCalculated matching the logged reward", async () => {
+      const tx = await program.methods.calculateRewards(new BN(2))
+        .accounts({ poolState: p.poolState, userStake })
+        .transaction();
+      tx.recentBlockhash = context.lastBlockhash;
+      tx.feePayer = admin.publicKey;
+      tx.sign(admin);
+      const meta = await context.banksClient.simulateTransaction(tx);
+      const logs: string[] = meta.meta.logMessages;
+
+      const line = logs.find((l) => l.includes("Day 2 reward:"));
+      const parser = new anchor.EventParser(program.programId, program.coder);
+      const event = findEvent(Array.from(parser.parseLogs(logs)) as any[], "RewardsCalculated");
+      expect(event.data.user.toBase58()).to.equal(user.publicKey.toBase58());
+      expect(event.data.day.toNumber()).to.equal(2);
+      expect(event.data.reward.toString()).to.equal(line.split("reward: ")[1]);
+    });
+  });
+
+  describe("Paged reward query", () => {
+    const user = Keypair.generate();
+    const amount = new BN(4_000_000).mul(new BN(1e9));
+    let p: any;
+    let userStake: PublicKey;
+
+    function range(from: number, to: number) {
+      return program.methods.calculateRewardsRange(new BN(from), new BN(to))
+        .accounts({ poolState: p.poolState, userStake })
+        .view();
+    }
+
+    async function dayReward(day: number): Promise<string> {
+      const tx = await program.methods.calculateRewards(new BN(day))
+        .accounts({ poolState: p.poolState, userStake })
+        .transaction();
+      tx.recentBlockhash = context.lastBlockhash;
+      tx.feePayer = admin.publicKey;
+      tx.sign(admin);
+      const meta = await context.banksClient.simulateTransaction(tx);
+      const line = meta.meta.logMessages.find((l: string) => l.includes(`Day ${day} reward:`));
+      return line.split("reward: ")[1];
+    }
+
+    before(async () => {
+      await fundAccount(user.publicKey);
+      p = await setupPool([computeLeaf(user.publicKey, amount)]);
+      await warpTo(p.start + 60);
+      ({ userStake } = await claimFor(p, user, amount));
+      await warpTo(p.start + 8 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+    });
+
+    it("pages through the 20-day program in two calls", async () => {
+      const pages = [...(await range(0, 10)), ...(await range(10, 20))].map((r: BN) => r.toString());
+      const full: string[] = [];
+      for (let day = 0; day < TOTAL_DAYS; day++) full.push(await dayReward(day));
+      expect(pages).to.deep.equal(full);
+    });
+
+    it("rejects a range wider than the page size", async () => {
+      await expectError(() => range(0, 20), "RangeTooLarge");
+    });
+
+    it("rejects a range past the program end", async () => {
+      await expectError(() => range(15, 21), "InvalidDay");
+    });
+  });
+
+  describe("Harvest delegate", () => {
+    const owner = Keypair.generate();
+    const delegate = Keypair.generate();
+    const stranger = Keypair.generate();
+    const amount = new BN(2_000_000).mul(new BN(1e9));
+    let p: any;
+    let userStake: PublicKey;
+    let ownerAta: PublicKey;
+
+    function withdrawAs(signer: Keypair, value: BN) {
+      return program.methods.withdrawRewards(value)
+        .accounts({ user: signer.publicKey, poolState: p.poolState, userStake, poolTokenAccount: p.poolToken, userTokenAccount: ownerAta, treasuryTokenAccount: null, tokenProgram: TOKEN_PROGRAM_ID })
+        .signers([signer]).rpc();
+    }
+
+    before(async () => {
+      for (const u of [owner, delegate, stranger]) await fundAccount(u.publicKey);
+      p = await setupPool([computeLeaf(owner.publicKey, amount)]);
+      await warpTo(p.start + 60);
+      ({ userStake, userAta: ownerAta } = await claimFor(p, owner, amount));
+      await warpTo(p.start + 3 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+
+      await program.methods.setHarvestDelegate(delegate.publicKey)
+        .accounts({ user: owner.publicKey, poolState: p.poolState, userStake })
+        .signers([owner]).rpc();
+    });
+
+    it("records the delegate on the stake", async () => {
+      const stake = await program.account.userStake.fetch(userStake);
+      expect(stake.harvestDelegate.toBase58()).to.equal(delegate.publicKey.toBase58());
+    });
+
+    it("lets the delegate harvest into the owner's token account", async () => {
+      const before = await tokenBalance(ownerAta);
+      await withdrawAs(delegate, new BN(1_000));
+      expect(await tokenBalance(ownerAta)).to.equal(before + 1_000n);
+    });
+
+    it("rejects a harvest by anyone else", async () => {
+      await expectError(() => withdrawAs(stranger, new BN(1_000)), "InvalidStakeOwner");
+    });
+
+    it("does not let the delegate unstake", async () => {
+      const delegateAta = await getOrCreateATABankrun(p.mint, delegate.publicKey, delegate);
+      await expectError(() => program.methods.unstake()
+        .accounts({ user: delegate.publicKey, poolState: p.poolState, userStake, poolTokenAccount: p.poolToken, tokenMint: p.mint, userTokenAccount: delegateAta, treasuryTokenAccount: null, referrerTokenAccount: null, tokenProgram: TOKEN_PROGRAM_ID, associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID, systemProgram: SystemProgram.programId })
+        .signers([delegate]).rpc(), "ConstraintSeeds", "InvalidStakeOwner");
+      expect(await context.banksClient.getAccount(userStake)).to.not.be.null;
+    });
+  });
+
+  describe("Destination token account mint checks", () => {
+    const TIMELOCK_SECONDS = 172_800;
+    let expired: any;
+    let terminated: any;
+    let wrongAta: PublicKey;
+
+    function recover(p: any, destinationTokenAccount: PublicKey) {
+      return program.methods.recoverExpiredRewards()
+        .accounts({ admin: admin.publicKey, poolState: p.poolState, approval: null, poolTokenAccount: p.poolToken, tokenMint: p.mint, destinationTokenAccount, tokenProgram: TOKEN_PROGRAM_ID })
+        .signers([admin]).rpc();
+    }
+
+    function drain(p: any, destinationTokenAccount: PublicKey) {
+      return program.methods.executeDrain()
+        .accounts({ admin: admin.publicKey, poolState: p.poolState, approval: null, poolTokenAccount: p.poolToken, tokenMint: p.mint, destinationTokenAccount, tokenProgram: TOKEN_PROGRAM_ID })
+        .signers([admin]).rpc();
+    }
+
+    before(async () => {
+      expired = await setupPool([computeLeaf(Keypair.generate().publicKey, new BN(1))]);
+      terminated = await setupPool([computeLeaf(Keypair.generate().publicKey, new BN(1))]);
+      const wrongMint = await createMintBankrun(TOKEN_DECIMALS, admin.publicKey);
+      wrongAta = await getOrCreateATABankrun(wrongMint, admin.publicKey);
+
+      await program.methods.initiateTermination()
+        .accounts({ admin: admin.publicKey, poolState: terminated.poolState, approval: null })
+        .signers([admin]).rpc();
+      await warpTo(Math.max(expired.start + (CLAIM_WINDOW_DAYS + 1) * SECONDS_PER_DAY, terminated.start + TIMELOCK_SECONDS));
+    });
+
+    it("recover_expired_rewards rejects an admin account of another mint", async () => {
+      await expectError(() => recover(expired, wrongAta), "InvalidAdminTokenMint");
+      await recover(expired, expired.adminAta);
+      expect(await tokenBalance(expired.poolToken)).to.equal(0n);
+    });
+
+    it("execute_drain rejects an admin account of another mint", async () => {
+      await expectError(() => drain(terminated, wrongAta), "InvalidAdminTokenMint");
+      await drain(terminated, terminated.adminAta);
+      expect(await tokenBalance(terminated.poolToken)).to.equal(0n);
+    });
+  });
+
+  describe("Unstake checked math", () => {
+    const user = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+
+    before(async () => {
+      await fundAccount(user.publicKey);
+      p = await setupPool([computeLeaf(user.publicKey, amount)]);
+      await warpTo(p.start + 60);
+      await claimFor(p, user, amount);
+      await warpTo(p.start + 2 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+    });
+
+    it("returns MathOverflow when total_staked is below the exiting stake", async () => {
+      // Corrupt total_staked to force the subtraction to underflow
+      await setTotalStaked(p.poolState, 0n);
+
+      await expectError(() => unstakeFor(p, user), "MathOverflow");
+      const [userStake] = getUserStakePda(p.poolState, user.publicKey);
+      expect(await context.banksClient.getAccount(userStake)).to.not.be.null;
+    });
+  });
+
+  describe("Recovery destination", () => {
+    const TIMELOCK_SECONDS = 172_800;
+    const treasury = Keypair.generate();
+    const outsider = Keypair.generate();
+    let expired: any;
+    let terminated: any;
+    let treasuryAta: PublicKey;
+
+    before(async () => {
+      await fundAccount(outsider.publicKey);
+      expired = await setupPool([computeLeaf(Keypair.generate().publicKey, new BN(1))]);
+      terminated = await setupPool([computeLeaf(Keypair.generate().publicKey, new BN(1))]);
+      treasuryAta = await getOrCreateATABankrun(expired.mint, treasury.publicKey);
+
+      await program.methods.initiateTermination()
+        .accounts({ admin: admin.publicKey, poolState: terminated.poolState, approval: null })
+        .signers([admin]).rpc();
+      await warpTo(Math.max(expired.start + (CLAIM_WINDOW_DAYS + 1) * SECONDS_PER_DAY, terminated.start + TIMELOCK_SECONDS));
+    });
+
+    it("recover_expired_rewards routes tokens to a non-admin destination", async () => {
+      const events = await sendAndGetEvents(
+        program.methods.recoverExpiredRewards()
+          .accounts({ admin: admin.publicKey, poolState: expired.poolState, approval: null, poolTokenAccount: expired.poolToken, tokenMint: expired.mint, destinationTokenAccount: treasuryAta, tokenProgram: TOKEN_PROGRAM_ID }),
+        [admin]
+      );
+      expect(await tokenBalance(treasuryAta)).to.equal(BigInt(TOTAL_POOL.toString()));
+      expect(findEvent(events, "TokensRecovered").data.destination.toBase58()).to.equal(treasuryAta.toBase58());
+    });
+
+    it("execute_drain routes tokens to a non-admin destination", async () => {
+      const drainAta = await getOrCreateATABankrun(terminated.mint, treasury.publicKey);
+      const events = await sendAndGetEvents(
+        program.methods.executeDrain()
+          .accounts({ admin: admin.publicKey, poolState: terminated.poolState, approval: null, poolTokenAccount: terminated.poolToken, tokenMint: terminated.mint, destinationTokenAccount: drainAta, tokenProgram: TOKEN_PROGRAM_ID }),
+        [admin]
+      );
+      expect(await tokenBalance(drainAta)).to.equal(BigInt(TOTAL_POOL.toString()));
+      expect(findEvent(events, "TokensRecovered").data.destination.toBase58()).to.equal(drainAta.toBase58());
+    });
+
+    it("still requires the admin to sign", async () => {
+      const pool = await setupPool([computeLeaf(Keypair.generate().publicKey, new BN(1))]);
+      const outsiderAta = await getOrCreateATABankrun(pool.mint, outsider.publicKey, outsider);
+      await warpTo(pool.start + (CLAIM_WINDOW_DAYS + 1) * SECONDS_PER_DAY);
+      await expectError(() => program.methods.recoverExpiredRewards()
+        .accounts({ admin: outsider.publicKey, poolState: pool.poolState, approval: null, poolTokenAccount: pool.poolToken, tokenMint: pool.mint, destinationTokenAccount: outsiderAta, tokenProgram: TOKEN_PROGRAM_ID })
+        .signers([outsider]).rpc(), "UnauthorizedAdmin");
+      expect(await tokenBalance(outsiderAta)).to.equal(0n);
+    });
+  });
+
+  describe("Snapshot earliest offset", () => {
+    const OFFSET = 3600;
+    let p: any;
+
+    before(async () => {
+      p = await setupPool([computeLeaf(Keypair.generate().publicKey, new BN(1))], { snapshotEarliestOffsetSeconds: OFFSET });
+    });
+
+    it("rejects an offset of a full day", async () => {
+      const mint = await createMintBankrun(TOKEN_DECIMALS, admin.publicKey);
+      const [poolState] = getPoolStatePda(mint);
+      const [poolToken] = getPoolTokenPda(poolState);
+      await expectError(() => program.methods.initializePool(new BN(p.start), Array.from(Buffer.alloc(32)), computeDailyRewards(), defaultPoolConfig({ snapshotEarliestOffsetSeconds: SECONDS_PER_DAY }))
+        .accounts({ admin: admin.publicKey, poolState, tokenMint: mint, poolTokenAccount: poolToken, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID, rent: SYSVAR_RENT_PUBKEY })
+        .signers([admin]).rpc(), "InvalidPoolConfig");
+    });
+
+    it("rejects a snapshot before the offset into the day", async () => {
+      await warpTo(p.start + SECONDS_PER_DAY + 1);
+      await expectError(() => snapshotPool(p), "SnapshotTooEarly");
+      expect((await program.account.poolState.fetch(p.poolState)).snapshotCount).to.equal(0);
+    });
+
+    it("accepts the snapshot once the offset has passed", async () => {
+      await warpTo(p.start + SECONDS_PER_DAY + OFFSET);
+      await snapshotPool(p);
+      expect((await program.account.poolState.fetch(p.poolState)).snapshotCount).to.equal(1);
+    });
+  });
+
+  describe("get_claim_status view", () => {
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    const fresh = Keypair.generate();
+    const staker = Keypair.generate();
+    const exited = Keypair.generate();
+    const opener = Keypair.generate();
+    let p: any;
+
+    function status(user: Keypair) {
+      const [claimMarker] = getClaimMarkerPda(p.poolState, user.publicKey);
+      const [userStake] = getUserStakePda(p.poolState, user.publicKey);
+      return program.methods.getClaimStatus()
+        .accounts({ poolState: p.poolState, user: user.publicKey, claimMarker, userStake })
+        .view();
+    }
+
+    before(async () => {
+      for (const u of [staker, exited, opener]) await fundAccount(u.publicKey);
+      p = await setupPool([staker, exited, fresh].map((u) => computeLeaf(u.publicKey, amount)));
+      await warpTo(p.start + 60);
+      await claimFor(p, staker, amount);
+      await claimFor(p, exited, amount);
+      const [openerStake] = getUserStakePda(p.poolState, opener.publicKey);
+      await program.methods.openStakeAccount()
+        .accounts({ user: opener.publicKey, poolState: p.poolState, userStake: openerStake, systemProgram: SystemProgram.programId })
+        .signers([opener]).rpc();
+      await warpTo(p.start + SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+      await unstakeFor(p, exited);
+    });
+
+    it("not claimed, not staking", async () => {
+      const s = await status(fresh);
+      expect(s.claimed).to.equal(false);
+      expect(s.staking).to.equal(false);
+      expect(s.stakedAmount.toNumber()).to.equal(0);
+      expect(s.claimDay.toNumber()).to.equal(0);
+    });
+
+    it("claimed and staking", async () => {
+      const s = await status(staker);
+      expect(s.claimed).to.equal(true);
+      expect(s.staking).to.equal(true);
+      expect(s.stakedAmount.toString()).to.equal(amount.toString());
+      expect(s.claimDay.toNumber()).to.equal(0);
+    });
+
+    it("claimed, no longer staking after unstake", async () => {
+      const s = await status(exited);
+      expect(s.claimed).to.equal(true);
+      expect(s.staking).to.equal(false);
+      expect(s.stakedAmount.toNumber()).to.equal(0);
+    });
+
+    it("not claimed, staking account opened ahead of the claim", async () => {
+      const s = await status(opener);
+      expect(s.claimed).to.equal(false);
+      expect(s.staking).to.equal(true);
+      expect(s.stakedAmount.toNumber()).to.equal(0);
+    });
+  });
+
+  describe("Snapshot skip events", () => {
+    const other = Keypair.generate();
+    const late = Keypair.generate();
+    let p: any;
+
+    function snapshotIx(signer: Keypair) {
+      return program.methods.snapshot().accounts({ signer: signer.publicKey, poolState: p.poolState });
+    }
+
+    before(async () => {
+      await fundAccount(other.publicKey);
+      await fundAccount(late.publicKey);
+      p = await setupPool([computeLeaf(Keypair.generate().publicKey, new BN(1))]);
+      await warpTo(p.start + SECONDS_PER_DAY + 60);
+    });
+
+    it("emits SnapshotSkipped with ALREADY_RECORDED on the second crank of a day", async () => {
+      const first = await sendAndGetEvents(snapshotIx(admin), [admin]);
+      expect(findEvent(first, "snapshotTaken")).to.not.be.undefined;
+      expect(findEvent(first, "snapshotSkipped")).to.be.undefined;
+
+      const second = await sendAndGetEvents(snapshotIx(other), [other]);
+      expect(findEvent(second, "snapshotTaken")).to.be.undefined;
+      const skipped = findEvent(second, "snapshotSkipped");
+      expect(skipped.data.day.toNumber()).to.equal(1);
+      expect(skipped.data.reasonCode).to.equal(0);
+    });
+
+    it("reports PROGRAM_ENDED once the final day is recorded", async () => {
+      await warpTo(p.start + 21 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+
+      const events = await sendAndGetEvents(snapshotIx(late), [late]);
+      const skipped = findEvent(events, "snapshotSkipped");
+      expect(skipped.data.day.toNumber()).to.equal(20);
+      expect(skipped.data.reasonCode).to.equal(1);
+    });
+  });
+
+  describe("Stake swing circuit breaker", () => {
+    const small = Keypair.generate();
+    const whale = Keypair.generate();
+    const late = Keypair.generate();
+    const smallAmount = new BN(1_000_000).mul(new BN(1e9));
+    const whaleAmount = new BN(10_000_000).mul(new BN(1e9));
+    const lateAmount = new BN(1_000).mul(new BN(1e9));
+    let p: any;
+
+    before(async () => {
+      for (const u of [small, whale, late]) await fundAccount(u.publicKey);
+      p = await setupPool(
+        [computeLeaf(small.publicKey, smallAmount), computeLeaf(whale.publicKey, whaleAmount), computeLeaf(late.publicKey, lateAmount)],
+        { maxDailyStakeDeltaBps: 1000 },
+      );
+      await warpTo(p.start + 60);
+      await claimFor(p, small, smallAmount);
+      await claimFor(p, whale, whaleAmount);
+      await warpTo(p.start + SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+    });
+
+    it("does not trip on a swing within the threshold", async () => {
+      await unstakeFor(p, small);
+      await warpTo(p.start + 2 * SECONDS_PER_DAY + 60);
+      const events = await sendAndGetEvents(program.methods.snapshot().accounts({ signer: admin.publicKey, poolState: p.poolState }), [admin]);
+      expect(findEvent(events, "snapshotTaken")).to.not.be.undefined;
+      expect(findEvent(events, "circuitBreakerTripped")).to.be.undefined;
+      expect((await program.account.poolState.fetch(p.poolState)).paused).to.equal(0);
+    });
+
+    it("pauses the pool when the swing exceeds the threshold", async () => {
+      await unstakeFor(p, whale);
+      await warpTo(p.start + 3 * SECONDS_PER_DAY + 60);
+      const events = await sendAndGetEvents(program.methods.snapshot().accounts({ signer: admin.publicKey, poolState: p.poolState }), [admin]);
+      expect(findEvent(events, "snapshotTaken")).to.not.be.undefined;
+      const tripped = findEvent(events, "circuitBreakerTripped");
+      expect(tripped.data.day.toNumber()).to.equal(3);
+      // (66M - 56M) / 66M
+      expect(tripped.data.deltaBps.toNumber()).to.equal(1515);
+
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.paused).to.equal(1);
+      expect(state.pauseReason).to.equal(5);
+      expect(state.pauseExpiry.toNumber()).to.equal(0);
+      expect(state.snapshotCount).to.equal(3);
+    });
+
+    it("blocks claims and snapshots until the admin unpauses", async () => {
+      await expectError(() => claimFor(p, late, lateAmount), "PoolPaused");
+      await warpTo(p.start + 4 * SECONDS_PER_DAY + 60);
+      await expectError(() => snapshotPool(p), "PoolPaused");
+
+      await program.methods.unpausePool().accounts({ admin: admin.publicKey, poolState: p.poolState }).signers([admin]).rpc();
+      await snapshotPool(p);
+      await claimFor(p, late, lateAmount);
+      expect((await program.account.poolState.fetch(p.poolState)).snapshotCount).to.equal(4);
+    });
+  });
+
+  describe("Snapshot history view", () => {
+    const user = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+
+    before(async () => {
+      await fundAccount(user.publicKey);
+      p = await setupPool([computeLeaf(user.publicKey, amount)]);
+    });
+
+    it("returns empty arrays before any snapshot", async () => {
+      const history = await program.methods.getSnapshots().accounts({ poolState: p.poolState }).view();
+      expect(history.dailySnapshots).to.have.length(0);
+      expect(history.dailyRewards).to.have.length(0);
+    });
+
+    it("matches the recorded snapshots and rewards after several days", async () => {
+      await warpTo(p.start + 60);
+      await claimFor(p, user, amount);
+      await warpTo(p.start + SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+      await unstakeFor(p, user);
+      await warpTo(p.start + 4 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+
+      const state = await program.account.poolState.fetch(p.poolState);
+      const history = await program.methods.getSnapshots().accounts({ poolState: p.poolState }).view();
+      expect(state.snapshotCount).to.equal(4);
+      expect(history.dailySnapshots.map((v: BN) => v.toString()))
+        .to.deep.equal(state.dailySnapshots.slice(0, 4).map((v: BN) => v.toString()));
+      expect(history.dailyRewards.map((v: BN) => v.toString()))
+        .to.deep.equal(state.dailyRewards.slice(0, 4).map((v: BN) => v.toString()));
+      expect(history.dailySnapshots[0].toString()).to.equal(AIRDROP_POOL.toString());
+      expect(history.dailySnapshots[3].toString()).to.equal(AIRDROP_POOL.sub(amount).toString());
+    });
+  });
+
+  describe("Reward preview at the snapshot_count boundary", () => {
+    const staker = Keypair.generate();
+    const leaver = Keypair.generate();
+    const second = Keypair.generate();
+    const stakerAmount = new BN(3_000_000).mul(new BN(1e9));
+    const leaverAmount = new BN(5_000_000).mul(new BN(1e9));
+    const secondAmount = new BN(2_000_000).mul(new BN(1e9));
+    let p: any;
+    let userStake: PublicKey;
+
+    before(async () => {
+      for (const u of [staker, leaver, second]) await fundAccount(u.publicKey);
+      p = await setupPool(
+        [computeLeaf(staker.publicKey, stakerAmount), computeLeaf(leaver.publicKey, leaverAmount), computeLeaf(second.publicKey, secondAmount)],
+        { rewardMode: 1 },
+      );
+      await warpTo(p.start + 60);
+      ({ userStake } = await claimFor(p, staker, stakerAmount));
+      await claimFor(p, leaver, leaverAmount);
+      await claimFor(p, second, secondAmount);
+      await warpTo(p.start + SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+      await unstakeFor(p, leaver);
+      await warpTo(p.start + 2 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+      await unstakeFor(p, second);
+      await warpTo(p.start + 3 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+    });
+
+    it("prices day snapshot_count - 1 with its recorded denominator and day snapshot_count with the latest snapshot", async () => {
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.snapshotCount).to.equal(3);
+      const snaps: BN[] = state.dailySnapshots;
+      const rewards: BN[] = state.dailyRewards;
+      // Recorded days 1 and 2 differ, so the smoothed denominator (day 1's larger reading) differs from the point reading
+      expect(snaps[1].toString()).to.equal(AIRDROP_POOL.sub(leaverAmount).toString());
+      expect(snaps[2].toString()).to.equal(AIRDROP_POOL.sub(leaverAmount).sub(secondAmount).toString());
+
+      const [lastRecorded, firstFuture] = await program.methods.calculateRewardsRange(new BN(2), new BN(4))
+        .accounts({ poolState: p.poolState, userStake })
+        .view();
+      const smoothed = BN.max(snaps[1], snaps[2]);
+      expect(lastRecorded.toString()).to.equal(stakerAmount.mul(rewards[2]).div(smoothed).toString());
+      expect(firstFuture.toString()).to.equal(stakerAmount.mul(rewards[3]).div(snaps[2]).toString());
+    });
+
+    it("matches what unstake pays for recorded days", async () => {
+      const recorded = await program.methods.calculateRewardsRange(new BN(0), new BN(3))
+        .accounts({ poolState: p.poolState, userStake })
+        .view();
+      const info = await program.methods.getUserStake()
+        .accounts({ poolState: p.poolState, userStake })
+        .view();
+      const state = await program.account.poolState.fetch(p.poolState);
+      const snaps = state.dailySnapshots.map((v: BN) => BigInt(v.toString()));
+      const accrued = accruedRewards(stakerAmount, state, 0, 3, (d) => (d > 0 ? (snaps[d - 1] + snaps[d]) / 2n : snaps[d]));
+      // Pending sums the same day shares before flooring, so it can only exceed the floored previews
+      const sum = BigInt(recorded.reduce((acc: BN, r: BN) => acc.add(r), new BN(0)).toString());
+      expect(BigInt(info.pendingRewards.toString())).to.equal(accrued);
+      expect(accrued >= sum && accrued < sum + 3n).to.be.true;
+    });
+  });
+
+  describe("snapshot_count saturation", () => {
+    const user = Keypair.generate();
+    const amount = new BN(2_000_000).mul(new BN(1e9));
+    let p: any;
+    let userStake: PublicKey;
+
+    before(async () => {
+      await fundAccount(user.publicKey);
+      p = await setupPool([computeLeaf(user.publicKey, amount)]);
+      await warpTo(p.start + 60);
+      ({ userStake } = await claimFor(p, user, amount));
+    });
+
+    it("caps snapshot_count at program_days with the clock far past the program end", async () => {
+      await warpTo(p.start + 60 * SECONDS_PER_DAY);
+      await snapshotPool(p);
+      let state = await program.account.poolState.fetch(p.poolState);
+      expect(state.snapshotCount).to.equal(20);
+      expect(state.programDays).to.equal(20);
+
+      // A second cranker on a later day records nothing and leaves the count in place
+      const other = Keypair.generate();
+      await fundAccount(other.publicKey);
+      await warpTo(p.start + 200 * SECONDS_PER_DAY);
+      await snapshotPool(p, other);
+      state = await program.account.poolState.fetch(p.poolState);
+      expect(state.snapshotCount).to.equal(20);
+    });
+
+    it("keeps reward loops within the program days", async () => {
+      const rewards = await program.methods.calculateRewardsRange(new BN(0), new BN(16))
+        .accounts({ poolState: p.poolState, userStake })
+        .view();
+      expect(rewards).to.have.length(16);
+      await expectError(
+        () => program.methods.calculateRewardsRange(new BN(16), new BN(21)).accounts({ poolState: p.poolState, userStake }).view(),
+        "InvalidDay",
+      );
+
+      const info = await program.methods.getUserStake().accounts({ poolState: p.poolState, userStake }).view();
+      expect(info.pendingRewards.gtn(0)).to.equal(true);
+      await unstakeFor(p, user);
+    });
+  });
+
+  describe("Open stake cap", () => {
+    const first = Keypair.generate();
+    const second = Keypair.generate();
+    const third = Keypair.generate();
+    const tokens = (n: number) => new BN(n).mul(new BN(1e9));
+    const cap = tokens(3_000_000);
+    let p: any;
+
+    before(async () => {
+      for (const u of [first, second, third]) await fundAccount(u.publicKey);
+      p = await setupPool(
+        [computeLeaf(first.publicKey, tokens(2_000_000)), computeLeaf(second.publicKey, tokens(1_000_000)), computeLeaf(third.publicKey, tokens(1_000_000))],
+        { maxTotalStaked: cap },
+      );
+      await warpTo(p.start + 60);
+    });
+
+    it("accepts claims up to the cap", async () => {
+      await claimFor(p, first, tokens(2_000_000));
+      await claimFor(p, second, tokens(1_000_000));
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.totalAirdropClaimed.toString()).to.equal(cap.toString());
+    });
+
+    it("rejects the claim that would breach the cap", async () => {
+      await expectError(() => claimFor(p, third, tokens(1_000_000)), "StakeCapReached");
+    });
+
+    it("frees room once a stake exits", async () => {
+      await warpTo(p.start + SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+      await unstakeFor(p, second);
+      await claimFor(p, third, tokens(1_000_000));
+    });
+  });
+
+  describe("Merkle root view", () => {
+    const user = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+
+    it("returns the root passed at init and logs it in PoolInitialized", async () => {
+      const mint = await createMintBankrun(TOKEN_DECIMALS, admin.publicKey);
+      const [poolState] = getPoolStatePda(mint);
+      const [poolToken] = getPoolTokenPda(poolState);
+      const root = Array.from(getMerkleRoot(buildMerkleTree([computeLeaf(user.publicKey, amount)])));
+      const start = Number((await context.banksClient.getClock()).unixTimestamp) + 1000;
+
+      const events = await sendAndGetEvents(
+        program.methods.initializePool(new BN(start), root, computeDailyRewards(), defaultPoolConfig())
+          .accounts({ admin: admin.publicKey, poolState, tokenMint: mint, poolTokenAccount: poolToken, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID, rent: SYSVAR_RENT_PUBKEY }),
+        [admin],
+      );
+      expect(Array.from(findEvent(events, "poolInitialized").data.merkleRoot)).to.deep.equal(root);
+
+      const onChain = await program.methods.getMerkleRoot().accounts({ poolState }).view();
+      expect(Array.from(onChain)).to.deep.equal(root);
+    });
+  });
+
+  describe("Reward rounding direction", () => {
+    const amount = new BN(3_000_000).mul(new BN(1e9)).addn(7);
+
+    async function stakedPool(roundUpRewards: boolean) {
+      const user = Keypair.generate();
+      await fundAccount(user.publicKey);
+      const p = await setupPool([computeLeaf(user.publicKey, amount)], { roundUpRewards });
+      await warpTo(p.start + 60);
+      const { userStake } = await claimFor(p, user, amount);
+      await warpTo(p.start + 3 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+      return { p, userStake };
+    }
+
+    it("rounds each day's share down by default and up when configured", async () => {
+      const floor = await stakedPool(false);
+      const ceil = await stakedPool(true);
+      const floorRewards = await program.methods.calculateRewardsRange(new BN(0), new BN(3))
+        .accounts({ poolState: floor.p.poolState, userStake: floor.userStake }).view();
+      const ceilRewards = await program.methods.calculateRewardsRange(new BN(0), new BN(3))
+        .accounts({ poolState: ceil.p.poolState, userStake: ceil.userStake }).view();
+
+      const state = await program.account.poolState.fetch(ceil.p.poolState);
+      for (let d = 0; d < 3; d++) {
+        const num = amount.mul(state.dailyRewards[d]);
+        const den = state.dailySnapshots[d];
+        expect(num.mod(den).isZero()).to.equal(false);
+        expect(floorRewards[d].toString()).to.equal(num.div(den).toString());
+        expect(ceilRewards[d].toString()).to.equal(num.div(den).addn(1).toString());
+      }
+
+      const ceilInfo = await program.methods.getUserStake()
+        .accounts({ poolState: ceil.p.poolState, userStake: ceil.userStake }).view();
+      const sum = ceilRewards.reduce((acc: BN, r: BN) => acc.add(r), new BN(0));
+      expect(ceilInfo.pendingRewards.toString()).to.equal(sum.toString());
+    });
+
+    describe("budget ceiling when rounding up", () => {
+      const users = Array.from({ length: 8 }, () => Keypair.generate());
+      // Unequal allocations (1..8 parts) that together exhaust AIRDROP_POOL
+      const part = AIRDROP_POOL.div(new BN(36));
+      const amounts = users.map((_, i) => part.mul(new BN(i + 1)));
+      amounts[7] = amounts[7].add(AIRDROP_POOL.sub(part.mul(new BN(36))));
+      let p: any;
+
+      before(async () => {
+        for (const u of users) await fundAccount(u.publicKey);
+        p = await setupPool(users.map((u, i) => computeLeaf(u.publicKey, amounts[i])), { roundUpRewards: true });
+        await warpTo(p.start + 60);
+        for (let i = 0; i < users.length; i++) await claimFor(p, users[i], amounts[i]);
+        await warpTo(p.start + (TOTAL_DAYS + 1) * SECONDS_PER_DAY + 60);
+        await snapshotPool(p);
+      });
+
+      it("never pays more than the staking pool even though shares round up", async () => {
+        let paid = 0n;
+        for (let i = 0; i < users.length; i++) {
+          const userAta = await unstakeFor(p, users[i]);
+          paid += (await tokenBalance(userAta)) - BigInt(amounts[i].toString());
+        }
+
+        const staking = BigInt(STAKING_POOL.toString());
+        expect(paid <= staking).to.be.true;
+        const state = await program.account.poolState.fetch(p.poolState);
+        expect(state.stakingBudgetRemaining.toString()).to.equal((staking - paid).toString());
+      });
+    });
+  });
+
+  describe("Legacy UserStake migration", () => {
+    const user = Keypair.generate();
+    const amount = new BN(2_000_000).mul(new BN(1e9));
+    // Layout before harvest_delegate (32), version (1), claimed_days (4) and frozen (1) were appended
+    const LEGACY_TAIL = 38;
+    let p: any;
+    let userStake: PublicKey;
+    let fullLength: number;
+
+    function migrate() {
+      return program.methods.migrateUserStake()
+        .accounts({ payer: admin.publicKey, poolState: p.poolState, user: user.publicKey, userStake, systemProgram: SystemProgram.programId })
+        .signers([admin]).rpc();
+    }
+
+    before(async () => {
+      await fundAccount(user.publicKey);
+      p = await setupPool([computeLeaf(user.publicKey, amount)]);
+      await warpTo(p.start + 60);
+      ({ userStake } = await claimFor(p, user, amount));
+
+      const account = await context.banksClient.getAccount(userStake);
+      fullLength = account.data.length;
+      await context.setAccount(userStake, { ...account, data: Buffer.from(account.data).subarray(0, fullLength - LEGACY_TAIL) });
+      await warpTo(p.start + 2 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+    });
+
+    it("new stakes are written at the current version", async () => {
+      const other = Keypair.generate();
+      await fundAccount(other.publicKey);
+      const [otherStake] = getUserStakePda(p.poolState, other.publicKey);
+      await program.methods.openStakeAccount()
+        .accounts({ user: other.publicKey, poolState: p.poolState, userStake: otherStake, systemProgram: SystemProgram.programId })
+        .signers([other]).rpc();
+      expect((await program.account.userStake.fetch(otherStake)).version).to.equal(3);
+    });
+
+    it("a legacy-layout stake cannot be read until migrated", async () => {
+      await expectError(() => unstakeFor(p, user), "AccountDidNotDeserialize");
+    });
+
+    it("migrates in place, preserving the old fields", async () => {
+      await migrate();
+      const account = await context.banksClient.getAccount(userStake);
+      expect(account.data.length).to.equal(fullLength);
+
+      const stake = await program.account.userStake.fetch(userStake);
+      expect(stake.version).to.equal(3);
+      expect(stake.claimedDays).to.equal(0);
+      expect(stake.frozen).to.equal(0);
+      expect(stake.owner.toBase58()).to.equal(user.publicKey.toBase58());
+      expect(stake.stakedAmount.toString()).to.equal(amount.toString());
+      expect(stake.claimDay.toNumber()).to.equal(0);
+      expect(stake.harvestDelegate.toBase58()).to.equal(PublicKey.default.toBase58());
+    });
+
+    it("rejects a second migration", async () => {
+      await expectError(() => migrate(), "UserStakeAlreadyMigrated");
+    });
+
+    it("the migrated stake withdraws rewards and unstakes normally", async () => {
+      const info = await program.methods.getUserStake().accounts({ poolState: p.poolState, userStake }).view();
+      const half = info.pendingRewards.divn(2);
+      await program.methods.withdrawRewards(half)
+        .accounts({ user: user.publicKey, poolState: p.poolState, userStake, poolTokenAccount: p.poolToken, userTokenAccount: getAssociatedTokenAddressSync(p.mint, user.publicKey), treasuryTokenAccount: null, tokenProgram: TOKEN_PROGRAM_ID })
+        .signers([user]).rpc();
+      expect((await program.account.userStake.fetch(userStake)).rewardsWithdrawn.toString()).to.equal(half.toString());
+
+      const userAta = await unstakeFor(p, user);
+      expect(await tokenBalance(userAta)).to.equal(BigInt(amount.add(info.pendingRewards).toString()));
+      expect(await context.banksClient.getAccount(userStake)).to.be.null;
+    });
+  });
+
+  describe("Permissioned snapshots", () => {
+    const authority = Keypair.generate();
+    const stranger = Keypair.generate();
+
+    before(async () => {
+      await fundAccount(authority.publicKey);
+      await fundAccount(stranger.publicKey);
+    });
+
+    it("rejects permissioned mode without an authority", async () => {
+      const mint = await createMintBankrun(TOKEN_DECIMALS, admin.publicKey);
+      const [poolState] = getPoolStatePda(mint);
+      const [poolToken] = getPoolTokenPda(poolState);
+      const start = Number((await context.banksClient.getClock()).unixTimestamp) + 1000;
+      await expectError(
+        () => program.methods.initializePool(new BN(start), Array.from(Buffer.alloc(32)), computeDailyRewards(), defaultPoolConfig({ permissionedSnapshots: true }))
+          .accounts({ admin: admin.publicKey, poolState, tokenMint: mint, poolTokenAccount: poolToken, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID, rent: SYSVAR_RENT_PUBKEY })
+          .signers([admin]).rpc(),
+        "InvalidPoolConfig",
+      );
+    });
+
+    it("only the snapshot authority can crank in permissioned mode", async () => {
+      const p = await setupPool([computeLeaf(Keypair.generate().publicKey, new BN(1))], { snapshotAuthority: authority.publicKey, permissionedSnapshots: true });
+      await warpTo(p.start + SECONDS_PER_DAY + 60);
+
+      await expectError(() => snapshotPool(p, stranger), "Unauthorized");
+      await expectError(() => snapshotPool(p, admin), "Unauthorized");
+      await snapshotPool(p, authority);
+      expect((await program.account.poolState.fetch(p.poolState)).snapshotCount).to.equal(1);
+    });
+
+    it("anyone can crank in open mode, even with an authority configured", async () => {
+      const p = await setupPool([computeLeaf(Keypair.generate().publicKey, new BN(1))], { snapshotAuthority: authority.publicKey });
+      await warpTo(p.start + SECONDS_PER_DAY + 60);
+
+      await snapshotPool(p, stranger);
+      expect((await program.account.poolState.fetch(p.poolState)).snapshotCount).to.equal(1);
+    });
+  });
+
+  describe("Accrual freeze", () => {
+    const other = Keypair.generate();
+    let p: any;
+    let freezeTs: number;
+
+    function phase() {
+      return program.methods.getPhase().accounts({ poolState: p.poolState }).view();
+    }
+
+    function freeze() {
+      return program.methods.freezeAccrual().accounts({ admin: admin.publicKey, poolState: p.poolState }).signers([admin]).rpc();
+    }
+
+    function unfreeze() {
+      return program.methods.unfreezeAccrual().accounts({ admin: admin.publicKey, poolState: p.poolState }).signers([admin]).rpc();
+    }
+
+    before(async () => {
+      await fundAccount(other.publicKey);
+      p = await setupPool([computeLeaf(Keypair.generate().publicKey, new BN(1))]);
+      freezeTs = p.start + 2 * SECONDS_PER_DAY + 100;
+      await warpTo(freezeTs);
+      await snapshotPool(p);
+    });
+
+    it("only unfreezes a frozen pool and only the admin can freeze", async () => {
+      await expectError(() => unfreeze(), "AccrualNotFrozen");
+      await expectError(
+        () => program.methods.freezeAccrual().accounts({ admin: other.publicKey, poolState: p.poolState }).signers([other]).rpc(),
+        "UnauthorizedAdmin",
+      );
+    });
+
+    it("freezing for a day leaves the program day unchanged", async () => {
+      const before = await phase();
+      await freeze();
+      await expectError(() => freeze(), "AccrualAlreadyFrozen");
+
+      await warpTo(freezeTs + SECONDS_PER_DAY);
+      const during = await phase();
+      expect(during.phase).to.equal(1);
+      expect(during.daysRemainingInPhase.toNumber()).to.equal(before.daysRemainingInPhase.toNumber());
+
+      // Still day 2: the snapshot has nothing new to record
+      const events = await sendAndGetEvents(program.methods.snapshot().accounts({ signer: other.publicKey, poolState: p.poolState }), [other]);
+      expect(findEvent(events, "snapshotSkipped").data.day.toNumber()).to.equal(2);
+      expect((await program.account.poolState.fetch(p.poolState)).snapshotCount).to.equal(2);
+    });
+
+    it("unfreezing banks the frozen duration", async () => {
+      await unfreeze();
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.frozenSeconds.toNumber()).to.equal(SECONDS_PER_DAY);
+      expect(state.frozenSince.toNumber()).to.equal(0);
+    });
+
+    it("shifts the claim window end out by the frozen duration", async () => {
+      // One hour past the unfrozen window end the pool is still in its exit window
+      await warpTo(p.start + 40 * SECONDS_PER_DAY + 3600);
+      const shifted = await phase();
+      expect(shifted.phase).to.equal(2);
+      expect(shifted.daysRemainingInPhase.toNumber()).to.equal(1);
+
+      await warpTo(p.start + 41 * SECONDS_PER_DAY);
+      expect((await phase()).phase).to.equal(3);
+      await expectError(() => freeze(), "AccrualFreezeOutsideProgram");
+    });
+  });
+
+  describe("Snapshot correction", () => {
+    const user = Keypair.generate();
+    const other = Keypair.generate();
+    const amount = new BN(2_000_000).mul(new BN(1e9));
+    const corrected = AIRDROP_POOL.sub(new BN(1_000_000).mul(new BN(1e9)));
+    let p: any;
+
+    function correct(day: number, value: BN, signer: Keypair = admin) {
+      return program.methods.adminSetSnapshot(new BN(day), value)
+        .accounts({ admin: signer.publicKey, poolState: p.poolState })
+        .signers([signer]).rpc();
+    }
+
+    before(async () => {
+      await fundAccount(user.publicKey);
+      await fundAccount(other.publicKey);
+      p = await setupPool([computeLeaf(user.publicKey, amount)]);
+      await warpTo(p.start + 60);
+      await claimFor(p, user, amount);
+      await warpTo(p.start + 2 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+    });
+
+    it("only the admin can correct", async () => {
+      await expectError(() => correct(1, corrected, other), "UnauthorizedAdmin");
+    });
+
+    it("rejects a value below the open stake", async () => {
+      await expectError(() => correct(1, amount.subn(1)), "InvalidSnapshotValue");
+    });
+
+    it("corrects the snapshot recorded today", async () => {
+      const events = await sendAndGetEvents(
+        program.methods.adminSetSnapshot(new BN(1), corrected).accounts({ admin: admin.publicKey, poolState: p.poolState }),
+        [admin],
+      );
+      const event = findEvent(events, "snapshotCorrected");
+      expect(event.data.day.toNumber()).to.equal(1);
+      expect(event.data.old.toString()).to.equal(AIRDROP_POOL.toString());
+      expect(event.data.new.toString()).to.equal(corrected.toString());
+
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.dailySnapshots[1].toString()).to.equal(corrected.toString());
+      expect(state.dailySnapshots[0].toString()).to.equal(AIRDROP_POOL.toString());
+    });
+
+    it("rejects finalized past days", async () => {
+      await expectError(() => correct(0, corrected), "SnapshotNotCorrectable");
+      await expectError(() => correct(2, corrected), "SnapshotNotCorrectable");
+
+      await warpTo(p.start + 3 * SECONDS_PER_DAY + 60);
+      await expectError(() => correct(1, AIRDROP_POOL), "SnapshotNotCorrectable");
+    });
+  });
+
+  describe("Batched claims", () => {
+    const relayer = Keypair.generate();
+    const users = [Keypair.generate(), Keypair.generate(), Keypair.generate(), Keypair.generate(), Keypair.generate(), Keypair.generate()];
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+
+    async function batchAccounts(user: Keypair, signs: boolean, tokenOwner: PublicKey) {
+      const userAta = await getOrCreateATABankrun(p.mint, tokenOwner);
+      const [claimMarker] = getClaimMarkerPda(p.poolState, user.publicKey);
+      const [userStake] = getUserStakePda(p.poolState, user.publicKey);
+      const [blacklistEntry] = getBlacklistPda(p.poolState, user.publicKey);
+      return [
+        { pubkey: user.publicKey, isSigner: signs, isWritable: false },
+        { pubkey: userAta, isSigner: false, isWritable: true },
+        { pubkey: claimMarker, isSigner: false, isWritable: true },
+        { pubkey: userStake, isSigner: false, isWritable: true },
+        { pubkey: blacklistEntry, isSigner: false, isWritable: false },
+      ];
+    }
+
+    // Claimers co-sign, so a batch of two is what fits in one transaction with these proofs
+    async function sendBatch(
+      batch: Keypair[],
+      proofFor = (u: Keypair) => getMerkleProof(p.layers, computeLeaf(u.publicKey, amount)),
+      { unsigned = null as Keypair | null, tokenOwnerFor = (u: Keypair) => u.publicKey } = {},
+    ) {
+      const claims = batch.map((u) => ({ amount, index: new BN(0), proof: proofFor(u) }));
+      const remaining = [];
+      for (const u of batch) remaining.push(...(await batchAccounts(u, u !== unsigned, tokenOwnerFor(u))));
+      await program.methods.claimAirdropBatch(claims)
+        .accounts({ relayer: relayer.publicKey, poolState: p.poolState, poolTokenAccount: p.poolToken, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID })
+        .remainingAccounts(remaining)
+        .signers([relayer, ...batch.filter((u) => u !== unsigned)]).rpc();
+    }
+
+    before(async () => {
+      await fundAccount(relayer.publicKey);
+      p = await setupPool(users.map((u) => computeLeaf(u.publicKey, amount)));
+      await warpTo(p.start + 60);
+    });
+
+    it("relays two co-signed claims in one transaction", async () => {
+      await sendBatch(users.slice(0, 2));
+      for (const u of users.slice(0, 2)) {
+        const [claimMarker] = getClaimMarkerPda(p.poolState, u.publicKey);
+        expect(await context.banksClient.getAccount(claimMarker)).to.not.be.null;
+        const [userStake] = getUserStakePda(p.poolState, u.publicKey);
+        const stake = await program.account.userStake.fetch(userStake);
+        expect(stake.owner.toBase58()).to.equal(u.publicKey.toBase58());
+        expect(stake.stakedAmount.toString()).to.equal(amount.toString());
+        const ata = await getAccountBankrun(getAssociatedTokenAddressSync(p.mint, u.publicKey));
+        expect(ata.amount.toString()).to.equal(amount.toString());
+      }
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.totalAirdropClaimed.toString()).to.equal(amount.muln(2).toString());
+    });
+
+    it("rejects a claimer who did not sign", async () => {
+      const batch = users.slice(2, 4);
+      await expectError(() => sendBatch(batch, undefined, { unsigned: batch[1] }), "BatchClaimerNotSigner");
+      const [claimMarker] = getClaimMarkerPda(p.poolState, batch[0].publicKey);
+      expect(await context.banksClient.getAccount(claimMarker)).to.be.null;
+    });
+
+    it("rejects a token account the claimer does not own", async () => {
+      await expectError(
+        () => sendBatch([users[2]], undefined, { tokenOwnerFor: () => users[0].publicKey }),
+        "InvalidBatchAccounts",
+      );
+    });
+
+    it("reverts the whole batch when one proof is invalid", async () => {
+      const batch = users.slice(4);
+      // The middle claim carries another claimer's proof
+      const tampered = batch[1];
+      await expectError(
+        () => sendBatch(batch, (u) => getMerkleProof(p.layers, computeLeaf((u === tampered ? users[0] : u).publicKey, amount))),
+        "InvalidMerkleProof",
+      );
+      for (const u of batch) {
+        const [claimMarker] = getClaimMarkerPda(p.poolState, u.publicKey);
+        expect(await context.banksClient.getAccount(claimMarker)).to.be.null;
+      }
+    });
+
+    it("rejects a batch above MAX_BATCH_CLAIMS", async () => {
+      // Checked before any account, so the oversized batch needs neither proofs nor accounts
+      const claims = Array(5).fill({ amount, index: new BN(0), proof: [] });
+      await expectError(
+        () => program.methods.claimAirdropBatch(claims)
+          .accounts({ relayer: relayer.publicKey, poolState: p.poolState, poolTokenAccount: p.poolToken, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID })
+          .signers([relayer]).rpc(),
+        "BatchTooLarge",
+      );
+    });
+  });
+
+  describe("Terminate surplus split", () => {
+    const TIMELOCK_SECONDS = 172_800;
+    const treasury = Keypair.generate();
+    let p: any;
+    let treasuryAta: PublicKey;
+
+    function split(treasuryBps: number, destinationTokenAccount: PublicKey = p.adminAta) {
+      return program.methods.terminatePoolSplit(treasuryBps)
+        .accounts({ admin: admin.publicKey, poolState: p.poolState, approval: null, poolTokenAccount: p.poolToken, tokenMint: p.mint, destinationTokenAccount, treasuryTokenAccount: treasuryAta, tokenProgram: TOKEN_PROGRAM_ID });
+    }
+
+    before(async () => {
+      p = await setupPool([computeLeaf(Keypair.generate().publicKey, new BN(1))], { treasury: treasury.publicKey });
+      treasuryAta = await getOrCreateATABankrun(p.mint, treasury.publicKey);
+      // 7 extra raw units so the surplus does not divide evenly
+      await provider.sendAndConfirm(new anchor.web3.Transaction().add(
+        createMintToInstruction(p.mint, p.poolToken, admin.publicKey, 7n)
+      ), [admin]);
+      await program.methods.initiateTermination()
+        .accounts({ admin: admin.publicKey, poolState: p.poolState, approval: null })
+        .signers([admin]).rpc();
+      await warpTo(p.start + TIMELOCK_SECONDS);
+    });
+
+    it("rejects treasury_bps above 10000", async () => {
+      await expectError(() => split(10_001).signers([admin]).rpc(), "InvalidTreasuryBps");
+    });
+
+    it("rejects a destination of another mint", async () => {
+      const otherMint = await createMintBankrun(TOKEN_DECIMALS, admin.publicKey);
+      const otherAta = await getOrCreateATABankrun(otherMint, admin.publicKey);
+      await expectError(() => split(3_333, otherAta).signers([admin]).rpc(), "InvalidAdminTokenMint");
+    });
+
+    it("splits the surplus by treasury_bps, leaving rounding dust to the admin", async () => {
+      const surplus = BigInt(TOTAL_POOL.toString()) + 7n;
+      const expectedTreasury = (surplus * 3_333n) / 10_000n;
+      const adminBefore = await tokenBalance(p.adminAta);
+
+      const events = await sendAndGetEvents(split(3_333), [admin]);
+      expect(await tokenBalance(treasuryAta)).to.equal(expectedTreasury);
+      expect((await tokenBalance(p.adminAta)) - adminBefore).to.equal(surplus - expectedTreasury);
+      expect(await tokenBalance(p.poolToken)).to.equal(0n);
+
+      const event = findEvent(events, "PoolTerminated").data;
+      expect(event.treasuryAmount.toString()).to.equal(expectedTreasury.toString());
+      expect(event.adminAmount.toString()).to.equal((surplus - expectedTreasury).toString());
+      expect(event.treasuryBps).to.equal(3_333);
+    });
+  });
+
+  describe("Reward cliff", () => {
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+
+    async function stakedPool(rewardCliffDays: number) {
+      const user = Keypair.generate();
+      await fundAccount(user.publicKey);
+      const p = await setupPool([computeLeaf(user.publicKey, amount)], { rewardCliffDays: new BN(rewardCliffDays) });
+      await warpTo(p.start + 60);
+      const { userStake } = await claimFor(p, user, amount);
+      return { p, userStake };
+    }
+
+    it("pays nothing for days 0 and 1 and normally from day 2 with a 2-day cliff", async () => {
+      const cliff = await stakedPool(2);
+      const base = await stakedPool(0);
+      await warpTo(Math.max(cliff.p.start, base.p.start) + 4 * SECONDS_PER_DAY + 60);
+      await snapshotPool(cliff.p);
+      await snapshotPool(base.p);
+
+      const range = async ({ p, userStake }: any) =>
+        (await program.methods.calculateRewardsRange(new BN(0), new BN(4)).accounts({ poolState: p.poolState, userStake }).view())
+          .map((r: any) => BigInt(r.toString()));
+      const cliffDays = await range(cliff);
+      const baseDays = await range(base);
+      expect(cliffDays.slice(0, 2)).to.deep.equal([0n, 0n]);
+      expect(baseDays[0] > 0n).to.be.true;
+      expect(cliffDays.slice(2)).to.deep.equal(baseDays.slice(2));
+
+      const pending = async ({ p, userStake }: any) =>
+        BigInt((await program.methods.getUserStake().accounts({ poolState: p.poolState, userStake }).view()).pendingRewards.toString());
+      const accrued = async ({ p }: any, from: number) => accruedRewards(amount, await program.account.poolState.fetch(p.poolState), from, 4);
+      expect(await pending(cliff)).to.equal(await accrued(cliff, 2));
+      expect(await pending(base)).to.equal(await accrued(base, 0));
+    });
+
+    it("rejects a cliff of MAX_PROGRAM_DAYS or more", async () => {
+      await expectError(() => setupPool([computeLeaf(Keypair.generate().publicKey, amount)], { rewardCliffDays: new BN(32) }), "InvalidPoolConfig");
+    });
+  });
+
+  describe("Per-day reward claims", () => {
+    const user = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+    let userStake: PublicKey;
+    let userAta: PublicKey;
+
+    function claimDay(day: number) {
+      return program.methods.claimDayReward(new BN(day))
+        .accounts({ user: user.publicKey, poolState: p.poolState, userStake, poolTokenAccount: p.poolToken, userTokenAccount: userAta, treasuryTokenAccount: null, tokenProgram: TOKEN_PROGRAM_ID })
+        .signers([user]).rpc();
+    }
+
+    async function pendingRewards(): Promise<bigint> {
+      const info = await program.methods.getUserStake().accounts({ poolState: p.poolState, userStake }).view();
+      return BigInt(info.pendingRewards.toString());
+    }
+
+    before(async () => {
+      await fundAccount(user.publicKey);
+      p = await setupPool([computeLeaf(user.publicKey, amount)]);
+      await warpTo(p.start + 60);
+      ({ userStake, userAta } = await claimFor(p, user, amount));
+      await warpTo(p.start + 3 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+    });
+
+    it("pays a finalized day's share once and marks it in claimed_days", async () => {
+      const [dayShare] = await program.methods.calculateRewardsRange(new BN(1), new BN(2)).accounts({ poolState: p.poolState, userStake }).view();
+      const before = await tokenBalance(userAta);
+      await claimDay(1);
+      expect(await tokenBalance(userAta)).to.equal(before + BigInt(dayShare.toString()));
+      expect((await program.account.userStake.fetch(userStake)).claimedDays).to.equal(0b10);
+
+      await expectError(() => claimDay(1), "DayAlreadyClaimed");
+    });
+
+    it("rejects a day without a snapshot", async () => {
+      await expectError(() => claimDay(3), "SnapshotRequiredFirst");
+    });
+
+    it("unstake pays principal plus only the unclaimed days", async () => {
+      const pending = await pendingRewards();
+      const before = await tokenBalance(userAta);
+      await unstakeFor(p, user);
+      expect(await tokenBalance(userAta)).to.equal(before + BigInt(amount.toString()) + pending);
+    });
+  });
+
+  describe("Expiry view", () => {
+    let p: any;
+
+    const status = () => program.methods.isExpired().accounts({ poolState: p.poolState }).view();
+
+    before(async () => {
+      p = await setupPool([computeLeaf(Keypair.generate().publicKey, new BN(1))]);
+    });
+
+    it("flips to expired exactly at the exit deadline", async () => {
+      const deadline = p.start + CLAIM_WINDOW_DAYS * SECONDS_PER_DAY;
+
+      await warpTo(deadline - 1);
+      const open = await status();
+      expect(open.expired).to.equal(false);
+      expect(open.exitDeadline.toNumber()).to.equal(deadline);
+
+      await warpTo(deadline);
+      const closed = await status();
+      expect(closed.expired).to.equal(true);
+      expect(closed.exitDeadline.toNumber()).to.equal(deadline);
+    });
+  });
+
+  describe("Airdrop cap increase", () => {
+    const whale = Keypair.generate();
+    const latecomer = Keypair.generate();
+    const extra = new BN(1_000).mul(new BN(1e9));
+    let p: any;
+
+    function increase(additional: BN) {
+      return program.methods.increaseAirdropCap(additional)
+        .accounts({ admin: admin.publicKey, poolState: p.poolState, poolTokenAccount: p.poolToken });
+    }
+
+    before(async () => {
+      for (const u of [whale, latecomer]) await fundAccount(u.publicKey);
+      p = await setupPool([computeLeaf(whale.publicKey, AIRDROP_POOL), computeLeaf(latecomer.publicKey, extra)]);
+      await warpTo(p.start + 60);
+      await claimFor(p, whale, AIRDROP_POOL);
+      await expectError(() => claimFor(p, latecomer, extra), "AirdropPoolExhausted");
+    });
+
+    it("rejects an increase the pool does not hold tokens for", async () => {
+      await expectError(() => increase(extra).signers([admin]).rpc(), "PoolUnderfunded");
+      await expectError(() => claimFor(p, latecomer, extra), "AirdropPoolExhausted");
+    });
+
+    it("rejects an increase that overflows the cap with MathOverflow", async () => {
+      await expectError(() => increase(new BN("18446744073709551615")).signers([admin]).rpc(), "MathOverflow");
+    });
+
+    it("accepts claims beyond the original cap after a funded increase", async () => {
+      await provider.sendAndConfirm(new anchor.web3.Transaction().add(
+        createMintToInstruction(p.mint, p.poolToken, admin.publicKey, BigInt(extra.toString())),
+        await increase(extra).instruction(),
+      ), [admin]);
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.airdropCap.toString()).to.equal(AIRDROP_POOL.add(extra).toString());
+      expect(state.airdropExhausted).to.equal(0);
+
+      await claimFor(p, latecomer, extra);
+      const stats = await program.methods.getPoolStats().accounts({ poolState: p.poolState, poolTokenAccount: p.poolToken }).view();
+      expect(stats.totalAirdropClaimed.toString()).to.equal(AIRDROP_POOL.add(extra).toString());
+      expect(stats.airdropRemaining.toNumber()).to.equal(0);
+    });
+  });
+
+  describe("Claim diagnosis", () => {
+    const user = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+
+    const errorCode = (name: string) => program.idl.errors.find((e: any) => e.name.toLowerCase() === name.toLowerCase()).code;
+
+    async function diagnose(proof = getMerkleProof(p.layers, computeLeaf(user.publicKey, amount))): Promise<number> {
+      const [claimMarker] = getClaimMarkerPda(p.poolState, user.publicKey);
+      const [blacklistEntry] = getBlacklistPda(p.poolState, user.publicKey);
+      const result = await program.methods.debugClaim(amount, proof, new BN(0), 0)
+        .accounts({ user: user.publicKey, poolState: p.poolState, claimMarker, blacklistEntry })
+        .view();
+      return result.errorCode;
+    }
+
+    before(async () => {
+      await fundAccount(user.publicKey);
+      p = await setupPool([computeLeaf(user.publicKey, amount), computeLeaf(Keypair.generate().publicKey, amount)]);
+    });
+
+    it("reports a claim before start_time", async () => {
+      expect(await diagnose()).to.equal(errorCode("poolNotStartedYet"));
+    });
+
+    it("reports a paused pool", async () => {
+      await warpTo(p.start + 60);
+      await program.methods.pausePool(new BN(0), 0).accounts({ admin: admin.publicKey, poolState: p.poolState }).signers([admin]).rpc();
+      expect(await diagnose()).to.equal(errorCode("poolPaused"));
+      await program.methods.unpausePool().accounts({ admin: admin.publicKey, poolState: p.poolState }).signers([admin]).rpc();
+    });
+
+    it("reports a bad proof", async () => {
+      expect(await diagnose([Array.from(Buffer.alloc(32, 7))])).to.equal(errorCode("invalidMerkleProof"));
+    });
+
+    it("returns 0 for a claim that would pass, without creating accounts", async () => {
+      expect(await diagnose()).to.equal(0);
+      const [claimMarker] = getClaimMarkerPda(p.poolState, user.publicKey);
+      expect(await context.banksClient.getAccount(claimMarker)).to.be.null;
+    });
+
+    it("reports an address that already claimed", async () => {
+      await claimFor(p, user, amount);
+      expect(await diagnose()).to.equal(errorCode("alreadyClaimed"));
+    });
+  });
+
+  describe("Reward u64 narrowing", () => {
+    const user = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+    let userStake: PublicKey;
+
+    before(async () => {
+      await fundAccount(user.publicKey);
+      p = await setupPool([computeLeaf(user.publicKey, amount)]);
+      await warpTo(p.start + 60);
+      ({ userStake } = await claimFor(p, user, amount));
+      await warpTo(p.start + 2 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+
+      // Corrupt daily_snapshots[0] (offset 8 + 4 * 32 + 8 * 3 + 4 + 4 * 2 + 8 * 32) to 1 so
+      // the day's share no longer fits in a u64
+      const account = await context.banksClient.getAccount(p.poolState);
+      const data = Buffer.from(account.data);
+      data.writeBigUInt64LE(1n, 428);
+      await context.setAccount(p.poolState, { ...account, data });
+    });
+
+    it("fails with MathOverflow instead of truncating accrued rewards", async () => {
+      // Sent as transactions so the program error is decoded
+      await expectError(() => program.methods.getUserStake().accounts({ poolState: p.poolState, userStake }).rpc(), "MathOverflow");
+      await expectError(() => unstakeFor(p, user), "MathOverflow");
+    });
+
+    it("fails with MathOverflow instead of truncating a day preview", async () => {
+      await expectError(() => program.methods.calculateRewards(new BN(0)).accounts({ poolState: p.poolState, userStake }).rpc(), "MathOverflow");
+    });
+  });
+
+  describe("Unstake after the program ends", () => {
+    const user = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+
+    before(async () => {
+      await fundAccount(user.publicKey);
+      p = await setupPool([computeLeaf(user.publicKey, amount)]);
+      await warpTo(p.start + 60);
+      await claimFor(p, user, amount);
+    });
+
+    it("pays the full 20 days of rewards when unstaking on day 25", async () => {
+      await warpTo(p.start + 25 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+
+      const state = await program.account.poolState.fetch(p.poolState);
+      const expected = accruedRewards(amount, state, 0, 20);
+
+      const userAta = getAssociatedTokenAddressSync(p.mint, user.publicKey);
+      const before = await tokenBalance(userAta);
+      await unstakeFor(p, user);
+      expect(await tokenBalance(userAta)).to.equal(before + expected);
+    });
+  });
+
+  describe("Donations", () => {
+    const donor = Keypair.generate();
+    const amount = new BN(5_000).mul(new BN(1e9));
+    let p: any;
+    let donorAta: PublicKey;
+
+    before(async () => {
+      await fundAccount(donor.publicKey);
+      p = await setupPool([computeLeaf(Keypair.generate().publicKey, new BN(1))], {}, 1000, false);
+      donorAta = await getOrCreateATABankrun(p.mint, donor.publicKey);
+      await provider.sendAndConfirm(new anchor.web3.Transaction().add(
+        createMintToInstruction(p.mint, donorAta, admin.publicKey, BigInt(amount.toString()))
+      ), [admin]);
+    });
+
+    it("moves the donation into the pool and raises the budget and counter", async () => {
+      const poolBefore = await tokenBalance(p.poolToken);
+      const stateBefore = await program.account.poolState.fetch(p.poolState);
+
+      const events = await sendAndGetEvents(
+        program.methods.donate(amount)
+          .accounts({ donor: donor.publicKey, poolState: p.poolState, poolTokenAccount: p.poolToken, rewardPoolTokenAccount: null, donorTokenAccount: donorAta, tokenProgram: TOKEN_PROGRAM_ID }),
+        [donor],
+      );
+
+      expect(await tokenBalance(p.poolToken)).to.equal(poolBefore + BigInt(amount.toString()));
+      expect(await tokenBalance(donorAta)).to.equal(0n);
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.stakingBudgetRemaining.toString()).to.equal(stateBefore.stakingBudgetRemaining.add(amount).toString());
+      expect(state.totalDonated.toString()).to.equal(amount.toString());
+      expect(state.totalAirdropClaimed.toString()).to.equal(stateBefore.totalAirdropClaimed.toString());
+
+      const donated = findEvent(events, "Donated").data;
+      expect(donated.donor.toBase58()).to.equal(donor.publicKey.toBase58());
+      expect(donated.amount.toString()).to.equal(amount.toString());
+    });
+
+    it("keeps the donation in the budget when the program is resized", async () => {
+      const before = await program.account.poolState.fetch(p.poolState);
+      const last = before.dailyRewards[TOTAL_DAYS - 1];
+      await program.methods.resizePoolState(TOTAL_DAYS + 2, [last, last])
+        .accounts({ admin: admin.publicKey, poolState: p.poolState, systemProgram: SystemProgram.programId })
+        .signers([admin]).rpc();
+
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.stakingPool.toString()).to.equal(before.stakingPool.add(last.muln(2)).toString());
+      expect(state.stakingBudgetRemaining.toString()).to.equal(before.stakingBudgetRemaining.add(last.muln(2)).toString());
+      expect(state.stakingBudgetRemaining.sub(state.stakingPool).toString()).to.equal(amount.toString());
+    });
+
+    it("keeps the donation in the budget when the schedule is rebalanced", async () => {
+      const before = await program.account.poolState.fetch(p.poolState);
+      const halved = before.dailyRewards.slice(0, before.programDays).map((r: any) => r.divn(2));
+      const newSum = halved.reduce((a: any, b: any) => a.add(b), new BN(0));
+      await program.methods.rebalanceDailyRewards(halved)
+        .accounts({ admin: admin.publicKey, poolState: p.poolState, poolTokenAccount: p.poolToken, adminTokenAccount: p.adminAta, tokenProgram: TOKEN_PROGRAM_ID })
+        .signers([admin]).rpc();
+
+      const state = await program.account.poolState.fetch(p.poolState);
+      const refunded = before.stakingPool.sub(newSum);
+      expect(state.stakingBudgetRemaining.toString()).to.equal(before.stakingBudgetRemaining.sub(refunded).toString());
+      expect(state.stakingBudgetRemaining.sub(state.stakingPool).toString()).to.equal(amount.toString());
+    });
+  });
+
+  describe("State checkpoint", () => {
+    const user = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+
+    before(async () => {
+      await fundAccount(user.publicKey);
+      p = await setupPool([computeLeaf(user.publicKey, amount), computeLeaf(Keypair.generate().publicKey, amount)]);
+      await warpTo(p.start + 60);
+      await claimFor(p, user, amount);
+      await warpTo(p.start + 2 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+      await unstakeFor(p, user);
+    });
+
+    it("emits the live pool totals", async () => {
+      const events = await sendAndGetEvents(
+        program.methods.emitStateCheckpoint().accounts({ poolState: p.poolState }),
+        [admin],
+      );
+      const checkpoint = findEvent(events, "StateCheckpoint").data;
+      const state = await program.account.poolState.fetch(p.poolState);
+
+      expect(checkpoint.totalStaked.toString()).to.equal(state.totalStaked.toString());
+      expect(checkpoint.totalAirdropClaimed.toString()).to.equal(state.totalAirdropClaimed.toString());
+      expect(checkpoint.snapshotCount).to.equal(state.snapshotCount);
+      expect(checkpoint.totalRewardsPaid.toString()).to.equal(state.stakingPool.sub(state.stakingBudgetRemaining).toString());
+      expect(checkpoint.totalRewardsPaid.gtn(0)).to.be.true;
+      expect(checkpoint.paused).to.equal(false);
+      expect(checkpoint.terminated).to.equal(false);
+    });
+  });
+
+  describe("Proof depth limit", () => {
+    const user = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+
+    function claimWithProof(proof: number[][]) {
+      const [userStake] = getUserStakePda(p.poolState, user.publicKey);
+      const [claimMarker] = getClaimMarkerPda(p.poolState, user.publicKey);
+      const [blacklistEntry] = getBlacklistPda(p.poolState, user.publicKey);
+      return program.methods.claimAirdrop(amount, proof, new BN(0), 0)
+        .accounts({ user: user.publicKey, poolState: p.poolState, claimMarker, blacklistEntry, userStake, poolTokenAccount: p.poolToken, userTokenAccount: getAssociatedTokenAddressSync(p.mint, user.publicKey), systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID })
+        .signers([user]).rpc();
+    }
+
+    before(async () => {
+      await fundAccount(user.publicKey);
+      p = await setupPool([computeLeaf(user.publicKey, amount), computeLeaf(Keypair.generate().publicKey, amount)]);
+      await getOrCreateATABankrun(p.mint, user.publicKey, user);
+      await warpTo(p.start + 60);
+    });
+
+    it("rejects a proof longer than MAX_PROOF_DEPTH before verifying it", async () => {
+      const proof = getMerkleProof(p.layers, computeLeaf(user.publicKey, amount));
+      // MAX_PROOF_DEPTH + 1 nodes; too large for a claim transaction, so checked through
+      // verify_proof, which shares the depth check with every claim path
+      const padded = [...proof, ...Array.from({ length: 25 - proof.length }, () => Array.from(Buffer.alloc(32, 1)))];
+      await expectError(
+        () => program.methods.verifyProof(amount, padded, user.publicKey, new BN(0)).accounts({ poolState: p.poolState }).rpc(),
+        "ProofTooLong",
+      );
+    });
+
+    it("still accepts a normal-depth proof", async () => {
+      await claimWithProof(getMerkleProof(p.layers, computeLeaf(user.publicKey, amount)));
+      const [claimMarker] = getClaimMarkerPda(p.poolState, user.publicKey);
+      expect(await context.banksClient.getAccount(claimMarker)).to.not.be.null;
+    });
+  });
+
+  describe("Unstake to another account", () => {
+    const user = Keypair.generate();
+    const custodian = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+    let userStake: PublicKey;
+
+    const unstakeTo = (destination: PublicKey) =>
+      program.methods.unstakeTo(destination)
+        .accounts({ user: user.publicKey, poolState: p.poolState, userStake, poolTokenAccount: p.poolToken, destinationTokenAccount: destination, rewardPoolTokenAccount: null, treasuryTokenAccount: null, referrerTokenAccount: null, tokenProgram: TOKEN_PROGRAM_ID })
+        .signers([user]).rpc();
+
+    before(async () => {
+      await fundAccount(user.publicKey);
+      p = await setupPool([computeLeaf(user.publicKey, amount)]);
+      [userStake] = getUserStakePda(p.poolState, user.publicKey);
+      await warpTo(p.start + 60);
+      await claimFor(p, user, amount);
+      await warpTo(p.start + 25 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+    });
+
+    it("rejects a destination of another mint", async () => {
+      const otherMint = await createMintBankrun(TOKEN_DECIMALS, admin.publicKey);
+      const wrongMintAccount = await getOrCreateATABankrun(otherMint, custodian.publicKey);
+      await expectError(() => unstakeTo(wrongMintAccount), "ConstraintTokenMint");
+      expect(await context.banksClient.getAccount(userStake)).to.not.be.null;
+    });
+
+    it("pays the rewards into a third-party token account", async () => {
+      const state = await program.account.poolState.fetch(p.poolState);
+      const expected = accruedRewards(amount, state, 0, 20);
+
+      const custodyAta = await getOrCreateATABankrun(p.mint, custodian.publicKey);
+      await unstakeTo(custodyAta);
+
+      expect(await tokenBalance(custodyAta)).to.equal(expected);
+      expect(await getAccountBankrun(getAssociatedTokenAddressSync(p.mint, user.publicKey))).to.be.null;
+      expect(await context.banksClient.getAccount(userStake)).to.be.null;
+    });
+  });
+
+  describe("Stake freeze", () => {
+    const user = Keypair.generate();
+    const outsider = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+    let userStake: PublicKey;
+    let userAta: PublicKey;
+
+    const setFrozen = (frozen: boolean, signer: Keypair = admin) =>
+      (frozen ? program.methods.freezeStake(user.publicKey) : program.methods.unfreezeStake(user.publicKey))
+        .accounts({ admin: signer.publicKey, poolState: p.poolState, userStake })
+        .signers([signer]).rpc();
+
+    async function pendingRewards(): Promise<bigint> {
+      const info = await program.methods.getUserStake().accounts({ poolState: p.poolState, userStake }).view();
+      return BigInt(info.pendingRewards.toString());
+    }
+
+    before(async () => {
+      await fundAccount(user.publicKey);
+      await fundAccount(outsider.publicKey);
+      p = await setupPool([computeLeaf(user.publicKey, amount)]);
+      await warpTo(p.start + 60);
+      ({ userStake, userAta } = await claimFor(p, user, amount));
+      await warpTo(p.start + 2 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+    });
+
+    it("only lets the admin freeze a stake", async () => {
+      await expectError(() => setFrozen(true, outsider), "UnauthorizedAdmin");
+      await expectError(() => setFrozen(false), "StakeNotFrozen");
+      await setFrozen(true);
+      expect((await program.account.userStake.fetch(userStake)).frozen).to.equal(1);
+      await expectError(() => setFrozen(true), "StakeAlreadyFrozen");
+    });
+
+    it("blocks unstake and reward withdrawals while rewards keep accruing", async () => {
+      await expectError(() => unstakeFor(p, user), "StakeFrozen");
+      await expectError(
+        () => program.methods.withdrawRewards(new BN(1))
+          .accounts({ user: user.publicKey, poolState: p.poolState, userStake, poolTokenAccount: p.poolToken, userTokenAccount: userAta, treasuryTokenAccount: null, tokenProgram: TOKEN_PROGRAM_ID })
+          .signers([user]).rpc(),
+        "StakeFrozen",
+      );
+
+      const pendingBefore = await pendingRewards();
+      await warpTo(p.start + 4 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+      expect(await pendingRewards() > pendingBefore).to.be.true;
+    });
+
+    it("allows unstaking again after unfreeze_stake", async () => {
+      await setFrozen(false);
+      const before = await tokenBalance(userAta);
+      await unstakeFor(p, user);
+      expect(await tokenBalance(userAta) > before).to.be.true;
+      expect(await context.banksClient.getAccount(userStake)).to.be.null;
+    });
+  });
+
+  describe("Zero-stake day carry-forward", () => {
+    const user = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+    let userStake: PublicKey;
+    let scheduled: BN[];
+
+    before(async () => {
+      await fundAccount(user.publicKey);
+      p = await setupPool([computeLeaf(user.publicKey, amount)]);
+      scheduled = (await program.account.poolState.fetch(p.poolState)).dailyRewards;
+      await warpTo(p.start + 60);
+      ({ userStake } = await claimFor(p, user, amount));
+    });
+
+    it("moves a zero-stake day's reward into carry_reward", async () => {
+      const totalStaked = (await program.account.poolState.fetch(p.poolState)).totalStaked;
+      await setTotalStaked(p.poolState, 0n);
+      await warpTo(p.start + SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+      await setTotalStaked(p.poolState, BigInt(totalStaked.toString()));
+
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.dailySnapshots[0].toString()).to.equal("0");
+      // The published schedule is left as configured
+      expect(state.dailyRewards[0].toString()).to.equal(scheduled[0].toString());
+      expect(state.carryReward.toString()).to.equal(scheduled[0].toString());
+    });
+
+    it("pays the carried reward out with the next staked day", async () => {
+      await warpTo(p.start + 2 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+
+      const state = await program.account.poolState.fetch(p.poolState);
+      const dayOne = scheduled[0].add(scheduled[1]);
+      expect(state.dailyRewards[1].toString()).to.equal(scheduled[1].toString());
+      expect(state.carriedRewards[1].toString()).to.equal(scheduled[0].toString());
+      expect(state.carryReward.toString()).to.equal("0");
+
+      const [dayShare] = await program.methods.calculateRewardsRange(new BN(1), new BN(2)).accounts({ poolState: p.poolState, userStake }).view();
+      expect(dayShare.toString()).to.equal(amount.mul(dayOne).div(state.dailySnapshots[1]).toString());
+    });
+  });
+
+  describe("Snapshot completeness on drain", () => {
+    const TIMELOCK_SECONDS = 172_800;
+    let p: any;
+
+    async function setDayRecorded(day: number, recorded: boolean) {
+      const account = await context.banksClient.getAccount(p.poolState);
+      const state = program.coder.accounts.decode("poolState", Buffer.from(account.data));
+      state.snapshotTakenBitmap = recorded ? state.snapshotTakenBitmap | (1 << day) : state.snapshotTakenBitmap & ~(1 << day);
+      const data = await program.coder.accounts.encode("poolState", state);
+      await context.setAccount(p.poolState, { ...account, data: Buffer.concat([data, Buffer.alloc(account.data.length - data.length)]) });
+    }
+
+    const drain = () =>
+      program.methods.executeDrain()
+        .accounts({ admin: admin.publicKey, poolState: p.poolState, approval: null, poolTokenAccount: p.poolToken, tokenMint: p.mint, destinationTokenAccount: p.adminAta, tokenProgram: TOKEN_PROGRAM_ID })
+        .signers([admin]).rpc();
+
+    before(async () => {
+      p = await setupPool([computeLeaf(Keypair.generate().publicKey, new BN(1))]);
+      await warpTo(p.start + 3 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+      await program.methods.initiateTermination()
+        .accounts({ admin: admin.publicKey, poolState: p.poolState, approval: null })
+        .signers([admin]).rpc();
+      await warpTo(p.start + 3 * SECONDS_PER_DAY + 60 + TIMELOCK_SECONDS);
+    });
+
+    it("rejects the drain when a day below snapshot_count was never recorded", async () => {
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.snapshotCount).to.equal(3);
+      await setDayRecorded(1, false);
+      await expectError(drain, "SnapshotsNotCompleted");
+    });
+
+    it("drains once every recorded day is in the bitmap", async () => {
+      await setDayRecorded(1, true);
+      const adminBefore = await tokenBalance(p.adminAta);
+      await drain();
+      expect(await tokenBalance(p.adminAta) > adminBefore).to.be.true;
+    });
+  });
+
+  describe("Final payout estimate", () => {
+    const user = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+    let userStake: PublicKey;
+
+    before(async () => {
+      await fundAccount(user.publicKey);
+      p = await setupPool([computeLeaf(user.publicKey, amount)]);
+      await warpTo(p.start + 60);
+      ({ userStake } = await claimFor(p, user, amount));
+      await warpTo(p.start + 2 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+    });
+
+    it("adds recorded rewards to the remaining days projected on the latest snapshot", async () => {
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.snapshotCount).to.equal(2);
+      let expected = new BN(accruedRewards(amount, state, 0, 2).toString());
+      for (let d = 2; d < 20; d++) expected = expected.add(amount.mul(state.dailyRewards[d]).div(state.dailySnapshots[1]));
+
+      const estimate = await program.methods.estimateFinalPayout().accounts({ poolState: p.poolState, userStake }).view();
+      expect(estimate.toString()).to.equal(expected.toString());
+    });
+  });
+
+  describe("Pool token authority", () => {
+    it("initializes the pool token account as its own authority", async () => {
+      const p = await setupPool([computeLeaf(Keypair.generate().publicKey, new BN(1))]);
+      const poolToken = await getAccountBankrun(p.poolToken);
+      expect(poolToken.owner.toBase58()).to.equal(p.poolToken.toBase58());
+
+      const state = await program.account.poolState.fetch(p.poolState);
+      const [derived, bump] = getPoolTokenPda(p.poolState);
+      expect(derived.toBase58()).to.equal(p.poolToken.toBase58());
+      expect(state.poolTokenBump).to.equal(bump);
+    });
+  });
+
+  describe("Reward precision for tiny stakes", () => {
+    const user = Keypair.generate();
+    // One raw unit against a pool of AIRDROP_POOL: each day's share is a fraction of a unit
+    const amount = new BN(1);
+    let p: any;
+    let userStake: PublicKey;
+    let userAta: PublicKey;
+
+    const pending = async () =>
+      BigInt((await program.methods.getUserStake().accounts({ poolState: p.poolState, userStake }).view()).pendingRewards.toString());
+
+    before(async () => {
+      await fundAccount(user.publicKey);
+      p = await setupPool([computeLeaf(user.publicKey, amount)]);
+      await warpTo(p.start + 60);
+      ({ userStake, userAta } = await claimFor(p, user, amount));
+    });
+
+    it("rounds every single day to zero", async () => {
+      await warpTo(p.start + 2 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+      const days = await program.methods.calculateRewardsRange(new BN(0), new BN(2)).accounts({ poolState: p.poolState, userStake }).view();
+      expect(days.map((r: BN) => r.toString())).to.deep.equal(["0", "0"]);
+      expect(await pending()).to.equal(0n);
+    });
+
+    it("accumulates the fractions across days and pays them once they reach a unit", async () => {
+      await warpTo(p.start + (TOTAL_DAYS + 1) * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+      const state = await program.account.poolState.fetch(p.poolState);
+      const accrued = accruedRewards(amount, state, 0, TOTAL_DAYS);
+      expect(accrued > 0n).to.be.true;
+      expect(await pending()).to.equal(accrued);
+
+      const before = await tokenBalance(userAta);
+      await unstakeFor(p, user);
+      expect(await tokenBalance(userAta)).to.equal(before + accrued);
+    });
+  });
+
+  describe("Zero claim amount", () => {
+    const zeroUser = Keypair.generate();
+    const zero = new BN(0);
+    let p: any;
+
+    before(async () => {
+      await fundAccount(zeroUser.publicKey);
+      p = await setupPool([computeLeaf(zeroUser.publicKey, zero)]);
+      await warpTo(p.start + 60);
+    });
+
+    it("rejects a zero-amount leaf even though its proof verifies", async () => {
+      const proof = getMerkleProof(p.layers, computeLeaf(zeroUser.publicKey, zero));
+      expect(await program.methods.verifyProof(zero, proof, zeroUser.publicKey, new BN(0))
+        .accounts({ poolState: p.poolState }).view()).to.be.true;
+
+      await expectError(() => claimFor(p, zeroUser, zero), "ZeroClaimAmount");
+      const [userStake] = getUserStakePda(p.poolState, zeroUser.publicKey);
+      expect(await context.banksClient.getAccount(userStake)).to.be.null;
+    });
+  });
+
+  describe("Pool flags", () => {
+    const PAUSED = 1, TERMINATED = 2, INITIALIZED = 4, ACTIVE = 8;
+    let p: any;
+
+    const flags = async () => program.methods.getFlags().accounts({ poolState: p.poolState }).view();
+
+    before(async () => {
+      p = await setupPool([computeLeaf(Keypair.generate().publicKey, new BN(1e9))], {}, 1000, false);
+    });
+
+    it("reports initialized only before activation", async () => {
+      expect(await flags()).to.equal(INITIALIZED);
+      await activatePool(p.poolState, p.poolToken);
+      expect(await flags()).to.equal(INITIALIZED | ACTIVE);
+    });
+
+    it("reflects pausing and termination", async () => {
+      await program.methods.pausePool(new BN(0), 0).accounts({ admin: admin.publicKey, poolState: p.poolState }).signers([admin]).rpc();
+      expect(await flags()).to.equal(INITIALIZED | ACTIVE | PAUSED);
+
+      await program.methods.initiateTermination()
+        .accounts({ admin: admin.publicKey, poolState: p.poolState, approval: null })
+        .signers([admin]).rpc();
+      expect(await flags()).to.equal(INITIALIZED | ACTIVE | PAUSED | TERMINATED);
+
+      await program.methods.unpausePool().accounts({ admin: admin.publicKey, poolState: p.poolState }).signers([admin]).rpc();
+      expect(await flags()).to.equal(INITIALIZED | ACTIVE | TERMINATED);
+    });
+  });
+
+  describe("Guardian unpause", () => {
+    const guardian = Keypair.generate();
+    const stranger = Keypair.generate();
+    let p: any;
+
+    before(async () => {
+      await fundAccount(guardian.publicKey);
+      await fundAccount(stranger.publicKey);
+      p = await setupPool([computeLeaf(Keypair.generate().publicKey, new BN(1e9))], { guardian: guardian.publicKey });
+      await program.methods.pausePool(new BN(0), 0).accounts({ admin: admin.publicKey, poolState: p.poolState }).signers([admin]).rpc();
+    });
+
+    it("rejects unpause from a key that is neither admin nor guardian", async () => {
+      await expectError(() => program.methods.unpausePool()
+        .accounts({ admin: stranger.publicKey, poolState: p.poolState })
+        .signers([stranger]).rpc(), "UnauthorizedAdmin");
+    });
+
+    it("lets the guardian unpause", async () => {
+      await program.methods.unpausePool()
+        .accounts({ admin: guardian.publicKey, poolState: p.poolState })
+        .signers([guardian]).rpc();
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.paused).to.equal(0);
+    });
+
+    it("does not let the guardian pause or terminate", async () => {
+      await expectError(() => program.methods.pausePool(new BN(0), 0)
+        .accounts({ admin: guardian.publicKey, poolState: p.poolState })
+        .signers([guardian]).rpc(), "UnauthorizedAdmin");
+      await expectError(() => program.methods.initiateTermination()
+        .accounts({ admin: guardian.publicKey, poolState: p.poolState, approval: null })
+        .signers([guardian]).rpc(), "UnauthorizedAdmin");
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.paused).to.equal(0);
+      expect(state.terminated).to.equal(0);
+    });
+  });
+
+  describe("Reward curve check", () => {
+    // Ascending ramp: 20a + 190 step = STAKING_POOL
+    const step = STAKING_POOL.divn(400);
+    const base = STAKING_POOL.sub(step.muln(190)).divn(20);
+    const ramp = Array.from({ length: TOTAL_DAYS }, (_, d) => base.add(step.muln(d)));
+
+    async function initWith(dailyRewards: BN[], rewardCurve: number) {
+      const mint = await createMintBankrun(TOKEN_DECIMALS, admin.publicKey);
+      const [poolState] = getPoolStatePda(mint);
+      const [poolToken] = getPoolTokenPda(poolState);
+      const start = Number((await context.banksClient.getClock()).unixTimestamp) + 1000;
+      await program.methods.initializePool(new BN(start), Array.from(getMerkleRoot(buildMerkleTree([computeLeaf(admin.publicKey, new BN(1e9))]))), dailyRewards, defaultPoolConfig({ rewardCurve }))
+        .accounts({ admin: admin.publicKey, poolState, tokenMint: mint, poolTokenAccount: poolToken, systemProgram: SystemProgram.programId, tokenProgram: TOKEN_PROGRAM_ID, rent: SYSVAR_RENT_PUBKEY })
+        .signers([admin]).rpc();
+      return poolState;
+    }
+
+    it("accepts a constant schedule under the constant check", async () => {
+      const poolState = await initWith(computeDailyRewards().slice(0, TOTAL_DAYS), 1);
+      const state = await program.account.poolState.fetch(poolState);
+      expect(state.config.rewardCurve).to.equal(1);
+    });
+
+    it("rejects a back-loaded schedule under the constant check", async () => {
+      await expectError(() => initWith(ramp, 1), "InvalidDailyRewards");
+    });
+
+    it("accepts the ramp under the linear check and rejects a kinked one", async () => {
+      await initWith(ramp, 2);
+      const kinked = [...ramp];
+      kinked[9] = kinked[9].sub(step.divn(2));
+      kinked[10] = kinked[10].add(step.divn(2));
+      await expectError(() => initWith(kinked, 2), "InvalidDailyRewards");
+    });
+
+    it("rejects an unknown curve", async () => {
+      await expectError(() => initWith(ramp, 3), "InvalidPoolConfig");
+    });
+  });
+
+  describe("close_claim_marker_terminated", () => {
+    const markerUser = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+    let claimMarker: PublicKey;
+
+    const close = () => program.methods.closeClaimMarkerTerminated()
+      .accounts({ user: markerUser.publicKey, poolState: p.poolState, claimMarker })
+      .signers([markerUser]).rpc();
+
+    before(async () => {
+      await fundAccount(markerUser.publicKey);
+      p = await setupPool([computeLeaf(markerUser.publicKey, amount)]);
+      await warpTo(p.start + 60);
+      ({ claimMarker } = await claimFor(p, markerUser, amount));
+    });
+
+    it("rejects closing a marker while the pool is not terminated", async () => {
+      await expectError(close, "PoolNotTerminated");
+      expect(await context.banksClient.getAccount(claimMarker)).to.not.be.null;
+    });
+
+    it("closes the marker and returns its rent once terminated", async () => {
+      await program.methods.initiateTermination()
+        .accounts({ admin: admin.publicKey, poolState: p.poolState, approval: null })
+        .signers([admin]).rpc();
+      const before = await context.banksClient.getBalance(markerUser.publicKey);
+
+      await close();
+
+      expect(await context.banksClient.getAccount(claimMarker)).to.be.null;
+      const after = await context.banksClient.getBalance(markerUser.publicKey);
+      // The refunded rent outweighs the transaction fee
+      expect(after > before).to.be.true;
+    });
+  });
+
+  describe("Per-day reward consistency", () => {
+    const aAmount = new BN(1_000_000).mul(new BN(1e9));
+    const bAmount = new BN(3_333_333).mul(new BN(1e9));
+
+    for (const roundUpRewards of [false, true]) {
+      it(`calculate_rewards matches the accrued per-day term every day (round up: ${roundUpRewards})`, async () => {
+        const a = Keypair.generate();
+        const b = Keypair.generate();
+        await fundAccount(a.publicKey);
+        await fundAccount(b.publicKey);
+        const p = await setupPool([computeLeaf(a.publicKey, aAmount), computeLeaf(b.publicKey, bAmount)], { rewardMode: 1, roundUpRewards });
+        await warpTo(p.start + 60);
+        const { userStake } = await claimFor(p, a, aAmount);
+        await warpTo(p.start + 2 * SECONDS_PER_DAY + 60);
+        await snapshotPool(p);
+        await claimFor(p, b, bAmount);
+        await warpTo(p.start + 5 * SECONDS_PER_DAY + 60);
+        await snapshotPool(p);
+
+        const state = await program.account.poolState.fetch(p.poolState);
+        const count = state.snapshotCount;
+        const snap = (d: number) => BigInt(state.dailySnapshots[d].toString());
+        const averaged = (d: number) => (d > 0 && snap(d - 1) > snap(d) ? snap(d - 1) : snap(d));
+        const perDay: BN[] = await program.methods.calculateRewardsRange(new BN(0), new BN(count))
+          .accounts({ poolState: p.poolState, userStake })
+          .view();
+
+        let summed = 0n;
+        for (let d = 0; d < count; d++) {
+          const numerator = BigInt(aAmount.toString()) * BigInt(state.dailyRewards[d].toString());
+          const expected = roundUpRewards
+            ? (numerator + averaged(d) - 1n) / averaged(d)
+            : accruedRewards(aAmount, state, d, d + 1, averaged);
+          expect(perDay[d].toString(), `day ${d}`).to.equal(expected.toString());
+          summed += expected;
+        }
+
+        const info = await program.methods.getUserStake().accounts({ poolState: p.poolState, userStake }).view();
+        const pending = roundUpRewards ? summed : accruedRewards(aAmount, state, 0, count, averaged);
+        expect(info.pendingRewards.toString()).to.equal(pending.toString());
+      });
+    }
+  });
+
+  describe("Reward cap", () => {
+    const capUser = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    const capBps = 500;
+    const cap = amount.muln(capBps).divn(10_000);
+    let p: any;
+    let userStake: PublicKey;
+    let userAta: PublicKey;
+
+    before(async () => {
+      await fundAccount(capUser.publicKey);
+      p = await setupPool([computeLeaf(capUser.publicKey, amount)], { maxRewardMultipleBps: capBps });
+      await warpTo(p.start + 60);
+      ({ userStake, userAta } = await claimFor(p, capUser, amount));
+      await warpTo(p.start + 2 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+    });
+
+    it("rejects withdrawing past the cap", async () => {
+      await expectError(() => program.methods.withdrawRewards(cap.addn(1))
+        .accounts({ user: capUser.publicKey, poolState: p.poolState, userStake, poolTokenAccount: p.poolToken, userTokenAccount: userAta, treasuryTokenAccount: null, tokenProgram: TOKEN_PROGRAM_ID })
+        .signers([capUser]).rpc(), "InsufficientRewards");
+    });
+
+    it("pays exactly the cap on unstake and leaves the excess in the pool", async () => {
+      const before = await program.account.poolState.fetch(p.poolState);
+      const accrued = accruedRewards(amount, before, 0, 2);
+      expect(accrued > BigInt(cap.toString())).to.be.true;
+      const userBefore = await tokenBalance(userAta);
+      const poolBefore = await tokenBalance(p.poolToken);
+
+      const events = await sendAndGetEvents(
+        program.methods.unstake().accounts({ user: capUser.publicKey, poolState: p.poolState, userStake, poolTokenAccount: p.poolToken, tokenMint: p.mint, userTokenAccount: userAta, tokenProgram: TOKEN_PROGRAM_ID, associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID, systemProgram: SystemProgram.programId }),
+        [capUser]
+      );
+
+      expect((await tokenBalance(userAta)) - userBefore).to.equal(BigInt(cap.toString()));
+      expect(poolBefore - (await tokenBalance(p.poolToken))).to.equal(BigInt(cap.toString()));
+      const after = await program.account.poolState.fetch(p.poolState);
+      expect(before.stakingBudgetRemaining.sub(after.stakingBudgetRemaining).toString()).to.equal(cap.toString());
+      const capped = findEvent(events, "RewardCapped");
+      expect(capped.data.cappedAmount.toString()).to.equal((accrued - BigInt(cap.toString())).toString());
+    });
+  });
+
+  describe("Open stake accounting on drain", () => {
+    const TIMELOCK_SECONDS = 172_800;
+    const stakeUser = Keypair.generate();
+    const amount = new BN(1_000_000).mul(new BN(1e9));
+    let p: any;
+
+    const drain = () =>
+      program.methods.executeDrain()
+        .accounts({ admin: admin.publicKey, poolState: p.poolState, approval: null, poolTokenAccount: p.poolToken, tokenMint: p.mint, destinationTokenAccount: p.adminAta, tokenProgram: TOKEN_PROGRAM_ID })
+        .signers([admin]).rpc();
+
+    before(async () => {
+      await fundAccount(stakeUser.publicKey);
+      p = await setupPool([computeLeaf(stakeUser.publicKey, amount)]);
+      await warpTo(p.start + 60);
+      await claimFor(p, stakeUser, amount);
+      await warpTo(p.start + 3 * SECONDS_PER_DAY + 60);
+      await snapshotPool(p);
+      await program.methods.initiateTermination()
+        .accounts({ admin: admin.publicKey, poolState: p.poolState, approval: null })
+        .signers([admin]).rpc();
+      await warpTo(p.start + 3 * SECONDS_PER_DAY + 60 + TIMELOCK_SECONDS);
+    });
+
+    it("counts the funded stake", async () => {
+      const state = await program.account.poolState.fetch(p.poolState);
+      expect(state.fundedStakers).to.equal(1);
+    });
+
+    it("rejects the drain when total_staked reads no open stake but the counter says one remains", async () => {
+      const state = await program.account.poolState.fetch(p.poolState);
+      const honest = BigInt(state.totalStaked.toString());
+      // open_staked = total_staked + total_airdrop_claimed - airdrop_cap, so this reads zero
+      await setTotalStaked(p.poolState, honest - BigInt(amount.toString()));
+      await expectError(drain, "OpenStakesUncounted");
+
+      await setTotalStaked(p.poolState, honest);
+      const adminBefore = await tokenBalance(p.adminAta);
+      await drain();
+      expect(await tokenBalance(p.adminAta) > adminBefore).to.be.true;
+    });
+  });
+
+  describe("close_crank_record", () => {
+    const cranker = Keypair.generate();
+    let p: any;
+    let crankRecord: PublicKey;
+
+    const close = () =>
+      program.methods.closeCrankRecord()
+        .accounts({ signer: cranker.publicKey, poolState: p.poolState })
+        .signers([cranker]).rpc();
+
+    before(async () => {
+      await fundAccount(cranker.publicKey);
+      p = await setupPool([computeLeaf(Keypair.generate().publicKey, new BN(1))]);
+      [crankRecord] = PublicKey.findProgramAddressSync(
+        [Buffer.from("crank"), p.poolState.toBuffer(), cranker.publicKey.toBuffer()], program.programId
+      );
+      await warpTo(p.start + SECONDS_PER_DAY + 60);
+      await snapshotPool(p, cranker);
+    });
+
+    it("rejects closing while program days remain unsnapshotted", async () => {
+      await expectError(close, "CrankRecordInUse");
+      expect(await context.banksClient.getAccount(crankRecord)).to.not.be.null;
+    });
+
+    it("closes the record once every day is snapshotted, returning its rent", async () => {
+      await warpTo(p.start + (TOTAL_DAYS + 1) * SECONDS_PER_DAY + 60);
+      await snapshotPool(p, cranker);
+      expect((await program.account.poolState.fetch(p.poolState)).snapshotCount).to.equal(TOTAL_DAYS);
+
+      const before = (await context.banksClient.getAccount(cranker.publicKey)).lamports;
+      await close();
+      expect(await context.banksClient.getAccount(crankRecord)).to.be.null;
+      expect((await context.banksClient.getAccount(cranker.publicKey)).lamports > before).to.be.true;
+    });
   });
 
 });